@@ -7,6 +7,12 @@ pub trait AuthDB {
     fn commit(&mut self, index: usize);
 
     fn flush_all(&mut self) {}
+
+    /// The root as of the latest commit, if the algorithm exposes one.
+    fn root(&self) -> Option<Vec<u8>> {
+        None
+    }
+
     fn backend(&self) -> Option<&dyn KeyValueDB>;
 }
 
@@ -0,0 +1,24 @@
+extern crate blake3;
+extern crate ethereum_types;
+extern crate hash_db;
+extern crate plain_hasher;
+
+use ethereum_types::H256;
+use hash_db::Hasher;
+use plain_hasher::PlainHasher;
+
+/// Concrete `Hasher` impl for the BLAKE3 hash
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Blake3Hasher;
+impl Hasher for Blake3Hasher {
+    type Out = H256;
+    type StdHasher = PlainHasher;
+    const LENGTH: usize = 32;
+    fn hash(x: &[u8]) -> Self::Out {
+        H256(*blake3::hash(x).as_bytes())
+    }
+}
+
+pub fn blake3<T: AsRef<[u8]>>(s: T) -> H256 {
+    Blake3Hasher::hash(s.as_ref())
+}
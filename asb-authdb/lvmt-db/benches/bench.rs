@@ -39,7 +39,7 @@ fn bench_prove(b: &mut Bencher) {
         TypeDepths::USIZE,
         true,
     ));
-    let mut db = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
 
     let mut epoch_root_dict = HashMap::new();
 
@@ -85,7 +85,7 @@ fn bench_verify(b: &mut Bencher) {
         TypeDepths::USIZE,
         true,
     ));
-    let mut db = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
 
     let mut epoch_root_dict = HashMap::new();
 
@@ -122,7 +122,7 @@ fn bench_verify(b: &mut Bencher) {
 
     b.iter(|| {
         let i = rng.gen();
-        LvmtDB::verify(
+        <LvmtDB>::verify(
             &Key(vec![1, 2, i, 0]),
             &proofs[i as usize],
             |epoch| epoch_root_dict[&epoch],
@@ -131,3 +131,22 @@ fn bench_verify(b: &mut Bencher) {
         .unwrap();
     })
 }
+
+#[bench]
+fn bench_prove_batch(b: &mut Bencher) {
+    let backend = lvmt_db::storage::test_kvdb(NUM_COLS);
+    let pp = Arc::new(AMTParams::<Pairing>::from_dir(
+        "./pp",
+        TypeDepths::USIZE,
+        true,
+    ));
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
+
+    for epoch in 0..=255 {
+        db.set(&Key(vec![1, 2, epoch, 0]), vec![1, 2, epoch, 5].into());
+        db.commit(epoch as u64).unwrap();
+    }
+
+    let keys: Vec<Key> = (0..=255).map(|i| Key(vec![1, 2, i, 0])).collect();
+    b.iter(|| black_box(db.prove_batch(&keys[..64]).unwrap()))
+}
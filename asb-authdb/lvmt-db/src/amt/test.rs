@@ -76,6 +76,34 @@ fn test_amt() {
     test_all(&mut amt, &pp, "sibling pair");
 }
 
+#[test]
+fn test_multi_proof() {
+    let db = crate::storage::test_db_col();
+
+    const DEPTHS: usize = TestConfig::DEPTHS;
+    const LENGTH: usize = 1 << DEPTHS;
+
+    let pp = Arc::new(AMTParams::<Pairing>::from_dir("./pp", DEPTHS, true));
+
+    let mut amt = TestTree::new(64, db, pp.clone(), Some(NodeIndex::<TestDepths>::root()));
+    amt.set_commitment(&Default::default());
+    for i in 0..LENGTH {
+        *amt.write_versions(i) += i as u64 * 3 + 1;
+    }
+
+    for indices in [vec![5], vec![0, 1], vec![0, LENGTH / 2], vec![3, 9, 27, 60, 61]] {
+        let proof = amt.prove_multi(&indices).unwrap();
+        let openings: Vec<(usize, Fr<Pairing>)> =
+            indices.iter().map(|i| (*i, amt.get(*i).as_fr())).collect();
+        assert!(proof.len() < indices.len() * DEPTHS || indices.len() == 1);
+        assert!(TestTree::verify_multi(&openings, amt.commitment(), &proof, &pp));
+
+        let mut wrong_openings = openings.clone();
+        wrong_openings[0].1 += Fr::<Pairing>::from(1u64);
+        assert!(!TestTree::verify_multi(&wrong_openings, amt.commitment(), &proof, &pp));
+    }
+}
+
 #[test]
 fn test_one() {
     let db = crate::storage::test_db_col();
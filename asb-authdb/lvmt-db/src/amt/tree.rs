@@ -6,8 +6,9 @@ use crate::crypto::{
     AMTParams, TypeUInt,
 };
 use crate::serde::{MyFromBytes, MyToBytes};
-use crate::storage::access::PUT_MODE;
+use crate::metrics::PutKind;
 use crate::storage::{DBAccess, DBColumn, LayoutTrait};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 pub trait AMTConfigTrait {
@@ -123,14 +124,9 @@ impl<C: AMTConfigTrait> AMTree<C> {
     }
 
     pub fn flush(&mut self) -> G1<C::PE> {
-        *PUT_MODE.lock_mut().unwrap() = 0;
-        self.data.flush_cache();
-
-        *PUT_MODE.lock_mut().unwrap() = 1;
-        self.inner_nodes.flush_cache();
-
-        *PUT_MODE.lock_mut().unwrap() = 2;
-        self.subtree_roots.flush_cache();
+        self.data.flush_cache(PutKind::Data);
+        self.inner_nodes.flush_cache(PutKind::InnerNode);
+        self.subtree_roots.flush_cache(PutKind::SubtreeRoot);
 
         self.dirty = false;
         self.commitment.unwrap().clone()
@@ -225,6 +221,75 @@ impl<C: AMTConfigTrait> AMTree<C> {
         }
         return true;
     }
+
+    /// Proves the values at multiple indices at once. The proof consists of the inner nodes whose
+    /// subtrees contain none of `indices`, so the nodes shared by their paths appear only once.
+    pub fn prove_multi(&mut self, indices: &[usize]) -> Option<AMTProof<G1<C::PE>>> {
+        if !self.can_prove() {
+            return None;
+        }
+        let answers = Self::multi_proof_nodes(indices)
+            .into_iter()
+            .map(|(node_index, _)| self.inner_nodes.get_mut(&node_index).clone())
+            .collect();
+        Some(answers)
+    }
+
+    pub fn verify_multi(
+        openings: &[(usize, Fr<C::PE>)],
+        commitment: &G1<C::PE>,
+        proof: &AMTProof<G1<C::PE>>,
+        pp: &AMTParams<C::PE>,
+    ) -> bool {
+        let indices: Vec<usize> = openings.iter().map(|(index, _)| *index).collect();
+        let distinct = indices.iter().collect::<BTreeSet<_>>().len() == indices.len();
+        if !distinct || indices.iter().any(|index| *index >= C::LENGTH) {
+            return false;
+        }
+
+        let proof_nodes = Self::multi_proof_nodes(&indices);
+        if proof_nodes.len() != proof.len() {
+            return false;
+        }
+
+        let self_indents: G1<C::PE> = openings
+            .iter()
+            .map(|&(index, value)| pp.get_commitments(index).mul(value.into()))
+            .sum();
+        let others: G1<C::PE> = proof.iter().map(|node| node.commitment).sum();
+        if *commitment != self_indents + &others {
+            return false;
+        }
+
+        for (node, (node_index, index)) in proof.iter().zip(proof_nodes) {
+            let verification = *pp.get_sibling_verification(node_index.depth(), index);
+            if C::PE::pairing(node.commitment, pp.g2()) != C::PE::pairing(node.proof, verification)
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // The roots of the maximal subtrees containing none of `indices`, ordered by (depth, index).
+    // Each one comes with an index in its sibling subtree, which locates its verification key.
+    fn multi_proof_nodes(indices: &[usize]) -> Vec<(NodeIndex<C::Height>, usize)> {
+        let mut ancestors = BTreeMap::new();
+        for &index in indices {
+            let leaf = NodeIndex::<C::Height>::leaf(bitreverse(index, C::DEPTHS));
+            for height in 0..C::DEPTHS {
+                let ancestor = leaf.to_ancestor(height);
+                ancestors
+                    .entry((ancestor.depth(), ancestor.index()))
+                    .or_insert(index);
+            }
+        }
+        ancestors
+            .iter()
+            .filter(|((depth, node), _)| !ancestors.contains_key(&(*depth, node ^ 1)))
+            .map(|(&(depth, node), &index)| (NodeIndex::new(depth, node ^ 1), index))
+            .collect()
+    }
 }
 
 #[inline]
@@ -0,0 +1,292 @@
+use lvmt_db::crypto::{
+    amtp_file_name,
+    export::{Bls12_381, Bn254, G1Aff, G2Aff, PairingEngine},
+    pp_file_name, AMTParams, PowerTau,
+};
+use ppot2ark::{from_challenge, Adapter, Bn256, CeremonyParams};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "asb-pp",
+    about = "Manage the public parameters of LVMT and AMT",
+    rename_all = "kebab-case"
+)]
+enum Command {
+    #[structopt(about = "Generate the powers of tau with an insecure setup (for development only)")]
+    Setup {
+        #[structopt(long, parse(try_from_str = parse_curve), default_value = "bn254")]
+        curve: Curve,
+
+        #[structopt(long)]
+        depth: usize,
+
+        #[structopt(long, default_value = "./pp")]
+        dir: String,
+
+        #[structopt(long, help = "Number of threads, use all the cores by default")]
+        threads: Option<usize>,
+
+        #[structopt(long, help = "Overwrite the existing powers of tau")]
+        force: bool,
+
+        #[structopt(long, help = "Also derive the AMT parameters")]
+        amt_params: bool,
+    },
+
+    #[structopt(about = "Import the BN254 powers of tau from a perpetual powers of tau challenge")]
+    Import {
+        #[structopt(long)]
+        challenge: String,
+
+        #[structopt(long)]
+        depth: usize,
+
+        #[structopt(long, default_value = "./pp")]
+        dir: String,
+
+        #[structopt(long, help = "Overwrite the existing powers of tau")]
+        force: bool,
+
+        #[structopt(long, help = "Also derive the AMT parameters")]
+        amt_params: bool,
+    },
+
+    #[structopt(about = "Check that the G1 and G2 powers of tau are consistent")]
+    Verify {
+        #[structopt(long, parse(try_from_str = parse_curve), default_value = "bn254")]
+        curve: Curve,
+
+        #[structopt(long)]
+        depth: usize,
+
+        #[structopt(long, default_value = "./pp")]
+        dir: String,
+    },
+
+    #[structopt(about = "List the public parameters in a directory")]
+    Info {
+        #[structopt(long, default_value = "./pp")]
+        dir: String,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Curve {
+    BN254,
+    BLS12_381,
+}
+
+fn parse_curve(s: &str) -> Result<Curve, String> {
+    return Ok(match s {
+        "bn254" => Curve::BN254,
+        "bls12-381" => Curve::BLS12_381,
+        _ => {
+            return Err("Unrecognized curve".into());
+        }
+    });
+}
+
+const MAX_DEPTH: usize = 28;
+
+struct ProgressBar {
+    total: usize,
+    done: AtomicUsize,
+    printed: AtomicUsize,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 50;
+
+    fn new(total: usize) -> Self {
+        Self {
+            total,
+            done: AtomicUsize::new(0),
+            printed: AtomicUsize::new(0),
+        }
+    }
+
+    fn inc(&self, delta: usize) {
+        let done = self.done.fetch_add(delta, Ordering::Relaxed) + delta;
+        let percent = done * 100 / self.total;
+        // Only the thread advancing the percentage redraws the bar.
+        if self.printed.fetch_max(percent, Ordering::Relaxed) < percent {
+            let filled = percent * Self::WIDTH / 100;
+            eprint!(
+                "\r[{}{}] {:>3}%",
+                "#".repeat(filled),
+                " ".repeat(Self::WIDTH - filled),
+                percent
+            );
+            let _ = std::io::stderr().flush();
+            if percent == 100 {
+                eprintln!();
+            }
+        }
+    }
+}
+
+fn pp_file<PE: PairingEngine>(dir: &str, depth: usize) -> String {
+    format!("{}/{}", dir, pp_file_name::<PE>(depth))
+}
+
+// Exits if the powers of tau exist and are not to be overwritten.
+fn check_overwrite(file: &str, force: bool) {
+    if !force && Path::new(file).exists() {
+        eprintln!("{} already exists, use --force to overwrite", file);
+        std::process::exit(exitcode::CANTCREAT);
+    }
+}
+
+// The AMT parameters derived from the previous powers of tau are loaded as they are, so they are
+// removed once the powers of tau are rewritten.
+fn remove_amt_params<PE: PairingEngine>(dir: &str, depth: usize) {
+    let file = format!("{}/{}", dir, amtp_file_name::<PE>(depth));
+    match fs::remove_file(&file) {
+        Ok(()) => println!("Remove {}, which is derived from the previous powers of tau", file),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            eprintln!("Cannot remove the stale {}: {}", file, e);
+            std::process::exit(exitcode::CANTCREAT);
+        }
+    }
+}
+
+fn setup<PE: PairingEngine>(dir: &str, depth: usize, force: bool) {
+    let file = pp_file::<PE>(dir, depth);
+    check_overwrite(&file, force);
+
+    let start = Instant::now();
+    let progress = ProgressBar::new(2 << depth);
+    let pp = PowerTau::<PE>::setup_with_progress(depth, &|delta| progress.inc(delta));
+    pp.save(&file).unwrap();
+    println!("Write {} in {:.1?}", file, start.elapsed());
+    remove_amt_params::<PE>(dir, depth);
+}
+
+fn import(challenge: &str, dir: &str, depth: usize, force: bool) {
+    let file = pp_file::<Bn254>(dir, depth);
+    check_overwrite(&file, force);
+
+    let params = CeremonyParams::<Bn256>::new(28, 20);
+    let accumulator = from_challenge(challenge, depth, &params);
+    let g1: Vec<G1Aff<Bn254>> = (0..(1 << depth))
+        .map(|idx| accumulator.tau_powers_g1[idx].adapt())
+        .collect();
+    let g2: Vec<G2Aff<Bn254>> = (0..(1 << depth))
+        .map(|idx| accumulator.tau_powers_g2[idx].adapt())
+        .collect();
+
+    PowerTau(g1, g2).save(&file).unwrap();
+    println!("Write {}", file);
+    remove_amt_params::<Bn254>(dir, depth);
+}
+
+fn derive_amt_params<PE: PairingEngine>(dir: &str, depth: usize) {
+    let start = Instant::now();
+    let _ = AMTParams::<PE>::from_dir(dir, depth, false);
+    println!(
+        "Write {}/{} in {:.1?}",
+        dir,
+        amtp_file_name::<PE>(depth),
+        start.elapsed()
+    );
+}
+
+fn verify<PE: PairingEngine>(dir: &str, depth: usize) {
+    let file = pp_file::<PE>(dir, depth);
+    let result = PowerTau::<PE>::from_file(&file, true).and_then(|pp| {
+        if pp.depth() != depth {
+            println!("Warning: {} contains powers of depth {}", file, pp.depth());
+        }
+        pp.verify()
+    });
+    match result {
+        Ok(()) => println!("{}: OK", file),
+        Err(e) => {
+            println!("{}: {}", file, e);
+            std::process::exit(exitcode::DATAERR);
+        }
+    }
+}
+
+fn info<PE: PairingEngine>(dir: &str, curve: Curve) {
+    for depth in 1..=MAX_DEPTH {
+        for (kind, name) in [
+            ("powers of tau", pp_file_name::<PE>(depth)),
+            ("AMT params", amtp_file_name::<PE>(depth)),
+        ] {
+            let path = Path::new(dir).join(&name);
+            if let Ok(metadata) = path.metadata() {
+                println!(
+                    "{:<48} {:?}, depth {:>2}, {}, {:.1} MB",
+                    name,
+                    curve,
+                    depth,
+                    kind,
+                    metadata.len() as f64 / (1 << 20) as f64
+                );
+            }
+        }
+    }
+}
+
+macro_rules! with_curve {
+    ($curve: expr, $func: ident ( $($args: expr),* )) => {
+        match $curve {
+            Curve::BN254 => $func::<Bn254>($($args),*),
+            Curve::BLS12_381 => $func::<Bls12_381>($($args),*),
+        }
+    };
+}
+
+fn main() {
+    match Command::from_args() {
+        Command::Setup {
+            curve,
+            depth,
+            dir,
+            threads,
+            force,
+            amt_params,
+        } => {
+            assert!(depth <= MAX_DEPTH, "Depth should not exceed {}", MAX_DEPTH);
+            if let Some(threads) = threads {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()
+                    .unwrap();
+            }
+            println!("Warning: the insecure setup is only for development and benchmarking.");
+            with_curve!(curve, setup(&dir, depth, force));
+            if amt_params {
+                with_curve!(curve, derive_amt_params(&dir, depth));
+            }
+        }
+        Command::Import {
+            challenge,
+            depth,
+            dir,
+            force,
+            amt_params,
+        } => {
+            assert!(depth <= MAX_DEPTH, "Depth should not exceed {}", MAX_DEPTH);
+            import(&challenge, &dir, depth, force);
+            if amt_params {
+                derive_amt_params::<Bn254>(&dir, depth);
+            }
+        }
+        Command::Verify { curve, depth, dir } => {
+            with_curve!(curve, verify(&dir, depth));
+        }
+        Command::Info { dir } => {
+            info::<Bn254>(&dir, Curve::BN254);
+            info::<Bls12_381>(&dir, Curve::BLS12_381);
+        }
+    }
+}
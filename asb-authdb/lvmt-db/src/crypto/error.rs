@@ -12,5 +12,10 @@ error_chain! {
             description("In consistent length between expected params and real params")
             display("In consistent length between expected params and real params")
         }
+
+        InconsistentPowers {
+            description("The G1 and G2 powers are not the powers of the same tau")
+            display("The G1 and G2 powers are not the powers of the same tau")
+        }
     }
 }
@@ -5,7 +5,7 @@
 
 pub use ark_bls12_381::Bls12_381;
 pub use ark_bn254::Bn254;
-pub use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+pub use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
 pub use ark_ff::{
     utils::k_adicity, BigInteger, FftField, Field, FpParameters, FromBytes, One, PrimeField,
     ToBytes, UniformRand, Zero,
@@ -1,7 +1,7 @@
 use super::error;
 use super::export::{
-    AffineCurve, CanonicalDeserialize, CanonicalSerialize, Fr, G1Aff, G2Aff, PairingEngine,
-    ProjectiveCurve, SerializationError, UniformRand, G1, G2,
+    AffineCurve, CanonicalDeserialize, CanonicalSerialize, Fr, FrInt, G1Aff, G2Aff, PairingEngine,
+    PrimeField, ProjectiveCurve, SerializationError, UniformRand, VariableBaseMSM, G1, G2,
 };
 use super::pp_file_name;
 use ark_ff::utils::k_adicity;
@@ -15,13 +15,18 @@ use std::path::Path;
 #[derive(CanonicalDeserialize, CanonicalSerialize)]
 pub struct PowerTau<PE: PairingEngine>(pub Vec<G1Aff<PE>>, pub Vec<G2Aff<PE>>);
 
-fn power_tau<'a, G: AffineCurve>(gen: &'a G, tau: &'a G::ScalarField, length: usize) -> Vec<G> {
+fn power_tau<'a, G: AffineCurve>(
+    gen: &'a G,
+    tau: &'a G::ScalarField,
+    length: usize,
+    progress: &(dyn Fn(usize) + Sync),
+) -> Vec<G> {
     let gen: G::Projective = gen.into_projective();
     (0usize..length)
         .into_par_iter()
         .chunks(1024)
         .map(|x| {
-            ProjectiveCurve::batch_normalization_into_affine(
+            let chunk = ProjectiveCurve::batch_normalization_into_affine(
                 &x.iter()
                     .map(|idx| {
                         let mut gen = gen.clone();
@@ -29,7 +34,9 @@ fn power_tau<'a, G: AffineCurve>(gen: &'a G, tau: &'a G::ScalarField, length: us
                         gen
                     })
                     .collect::<Vec<G::Projective>>()[..],
-            )
+            );
+            progress(chunk.len());
+            chunk
         })
         .flatten()
         .collect()
@@ -38,29 +45,95 @@ fn power_tau<'a, G: AffineCurve>(gen: &'a G, tau: &'a G::ScalarField, length: us
 impl<PE: PairingEngine> PowerTau<PE> {
     #[cfg(test)]
     fn setup_with_tau(tau: Fr<PE>, depth: usize) -> PowerTau<PE> {
-        Self::setup_inner(Some(tau), depth)
+        Self::setup_inner(Some(tau), depth, &|_| ())
     }
 
     pub fn setup(depth: usize) -> PowerTau<PE> {
-        Self::setup_inner(None, depth)
+        Self::setup_inner(None, depth, &|_| ())
     }
 
-    fn setup_inner(tau: Option<Fr<PE>>, depth: usize) -> PowerTau<PE> {
+    /// Same as `setup`, but reports the number of newly computed powers (of both G1 and G2, so
+    /// `2 << depth` in total) to `progress`. It can be called from multiple threads.
+    pub fn setup_with_progress(depth: usize, progress: &(dyn Fn(usize) + Sync)) -> PowerTau<PE> {
+        Self::setup_inner(None, depth, progress)
+    }
+
+    fn setup_inner(
+        tau: Option<Fr<PE>>,
+        depth: usize,
+        progress: &(dyn Fn(usize) + Sync),
+    ) -> PowerTau<PE> {
         let random_tau = Fr::<PE>::rand(&mut rand::thread_rng());
         let tau = tau.unwrap_or(random_tau);
 
         let gen1 = G1Aff::<PE>::prime_subgroup_generator();
         let gen2 = G2Aff::<PE>::prime_subgroup_generator();
 
-        let g1pp: Vec<G1Aff<PE>> = power_tau(&gen1, &tau, 1 << depth);
-        let g2pp: Vec<G2Aff<PE>> = power_tau(&gen2, &tau, 1 << depth);
+        let g1pp: Vec<G1Aff<PE>> = power_tau(&gen1, &tau, 1 << depth, progress);
+        let g2pp: Vec<G2Aff<PE>> = power_tau(&gen2, &tau, 1 << depth, progress);
 
         return PowerTau(g1pp, g2pp);
     }
 
-    fn from_dir_inner(file: &str, expected_depth: usize) -> Result<PowerTau<PE>, error::Error> {
+    /// Loads the powers from `file`. The curve points are only checked to be in the prime order
+    /// subgroup if `checked` is set, which is much slower.
+    pub fn from_file(file: &str, checked: bool) -> Result<PowerTau<PE>, error::Error> {
         let buffer = File::open(file)?;
-        let pp: PowerTau<PE> = CanonicalDeserialize::deserialize_unchecked(buffer)?;
+        Ok(if checked {
+            CanonicalDeserialize::deserialize(buffer)?
+        } else {
+            CanonicalDeserialize::deserialize_unchecked(buffer)?
+        })
+    }
+
+    pub fn save(&self, file: &str) -> Result<(), error::Error> {
+        create_dir_all(Path::new(file).parent().unwrap())?;
+        let buffer = File::create(file)?;
+        self.serialize_uncompressed(&buffer)?;
+        Ok(())
+    }
+
+    pub fn depth(&self) -> usize {
+        k_adicity(2, self.0.len()) as usize
+    }
+
+    /// Checks that the G1 and G2 vectors start from the generators and are the powers of the same
+    /// tau. Each vector is folded by a random linear combination, so the check costs four
+    /// pairings and four multi-scalar multiplications.
+    pub fn verify(&self) -> Result<(), error::Error> {
+        let (g1pp, g2pp) = (&self.0, &self.1);
+        if g1pp.len() != g2pp.len() || !g1pp.len().is_power_of_two() {
+            return Err(error::ErrorKind::InconsistentLength.into());
+        }
+        if g1pp[0] != G1Aff::<PE>::prime_subgroup_generator()
+            || g2pp[0] != G2Aff::<PE>::prime_subgroup_generator()
+        {
+            return Err(error::ErrorKind::InconsistentPowers.into());
+        }
+        if g1pp.len() == 1 {
+            return Ok(());
+        }
+
+        let n = g1pp.len() - 1;
+        let rng = &mut rand::thread_rng();
+        let scalars: Vec<FrInt<PE>> = (0..n).map(|_| Fr::<PE>::rand(rng).into_repr()).collect();
+
+        let g1_low = VariableBaseMSM::multi_scalar_mul(&g1pp[..n], &scalars);
+        let g1_high = VariableBaseMSM::multi_scalar_mul(&g1pp[1..], &scalars);
+        let g2_low = VariableBaseMSM::multi_scalar_mul(&g2pp[..n], &scalars);
+        let g2_high = VariableBaseMSM::multi_scalar_mul(&g2pp[1..], &scalars);
+
+        // e(tau * g1_low, g2) == e(g1_low, tau * g2) and e(g1, tau * g2_low) == e(tau * g1, g2_low)
+        if PE::pairing(g1_high, g2pp[0]) != PE::pairing(g1_low, g2pp[1])
+            || PE::pairing(g1pp[0], g2_high) != PE::pairing(g1pp[1], g2_low)
+        {
+            return Err(error::ErrorKind::InconsistentPowers.into());
+        }
+        Ok(())
+    }
+
+    fn from_dir_inner(file: &str, expected_depth: usize) -> Result<PowerTau<PE>, error::Error> {
+        let pp = Self::from_file(file, false)?;
         let (g1_len, g2_len) = (pp.0.len(), pp.1.len());
         let depth = k_adicity(2, g1_len) as usize;
         if g1_len != g2_len {
@@ -79,7 +152,7 @@ impl<PE: PairingEngine> PowerTau<PE> {
     pub fn from_dir(dir: &str, expected_depth: usize) -> PowerTau<PE> {
         let file = &format!("{}/{}", dir, pp_file_name::<PE>(expected_depth));
         Self::from_dir_inner(file, expected_depth).expect(&format!(
-            "Fail to load public parameters for {} at depth {}, read TODO to generate",
+            "Fail to load public parameters for {} at depth {}, run `asb-pp setup` or `asb-pp import` to generate",
             std::any::type_name::<PE>(),
             expected_depth
         ))
@@ -91,9 +164,7 @@ impl<PE: PairingEngine> PowerTau<PE> {
             Ok(pp) => pp,
             Err(_) => {
                 let pp = Self::setup(expected_depth);
-                create_dir_all(Path::new(file).parent().unwrap()).unwrap();
-                let buffer = File::create(file).unwrap();
-                pp.serialize_uncompressed(&buffer).unwrap();
+                pp.save(file).unwrap();
                 pp
             }
         }
@@ -118,6 +189,17 @@ fn test_partial_load() {
     assert_eq!(small_pp.1[..], large_pp.1[..(small_pp.1.len())]);
 }
 
+#[test]
+fn test_verify() {
+    type Pairing = super::export::Pairing;
+
+    let mut pp = PowerTau::<Pairing>::setup(4);
+    pp.verify().unwrap();
+
+    pp.1[3] = pp.1[2];
+    assert!(pp.verify().is_err());
+}
+
 #[test]
 fn test_parallel_build() {
     use crate::crypto::export::{Pairing, ProjectiveCurve};
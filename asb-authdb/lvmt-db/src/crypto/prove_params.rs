@@ -41,6 +41,11 @@ impl<PE: PairingEngine> AMTParams<PE> {
 
     #[cfg(not(feature = "no_cache_pow"))]
     pub fn get_idents_pow(&self, index: usize, power: &FrInt<PE>) -> G1<PE> {
+        if let Some(answer) = Self::sum_cached_pow(&self.indents_cache.read().unwrap()[index], power)
+        {
+            return answer;
+        }
+
         let indents_cache = &mut *self.indents_cache.write().unwrap();
         let caches = &mut indents_cache[index];
         let mut answer = G1::<PE>::zero();
@@ -70,6 +75,13 @@ impl<PE: PairingEngine> AMTParams<PE> {
 
     #[cfg(not(feature = "no_cache_pow"))]
     pub fn get_quotient_pow(&self, depth: usize, index: usize, power: &FrInt<PE>) -> G1<PE> {
+        if let Some(answer) = Self::sum_cached_pow(
+            &self.quotients_cache.read().unwrap()[depth - 1][index],
+            power,
+        ) {
+            return answer;
+        }
+
         let quotient_cache = &mut *self.quotients_cache.write().unwrap();
         let caches = &mut quotient_cache[depth - 1][index];
         let mut answer = G1::<PE>::zero();
@@ -93,6 +105,22 @@ impl<PE: PairingEngine> AMTParams<PE> {
         answer
     }
 
+    // Only takes the read lock, so the concurrent commitment updates do not contend on the cache
+    // once it is warm. Returns `None` if some power of two has not been cached yet.
+    #[cfg(not(feature = "no_cache_pow"))]
+    fn sum_cached_pow(caches: &BTreeMap<usize, G1<PE>>, power: &FrInt<PE>) -> Option<G1<PE>> {
+        let mut answer = G1::<PE>::zero();
+        for (dword_idx, n) in power.as_ref().iter().enumerate() {
+            let mut limb: u64 = *n;
+            while limb.trailing_zeros() < 64 {
+                let bit_idx = limb.trailing_zeros() as usize;
+                answer += caches.get(&(dword_idx * 64 + bit_idx))?;
+                limb ^= 1 << bit_idx;
+            }
+        }
+        Some(answer)
+    }
+
     #[cfg(feature = "no_cache_pow")]
     pub fn get_quotient_pow(&self, depth: usize, index: usize, power: &FrInt<PE>) -> G1<PE> {
         self.quotients[depth - 1][index].mul(power)
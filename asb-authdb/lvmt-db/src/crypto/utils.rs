@@ -5,7 +5,7 @@ use std::collections::hash_map::DefaultHasher;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 
-pub trait TypeUInt: Copy + Eq + Hash + Debug + Sized {
+pub trait TypeUInt: Copy + Eq + Hash + Debug + Sized + Send + Sync + 'static {
     const USIZE: usize;
 }
 
@@ -26,6 +26,13 @@ type_uint! {
     pub struct TypeDepths(DEPTHS);
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Height<const N: usize>;
+
+impl<const N: usize> TypeUInt for Height<N> {
+    const USIZE: usize = N;
+}
+
 pub(crate) fn type_hash<T: Any>() -> String {
     let type_name = std::any::type_name::<T>().to_string();
     let mut s = DefaultHasher::new();
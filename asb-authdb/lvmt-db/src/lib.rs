@@ -13,12 +13,13 @@ pub mod crypto;
 mod enable_log;
 pub mod lvmt_db;
 pub mod merkle;
+pub mod metrics;
 pub mod multi_layer_amt;
 pub mod serde;
 pub mod single_amt;
 pub mod storage;
 
-pub use crate::lvmt_db::{LvmtDB, LvmtRoot, Proof};
+pub use crate::lvmt_db::{BatchProof, LvmtDB, LvmtRoot, Proof};
 pub use multi_layer_amt::Key;
 
 #[allow(unused)]
@@ -1,9 +1,8 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
 use std::io::Result;
 use std::sync::{Arc, RwLock};
 
 use ethereum_types::H256;
-use global::Global;
 use hashbrown::hash_map::Entry;
 // use hashbrown::hash_map::Entry;
 use hashbrown::HashMap;
@@ -11,15 +10,20 @@ use keccak_hash::keccak;
 use kvdb::{DBKey, DBOp, DBTransaction, KeyValueDB};
 
 use lvmt_serde_derive::{MyFromBytes, MyToBytes};
+use rayon::prelude::*;
 
 use crate::amt::{AMTData, AMTProof, AMTree};
 use crate::crypto::{
-    export::{Fr, FrInt, G1Aff, G1Projective, G1},
+    export::{
+        AffineCurve, CanonicalSerialize, Fr, FrInt, G1Aff, G1Projective, PairingEngine,
+        ProjectiveCurve, G1,
+    },
     AMTParams, Pairing, TypeDepths, TypeUInt,
 };
-use crate::merkle::{MerkleProof, StaticMerkleTree};
+use crate::merkle::{MerkleMultiProof, MerkleProof, StaticMerkleTree};
+use crate::metrics::LvmtMetrics;
 use crate::multi_layer_amt::{
-    AMTConfig, AMTNodeIndex, EpochPosition, Key, Node, TreeName, VerInfo, VersionTree,
+    AMTConfig, AMTNodeIndex, EpochPosition, Key, LvmtConfig, Node, TreeName, VerInfo, VersionTree,
 };
 use crate::serde::{MyFromBytes, MyToBytes};
 use crate::storage::DBColumn;
@@ -29,14 +33,10 @@ const COL_KEY_NEW: u32 = COL_VER_TREE + 1;
 const COL_MERKLE: u32 = COL_KEY_NEW + 1;
 pub const NUM_COLS: u32 = COL_MERKLE + 1;
 
-pub static INC_KEY_LEVEL_SUM: Global<u64> = Global::INIT;
-pub static INC_KEY_COUNT: Global<u64> = Global::INIT;
-pub static INC_TREE_COUNT: Global<u64> = Global::INIT;
-
-pub struct LvmtDB {
+pub struct LvmtDB<C: LvmtConfig = AMTConfig> {
     pub kvdb: Arc<dyn KeyValueDB>,
 
-    version_tree: VersionTree,
+    version_tree: VersionTree<C>,
     db_key: DBColumn,
     db_merkle: DBColumn,
 
@@ -44,13 +44,18 @@ pub struct LvmtDB {
     uncommitted_key_values: Vec<(Key, Box<[u8]>)>,
     dirty_guard: bool,
     only_merkle_root: bool,
+
+    commit_pool: Option<Arc<rayon::ThreadPool>>,
+    metrics: Arc<LvmtMetrics>,
 }
 
+// The commitment is serialized in its affine form for consensus, no matter whether it is stored
+// as a projective point.
 #[derive(Default, Clone, Debug, MyFromBytes, MyToBytes)]
-pub struct TreeValue {
+pub struct TreeValue<G: ProjectiveCurve + MyFromBytes + MyToBytes = G1Projective> {
     pub(crate) key: TreeName,
     pub(crate) version_number: u64,
-    pub(crate) commitment: G1Aff<Pairing>,
+    pub(crate) commitment: G,
 }
 
 #[derive(Default, Clone, Debug, MyFromBytes, MyToBytes)]
@@ -67,40 +72,181 @@ pub struct Value {
     pub(crate) position: EpochPosition,
 }
 
-#[derive(Default)]
-pub struct LevelProof {
+pub struct LevelProof<PE: PairingEngine = Pairing> {
     merkle_epoch: u64,
     merkle_proof: MerkleProof,
-    amt_proof: AMTProof<G1<Pairing>>,
-    commitment: G1<Pairing>,
-    node_fr_int: FrInt<Pairing>,
+    amt_proof: AMTProof<G1<PE>>,
+    commitment: G1<PE>,
+    node_fr_int: FrInt<PE>,
     node_version: u64,
 }
 
+impl<PE: PairingEngine> Default for LevelProof<PE> {
+    fn default() -> Self {
+        Self {
+            merkle_epoch: Default::default(),
+            merkle_proof: Default::default(),
+            amt_proof: Default::default(),
+            commitment: Default::default(),
+            node_fr_int: Default::default(),
+            node_version: Default::default(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AssociateProof {
     value: Option<Vec<u8>>,
     ver_info: VerInfo,
 }
 
-pub type Proof = (AssociateProof, VecDeque<LevelProof>);
+pub type Proof<PE = Pairing> = (AssociateProof, VecDeque<LevelProof<PE>>);
 pub type LvmtRoot = G1Projective;
 
+/// A proof for multiple keys. The AMT openings in the same tree are aggregated into one AMT
+/// multi-proof, and the Merkle leaves in the same epoch are proven by one Merkle multi-proof.
+pub struct BatchProof<PE: PairingEngine = Pairing> {
+    key_proofs: Vec<KeyProof>,
+    tree_proofs: Vec<TreeProof<PE>>,
+    merkle_proofs: Vec<MerkleMultiProof>,
+}
+
+// The positions of the Merkle leaves of a key, from level 0 to the level of the key.
+struct KeyProof {
+    assoc_proof: AssociateProof,
+    positions: Vec<EpochPosition>,
+}
+
+// The opened nodes are sorted by their indices.
+struct TreeProof<PE: PairingEngine> {
+    commitment: G1<PE>,
+    node_fr_ints: Vec<FrInt<PE>>,
+    amt_proof: AMTProof<G1<PE>>,
+}
+
+// The AMT openings grouped by trees and the Merkle leaves grouped by epochs of a batch proof, in
+// the order of first appearance. Both the prover and the verifier derive it from the key proofs.
+struct BatchLayout {
+    trees: Vec<(TreeName, Vec<usize>)>,
+    epochs: Vec<(u64, Vec<u64>)>,
+}
+
+impl BatchLayout {
+    fn new(keys: &[Key], key_proofs: &[KeyProof], depths: usize) -> Self {
+        let mut trees: Vec<(TreeName, BTreeSet<usize>)> = Vec::new();
+        let mut tree_slots: HashMap<TreeName, usize> = HashMap::new();
+        let mut epochs: Vec<(u64, BTreeSet<u64>)> = Vec::new();
+        let mut epoch_slots: HashMap<u64, usize> = HashMap::new();
+
+        for (key, key_proof) in keys.iter().zip(key_proofs.iter()) {
+            for level in 0..=key_proof.assoc_proof.ver_info.level {
+                let name = key.tree_at_level(level, depths);
+                let slot = *tree_slots.entry(name.clone()).or_insert_with(|| {
+                    trees.push((name, BTreeSet::new()));
+                    trees.len() - 1
+                });
+                trees[slot].1.insert(key.index_at_level(level, depths));
+            }
+
+            for position in key_proof.positions.iter() {
+                let slot = *epoch_slots.entry(position.epoch).or_insert_with(|| {
+                    epochs.push((position.epoch, BTreeSet::new()));
+                    epochs.len() - 1
+                });
+                epochs[slot].1.insert(position.position);
+            }
+        }
+
+        Self {
+            trees: trees
+                .into_iter()
+                .map(|(name, indices)| (name, indices.into_iter().collect()))
+                .collect(),
+            epochs: epochs
+                .into_iter()
+                .map(|(epoch, positions)| (epoch, positions.into_iter().collect()))
+                .collect(),
+        }
+    }
+}
+
+const HASH_SIZE: usize = 32;
+const EPOCH_POSITION_SIZE: usize = 16;
+const VER_INFO_SIZE: usize = 10;
+
+fn g1_size<PE: PairingEngine>() -> usize {
+    G1Aff::<PE>::prime_subgroup_generator().serialized_size()
+}
+
+fn fr_int_size<PE: PairingEngine>() -> usize {
+    FrInt::<PE>::default().as_ref().len() * 8
+}
+
+impl AssociateProof {
+    fn size(&self) -> usize {
+        self.value.as_ref().map_or(0, |x| x.len()) + VER_INFO_SIZE
+    }
+}
+
+/// The size of a single-key proof in bytes, with the curve points compressed.
+pub fn proof_size<PE: PairingEngine>(proof: &Proof<PE>) -> usize {
+    let (assoc_proof, level_proofs) = proof;
+    let level_size: usize = level_proofs
+        .iter()
+        .map(|level_proof| {
+            let (merkle_path, _) = &level_proof.merkle_proof;
+            EPOCH_POSITION_SIZE
+                + merkle_path.len() * HASH_SIZE
+                + (level_proof.amt_proof.len() * 2 + 1) * g1_size::<PE>()
+                + fr_int_size::<PE>()
+                + 8
+        })
+        .sum();
+    assoc_proof.size() + level_size
+}
+
+impl<PE: PairingEngine> BatchProof<PE> {
+    /// The size of the proof in bytes, with the curve points compressed.
+    pub fn size(&self) -> usize {
+        let key_size: usize = self
+            .key_proofs
+            .iter()
+            .map(|x| x.assoc_proof.size() + x.positions.len() * EPOCH_POSITION_SIZE)
+            .sum();
+        let tree_size: usize = self
+            .tree_proofs
+            .iter()
+            .map(|x| {
+                (x.amt_proof.len() * 2 + 1) * g1_size::<PE>()
+                    + x.node_fr_ints.len() * fr_int_size::<PE>()
+            })
+            .sum();
+        let merkle_size: usize = self
+            .merkle_proofs
+            .iter()
+            .map(|x| 4 + x.hashes.len() * HASH_SIZE)
+            .sum();
+        key_size + tree_size + merkle_size
+    }
+}
+
 const EPOCH_NUMBER_KEY: [u8; 2] = [0, 0];
 
-impl LvmtDB {
+impl<C: LvmtConfig> LvmtDB<C> {
     // The KeyValueDB requires 3 columns.
     pub fn new(
         backend: Arc<dyn KeyValueDB>,
-        pp: Arc<AMTParams<Pairing>>,
+        pp: Arc<AMTParams<C::PE>>,
         only_merkle_root: bool,
         shard_info: Option<(usize, usize)>,
     ) -> Self {
-        let db_ver_tree = DBColumn::from_kvdb(backend.clone(), COL_VER_TREE);
-        let shard_node = shard_info.map(|(depth, index)| AMTNodeIndex::new(depth, index));
+        let metrics: Arc<LvmtMetrics> = Default::default();
+        let column = |col| DBColumn::from_kvdb(backend.clone(), col).with_metrics(metrics.clone());
+        let db_ver_tree = column(COL_VER_TREE);
+        let shard_node = shard_info.map(|(depth, index)| AMTNodeIndex::<C>::new(depth, index));
         let version_tree = VersionTree::new(db_ver_tree, pp, shard_node);
-        let db_key = DBColumn::from_kvdb(backend.clone(), COL_KEY_NEW);
-        let db_merkle = DBColumn::from_kvdb(backend.clone(), COL_MERKLE);
+        let db_key = column(COL_KEY_NEW);
+        let db_merkle = column(COL_MERKLE);
         let kvdb = backend;
         Self {
             kvdb,
@@ -111,9 +257,26 @@ impl LvmtDB {
             uncommitted_key_values: Vec::new(),
             dirty_guard: false,
             only_merkle_root,
+            commit_pool: None,
+            metrics,
         }
     }
 
+    /// The counters of this instance, shared with all of its storage accesses.
+    pub fn metrics(&self) -> Arc<LvmtMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Runs the version lookups, the commitment updates and the key-value hashing of `commit` in a
+    /// dedicated rayon pool with `threads` threads. Without it, `commit` stays sequential.
+    pub fn set_commit_threads(&mut self, threads: usize) {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Fail to build the commit thread pool");
+        self.commit_pool = Some(Arc::new(pool));
+    }
+
     pub fn get(&self, key: &Key) -> Result<Option<Box<[u8]>>> {
         // assert!(
         //     !self.dirty_guard,
@@ -159,56 +322,19 @@ impl LvmtDB {
         Ok(epoch)
     }
 
-    pub fn commit(&mut self, _epoch: u64) -> Result<(G1Projective, H256)> {
+    pub fn commit(&mut self, _epoch: u64) -> Result<(G1<C::PE>, H256)> {
         let epoch = self.current_epoch()?;
 
-        let kv_num = self.uncommitted_key_values.len();
-        let mut hashes = Vec::with_capacity(kv_num);
-        let mut write_ops = Vec::with_capacity(kv_num);
-
-        for (position, (key, value)) in self.uncommitted_key_values.drain(..).enumerate() {
-            let version: Option<VerInfo> = match self.cache.read().unwrap().get(&key).as_ref() {
-                Some(&(value, _)) => value.clone().map(|x| x.version),
-                None => match self.db_key.get(key.as_ref())? {
-                    None => None,
-                    Some(value) => Some(Value::from_bytes_local(&value)?.version),
-                },
-            };
-
-            let version = self.version_tree.inc_key_ver(&key, version);
-
-            let value = Value {
-                value: value.to_vec(),
-                version,
-                position: EpochPosition {
-                    epoch,
-                    position: position as u64,
-                },
-            };
-            *INC_KEY_COUNT.lock_mut().unwrap() += 1;
-            *INC_KEY_LEVEL_SUM.lock_mut().unwrap() += version.level as u64 + 1;
-
-            write_ops.push(DBOp::Insert {
-                col: 0,
-                key: key.as_ref().into(),
-                value: value.to_bytes_local(),
-            });
-
-            let key_ver_value_hash = keccak(
-                &KeyValue {
-                    key: key.0,
-                    version,
-                    value: value.value,
-                }
-                .to_bytes_consensus(),
-            );
-
-            hashes.push(key_ver_value_hash);
-        }
+        let (mut hashes, write_ops) = if let Some(pool) = self.commit_pool.clone() {
+            pool.install(|| self.commit_key_values_parallel(epoch))?
+        } else {
+            self.commit_key_values(epoch)?
+        };
         self.db_key.write_buffered(DBTransaction { ops: write_ops });
 
         // println!("commit position");
-        let (amt_root, updates) = self.version_tree.commit(epoch, hashes.len() as u64);
+        let key_value_count = hashes.len();
+        let (amt_root, updates) = self.version_tree.commit(epoch, key_value_count as u64);
 
         for (tree, version, commitment) in updates.into_iter() {
             let name_ver_value_hash = keccak(
@@ -221,6 +347,7 @@ impl LvmtDB {
             );
             hashes.push(name_ver_value_hash);
         }
+        self.metrics.record_inc_trees((hashes.len() - key_value_count) as u64);
 
         let merkle_root =
             StaticMerkleTree::dump(self.db_merkle.clone(), epoch, hashes, self.only_merkle_root);
@@ -239,8 +366,106 @@ impl LvmtDB {
         Ok((amt_root, merkle_root))
     }
 
+    fn load_version(&self, key: &Key) -> Result<Option<VerInfo>> {
+        Ok(match self.cache.read().unwrap().get(key).as_ref() {
+            Some(&(value, _)) => value.clone().map(|x| x.version),
+            None => match self.db_key.get(key.as_ref())? {
+                None => None,
+                Some(value) => Some(Value::from_bytes_local(&value)?.version),
+            },
+        })
+    }
+
+    fn commit_key_values(&mut self, epoch: u64) -> Result<(Vec<H256>, Vec<DBOp>)> {
+        let kv_num = self.uncommitted_key_values.len();
+        let mut hashes = Vec::with_capacity(kv_num);
+        let mut write_ops = Vec::with_capacity(kv_num);
+
+        for (position, (key, value)) in std::mem::take(&mut self.uncommitted_key_values)
+            .into_iter()
+            .enumerate()
+        {
+            let version = self.load_version(&key)?;
+            let version = self.version_tree.inc_key_ver(&key, version);
+
+            self.metrics.record_inc_keys(1, version.level as u64 + 1);
+
+            let (write_op, hash) = Self::key_value_entry(epoch, position, key, value, version);
+            write_ops.push(write_op);
+            hashes.push(hash);
+        }
+        Ok((hashes, write_ops))
+    }
+
+    // Same as `commit_key_values`, but must be called inside the commit thread pool.
+    fn commit_key_values_parallel(&mut self, epoch: u64) -> Result<(Vec<H256>, Vec<DBOp>)> {
+        let key_values = std::mem::take(&mut self.uncommitted_key_values);
+
+        let this = &*self;
+        let versions = key_values
+            .par_iter()
+            .map(|(key, _)| this.load_version(key))
+            .collect::<Result<Vec<_>>>()?;
+
+        let versions = self.version_tree.inc_key_vers_in_batch(
+            key_values
+                .iter()
+                .map(|(key, _)| key)
+                .zip(versions.into_iter()),
+        );
+
+        self.metrics.record_inc_keys(
+            versions.len() as u64,
+            versions.iter().map(|x| x.level as u64 + 1).sum(),
+        );
+
+        let (write_ops, hashes) = key_values
+            .into_par_iter()
+            .zip(versions.into_par_iter())
+            .enumerate()
+            .map(|(position, ((key, value), version))| {
+                Self::key_value_entry(epoch, position, key, value, version)
+            })
+            .unzip();
+        Ok((hashes, write_ops))
+    }
+
+    fn key_value_entry(
+        epoch: u64,
+        position: usize,
+        key: Key,
+        value: Box<[u8]>,
+        version: VerInfo,
+    ) -> (DBOp, H256) {
+        let value = Value {
+            value: value.to_vec(),
+            version,
+            position: EpochPosition {
+                epoch,
+                position: position as u64,
+            },
+        };
+
+        let write_op = DBOp::Insert {
+            col: 0,
+            key: key.as_ref().into(),
+            value: value.to_bytes_local(),
+        };
+
+        let key_ver_value_hash = keccak(
+            &KeyValue {
+                key: key.0,
+                version,
+                value: value.value,
+            }
+            .to_bytes_consensus(),
+        );
+
+        (write_op, key_ver_value_hash)
+    }
+
     // TODO: for non-existence proof.
-    pub fn prove(&mut self, key: &Key) -> Result<Proof> {
+    pub fn prove(&mut self, key: &Key) -> Result<Proof<C::PE>> {
         let value = self
             .db_key
             .get(key.as_ref())?
@@ -259,8 +484,8 @@ impl LvmtDB {
         let (merkle_epoch, merkle_proof) = self.prove_merkle(value.position)?;
 
         // AMT Proof
-        let tree_name = key.tree_at_level(ver_info.level);
-        let index = key.index_at_level(ver_info.level);
+        let tree_name = key.tree_at_level(ver_info.level, C::DEPTHS);
+        let index = key.index_at_level(ver_info.level, C::DEPTHS);
         let (commitment, node, amt_proof) = self.prove_amt_node(tree_name.clone(), index);
 
         let mut level_proofs = VecDeque::with_capacity(ver_info.level as usize + 1);
@@ -270,13 +495,13 @@ impl LvmtDB {
             merkle_proof,
             amt_proof,
             commitment,
-            node_fr_int: node.as_fr_int(),
+            node_fr_int: AMTData::<Fr<C::PE>>::as_fr_int(&node),
             node_version: node.key_versions[ver_info.slot_index as usize],
         });
 
         for level in (0..ver_info.level).rev() {
-            let tree_name = key.tree_at_level(level);
-            let index = key.index_at_level(level) as usize;
+            let tree_name = key.tree_at_level(level, C::DEPTHS);
+            let index = key.index_at_level(level, C::DEPTHS) as usize;
 
             let position = self
                 .version_tree
@@ -292,7 +517,7 @@ impl LvmtDB {
                 merkle_proof,
                 amt_proof,
                 commitment,
-                node_fr_int: node.as_fr_int(),
+                node_fr_int: AMTData::<Fr<C::PE>>::as_fr_int(&node),
                 node_version: node.tree_version,
             });
         }
@@ -302,9 +527,9 @@ impl LvmtDB {
 
     pub fn verify<F: Fn(u64) -> H256>(
         key: &Key,
-        proof: &Proof,
+        proof: &Proof<C::PE>,
         epoch_root: F,
-        pp: &AMTParams<Pairing>,
+        pp: &AMTParams<C::PE>,
     ) -> std::result::Result<(), String> {
         let (assoc_proof, level_proofs) = proof;
 
@@ -312,10 +537,10 @@ impl LvmtDB {
 
         // Check the AMT proof
         for (level, level_proof) in level_proofs.iter().enumerate() {
-            let amt_index = key.index_at_level(level as u8);
-            let amt_proof_verified = AMTree::<AMTConfig>::verify(
+            let amt_index = key.index_at_level(level as u8, C::DEPTHS);
+            let amt_proof_verified = AMTree::<C>::verify(
                 amt_index as usize,
-                Fr::<Pairing>::from(level_proof.node_fr_int),
+                Fr::<C::PE>::from(level_proof.node_fr_int),
                 &level_proof.commitment,
                 level_proof.amt_proof.clone(),
                 pp,
@@ -354,14 +579,14 @@ impl LvmtDB {
         for level in 0..level_proofs.len() - 1 {
             let version = level_proofs[level].node_version;
             let level_proof = &level_proofs[level];
-            let tree_name = key.tree_at_level(level as u8 + 1);
+            let tree_name = key.tree_at_level(level as u8 + 1, C::DEPTHS);
             let commitment = level_proofs[level + 1].commitment;
 
             let key_ver_value_hash = keccak(
                 &TreeValue {
                     key: tree_name.clone(),
                     version_number: version,
-                    commitment: commitment.into(),
+                    commitment,
                 }
                 .to_bytes_consensus(),
             );
@@ -410,11 +635,187 @@ impl LvmtDB {
         Ok(())
     }
 
+    /// Proves multiple existent keys at once. See `BatchProof` for how the proofs are shared.
+    pub fn prove_batch(&mut self, keys: &[Key]) -> Result<BatchProof<C::PE>> {
+        let mut key_proofs = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = self
+                .db_key
+                .get(key.as_ref())?
+                .expect("We only support existent proof");
+            let value = Value::from_bytes_local(&value)?;
+            let ver_info = value.version;
+
+            let mut positions = Vec::with_capacity(ver_info.level as usize + 1);
+            for level in 0..ver_info.level {
+                let tree_name = key.tree_at_level(level, C::DEPTHS);
+                let index = key.index_at_level(level, C::DEPTHS);
+                let tree = self.version_tree.get_tree_mut(&tree_name);
+                positions.push(tree.get(index).tree_position);
+            }
+            positions.push(value.position);
+
+            key_proofs.push(KeyProof {
+                assoc_proof: AssociateProof {
+                    value: Some(value.value),
+                    ver_info,
+                },
+                positions,
+            });
+        }
+
+        let layout = BatchLayout::new(keys, &key_proofs, C::DEPTHS);
+
+        let mut tree_proofs = Vec::with_capacity(layout.trees.len());
+        for (name, indices) in layout.trees.iter() {
+            let tree = self.version_tree.get_tree_mut(name);
+            let node_fr_ints = indices
+                .iter()
+                .map(|index| AMTData::<Fr<C::PE>>::as_fr_int(tree.get(*index)))
+                .collect();
+            let amt_proof = tree
+                .prove_multi(indices)
+                .expect("Currently, all the nodes are working in full mode");
+            tree_proofs.push(TreeProof {
+                commitment: tree.commitment().clone(),
+                node_fr_ints,
+                amt_proof,
+            });
+        }
+
+        let merkle_proofs = layout
+            .epochs
+            .iter()
+            .map(|(epoch, positions)| {
+                StaticMerkleTree::new(self.db_merkle.clone(), *epoch).prove_multi(positions)
+            })
+            .collect();
+
+        Ok(BatchProof {
+            key_proofs,
+            tree_proofs,
+            merkle_proofs,
+        })
+    }
+
+    pub fn verify_batch<F: Fn(u64) -> H256>(
+        keys: &[Key],
+        proof: &BatchProof<C::PE>,
+        epoch_root: F,
+        pp: &AMTParams<C::PE>,
+    ) -> std::result::Result<(), String> {
+        if keys.len() != proof.key_proofs.len() {
+            return Err("Inconsistent number of keys".to_string());
+        }
+        for key_proof in proof.key_proofs.iter() {
+            let ver_info = key_proof.assoc_proof.ver_info;
+            if key_proof.positions.len() != ver_info.level as usize + 1 {
+                return Err("Inconsistent number of levels".to_string());
+            }
+            if key_proof.assoc_proof.value.is_none() {
+                return Err("We only support existent proof".to_string());
+            }
+        }
+
+        let layout = BatchLayout::new(keys, &proof.key_proofs, C::DEPTHS);
+        if layout.trees.len() != proof.tree_proofs.len()
+            || layout.epochs.len() != proof.merkle_proofs.len()
+        {
+            return Err("Inconsistent batch layout".to_string());
+        }
+
+        // Check the AMT proofs
+        let mut commitments = HashMap::new();
+        let mut node_fr_ints = HashMap::new();
+        for ((name, indices), tree_proof) in layout.trees.iter().zip(proof.tree_proofs.iter()) {
+            if indices.len() != tree_proof.node_fr_ints.len() {
+                return Err(format!("Inconsistent number of AMT openings at tree {:?}", name));
+            }
+            let openings: Vec<(usize, Fr<C::PE>)> = indices
+                .iter()
+                .zip(tree_proof.node_fr_ints.iter())
+                .map(|(index, fr_int)| (*index, Fr::<C::PE>::from(*fr_int)))
+                .collect();
+            let amt_proof_verified = AMTree::<C>::verify_multi(
+                &openings,
+                &tree_proof.commitment,
+                &tree_proof.amt_proof,
+                pp,
+            );
+            if !amt_proof_verified {
+                return Err(format!("Incorrect AMT proof at tree {:?}", name));
+            }
+
+            commitments.insert(name.clone(), tree_proof.commitment);
+            for (index, fr_int) in indices.iter().zip(tree_proof.node_fr_ints.iter()) {
+                node_fr_ints.insert((name.clone(), *index), *fr_int);
+            }
+        }
+
+        // Compute the Merkle leaves, and check the version consistency.
+        let mut leaves: HashMap<(u64, u64), H256> = HashMap::new();
+        for (key, key_proof) in keys.iter().zip(proof.key_proofs.iter()) {
+            let ver_info = key_proof.assoc_proof.ver_info;
+            for level in 0..=ver_info.level {
+                let tree_name = key.tree_at_level(level, C::DEPTHS);
+                let fr_int = &node_fr_ints[&(tree_name, key.index_at_level(level, C::DEPTHS))];
+
+                let hash = if level == ver_info.level {
+                    let version =
+                        Node::versions_from_fr_int(fr_int, ver_info.slot_index as usize + 1);
+                    if version != ver_info.version {
+                        return Err(format!("Inconsistent version value of key {:?}", key.0));
+                    }
+                    keccak(
+                        &KeyValue {
+                            key: key.0.clone(),
+                            version: ver_info,
+                            value: key_proof.assoc_proof.value.clone().unwrap(),
+                        }
+                        .to_bytes_consensus(),
+                    )
+                } else {
+                    let child_name = key.tree_at_level(level + 1, C::DEPTHS);
+                    let commitment = commitments[&child_name];
+                    keccak(
+                        &TreeValue {
+                            key: child_name,
+                            version_number: Node::versions_from_fr_int(fr_int, 0),
+                            commitment,
+                        }
+                        .to_bytes_consensus(),
+                    )
+                };
+
+                let position = key_proof.positions[level as usize];
+                let leaf = leaves.entry((position.epoch, position.position)).or_insert(hash);
+                if *leaf != hash {
+                    return Err(format!("Conflict Merkle leaves in epoch {}", position.epoch));
+                }
+            }
+        }
+
+        // Check the Merkle proofs
+        for ((epoch, positions), merkle_proof) in
+            layout.epochs.iter().zip(proof.merkle_proofs.iter())
+        {
+            let epoch_leaves: Vec<(u64, H256)> = positions
+                .iter()
+                .map(|position| (*position, leaves[&(*epoch, *position)]))
+                .collect();
+            if !StaticMerkleTree::verify_multi(&epoch_root(*epoch), &epoch_leaves, merkle_proof) {
+                return Err(format!("Incorrect Merkle proof in epoch {}", epoch));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn prove_amt_node(
         &mut self,
         name: TreeName,
         index: usize,
-    ) -> (G1<Pairing>, Node, AMTProof<G1<Pairing>>) {
+    ) -> (G1<C::PE>, Node, AMTProof<G1<C::PE>>) {
         let tree = self.version_tree.get_tree_mut(&name);
 
         let commitment = tree.commitment().clone();
@@ -439,11 +840,18 @@ impl LvmtDB {
 }
 
 pub fn cached_pp(dir: &str) -> Arc<AMTParams<Pairing>> {
-    cached_pp_with_depth(dir, TypeDepths::USIZE)
+    cached_pp_with_depth(dir, TypeDepths::USIZE, false)
 }
 
-pub fn cached_pp_with_depth(dir: &str, depth: usize) -> Arc<AMTParams<Pairing>> {
-    Arc::new(AMTParams::<Pairing>::from_dir(dir, depth, true))
+/// Loads the AMT parameters from `dir`, deriving them from the powers of tau if needed. The powers
+/// of tau are generated by an insecure setup only if `create_mode` is set, otherwise they must
+/// have been prepared by `asb-pp`.
+pub fn cached_pp_with_depth<PE: PairingEngine>(
+    dir: &str,
+    depth: usize,
+    create_mode: bool,
+) -> Arc<AMTParams<PE>> {
+    Arc::new(AMTParams::<PE>::from_dir(dir, depth, create_mode))
 }
 
 #[test]
@@ -456,7 +864,7 @@ fn test_simple_db() {
         TypeDepths::USIZE,
         true,
     ));
-    let mut db = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
 
     let mut epoch_root_dict = HashMap::new();
 
@@ -469,7 +877,8 @@ fn test_simple_db() {
             let key = Key(key.to_vec());
             assert_eq!(value, db.get(&key).unwrap().unwrap().into_vec());
             let proof = db.prove(&key).unwrap();
-            LvmtDB::verify(&key, &proof, |epoch| epoch_root_dict[&epoch], &pp).unwrap();
+            LvmtDB::<AMTConfig>::verify(&key, &proof, |epoch| epoch_root_dict[&epoch], &pp)
+                .unwrap();
         };
 
     for i in 0..=255 {
@@ -530,3 +939,128 @@ fn test_simple_db() {
         );
     }
 }
+
+#[test]
+fn test_parallel_commit() {
+    let pp = Arc::new(AMTParams::<Pairing>::from_dir(
+        "./pp",
+        TypeDepths::USIZE,
+        true,
+    ));
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, None);
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let mut parallel_db: LvmtDB = LvmtDB::new(backend, pp, false, None);
+    parallel_db.set_commit_threads(4);
+
+    for epoch in 0..16u8 {
+        for i in 0..=255u8 {
+            let key = Key(vec![i, epoch % 4, i / 3]);
+            db.set(&key, vec![i, epoch].into());
+            parallel_db.set(&key, vec![i, epoch].into());
+        }
+        let roots = db.commit(epoch as u64).unwrap();
+        let parallel_roots = parallel_db.commit(epoch as u64).unwrap();
+        assert_eq!(roots, parallel_roots);
+    }
+}
+
+#[test]
+fn test_instance_metrics() {
+    let pp = Arc::new(AMTParams::<Pairing>::from_dir(
+        "./pp",
+        TypeDepths::USIZE,
+        true,
+    ));
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, None);
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let idle_db: LvmtDB = LvmtDB::new(backend, pp, false, None);
+
+    for i in 0..=255u8 {
+        db.set(&Key(vec![i, 0]), vec![i].into());
+    }
+    db.commit(0).unwrap();
+
+    let metrics = db.metrics().snapshot();
+    assert_eq!(metrics.inc_key_count, 256);
+    assert!(metrics.put_count[crate::metrics::PutKind::Merkle as usize] > 0);
+    assert_eq!(idle_db.metrics().snapshot(), Default::default());
+}
+
+#[test]
+fn test_batch_proof() {
+    use std::collections::HashMap;
+
+    let pp = Arc::new(AMTParams::<Pairing>::from_dir(
+        "./pp",
+        TypeDepths::USIZE,
+        true,
+    ));
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let mut db: LvmtDB = LvmtDB::new(backend, pp.clone(), false, Some((0, 0)));
+
+    let mut epoch_root_dict = HashMap::new();
+    for epoch in 0..16u8 {
+        for i in 0..=255u8 {
+            db.set(&Key(vec![1, 2, i, epoch % 4]), vec![i, epoch].into());
+        }
+        let (_, epoch_root) = db.commit(epoch as u64).unwrap();
+        epoch_root_dict.insert(epoch as u64, epoch_root);
+    }
+
+    for batch_size in [1usize, 4, 16, 64, 256] {
+        let keys: Vec<Key> = (0..batch_size)
+            .map(|i| Key(vec![1, 2, i as u8, (i / 64) as u8]))
+            .collect();
+
+        let proof = db.prove_batch(&keys).unwrap();
+        <LvmtDB>::verify_batch(&keys, &proof, |epoch| epoch_root_dict[&epoch], &pp).unwrap();
+
+        let single_size: usize = keys
+            .iter()
+            .map(|key| proof_size(&db.prove(key).unwrap()))
+            .sum();
+        println!(
+            "batch size {:>3}: {:>8} bytes, single-key proofs {:>8} bytes",
+            batch_size,
+            proof.size(),
+            single_size
+        );
+        if batch_size > 1 {
+            assert!(proof.size() < single_size);
+        }
+
+        let mut wrong_keys = keys.clone();
+        wrong_keys[0] = Key(vec![1, 2, 0, 1]);
+        let result =
+            <LvmtDB>::verify_batch(&wrong_keys, &proof, |epoch| epoch_root_dict[&epoch], &pp);
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn test_bls12_381_db() {
+    use crate::crypto::{export::Bls12_381, Height};
+    use std::collections::HashMap;
+
+    type Config = AMTConfig<Bls12_381, Height<6>>;
+
+    let pp = cached_pp_with_depth::<Bls12_381>("./pp", 6, true);
+    let backend = crate::storage::test_kvdb(NUM_COLS);
+    let mut db = LvmtDB::<Config>::new(backend, pp.clone(), false, Some((0, 0)));
+
+    let mut epoch_root_dict = HashMap::new();
+    for epoch in 0..64u8 {
+        db.set(&Key(vec![epoch << 2, 1, 2]), vec![epoch].into());
+        let (_, epoch_root) = db.commit(epoch as u64).unwrap();
+        epoch_root_dict.insert(epoch as u64, epoch_root);
+    }
+
+    for epoch in 0..64u8 {
+        let key = Key(vec![epoch << 2, 1, 2]);
+        assert_eq!(vec![epoch], db.get(&key).unwrap().unwrap().into_vec());
+        let proof = db.prove(&key).unwrap();
+        LvmtDB::<Config>::verify(&key, &proof, |epoch| epoch_root_dict[&epoch], &pp).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::storage::access::PUT_MODE;
+use crate::metrics::PutKind;
 use crate::storage::{DBAccess, DBColumn, FlattenArray};
 use ethereum_types::H256;
 use keccak_hash::{keccak, KECCAK_EMPTY};
@@ -11,6 +11,14 @@ pub struct StaticMerkleTree {
 
 pub type MerkleProof = (Vec<H256>, u64);
 
+/// A proof for multiple leaves in the same tree. The positions of the leaves are provided by the
+/// verifier.
+#[derive(Default, Clone, Debug)]
+pub struct MerkleMultiProof {
+    pub depth: u32,
+    pub hashes: Vec<H256>,
+}
+
 fn combine_hash(a: &H256, b: &H256) -> H256 {
     let mut input = a.0.to_vec();
     input.extend_from_slice(&b.0);
@@ -49,6 +57,75 @@ impl StaticMerkleTree {
         return (proofs, position);
     }
 
+    /// `positions` must be sorted and distinct. The sibling nodes are recorded from the bottom
+    /// level to the top level, and are omitted if they can be computed from the proven leaves.
+    pub fn prove_multi(&mut self, positions: &[u64]) -> MerkleMultiProof {
+        let mut hashes = Vec::new();
+        let mut this_level = positions.to_vec();
+        for depth in (1..=self.depth).rev() {
+            let mut next_level = Vec::with_capacity(this_level.len());
+            let mut i = 0;
+            while i < this_level.len() {
+                let position = this_level[i];
+                if i + 1 < this_level.len() && this_level[i + 1] == position ^ 1 {
+                    i += 2;
+                } else {
+                    let index = (1 << depth) | (position ^ 1) as usize;
+                    let mut answer = self.data.get(&index).clone();
+                    if answer == Default::default() {
+                        answer = KECCAK_EMPTY
+                    };
+                    hashes.push(answer);
+                    i += 1;
+                }
+                next_level.push(position >> 1);
+            }
+            this_level = next_level;
+        }
+        MerkleMultiProof {
+            depth: self.depth,
+            hashes,
+        }
+    }
+
+    /// `leaves` are the (position, hash) pairs of the proven leaves, sorted by distinct positions.
+    pub fn verify_multi(root: &H256, leaves: &[(u64, H256)], proof: &MerkleMultiProof) -> bool {
+        if leaves.is_empty()
+            || leaves.windows(2).any(|x| x[0].0 >= x[1].0)
+            || leaves.last().unwrap().0.checked_shr(proof.depth).unwrap_or(0) != 0
+        {
+            return false;
+        }
+
+        let mut hashes = proof.hashes.iter();
+        let mut this_level = leaves.to_vec();
+        for _ in 0..proof.depth {
+            let mut next_level = Vec::with_capacity(this_level.len());
+            let mut i = 0;
+            while i < this_level.len() {
+                let (position, hash) = this_level[i];
+                let sibling = if i + 1 < this_level.len() && this_level[i + 1].0 == position ^ 1 {
+                    i += 1;
+                    this_level[i].1
+                } else if let Some(sibling) = hashes.next() {
+                    *sibling
+                } else {
+                    return false;
+                };
+                i += 1;
+
+                let parent = if position % 2 == 0 {
+                    combine_hash(&hash, &sibling)
+                } else {
+                    combine_hash(&sibling, &hash)
+                };
+                next_level.push((position >> 1, parent));
+            }
+            this_level = next_level;
+        }
+        hashes.next().is_none() && this_level[0].1 == *root
+    }
+
     pub fn verify(root: &H256, hash: &H256, proof: &MerkleProof) -> bool {
         let (merkle_path, pos) = proof;
         let mut current_hash = hash.clone();
@@ -93,8 +170,7 @@ impl StaticMerkleTree {
 
         backend.set(&0, H256::from_low_u64_be(depth as u64));
 
-        *PUT_MODE.lock_mut().unwrap() = 3;
-        backend.flush_cache();
+        backend.flush_cache(PutKind::Merkle);
 
         return root;
     }
@@ -120,5 +196,14 @@ fn test_static_merkle_tree() {
                 i
             );
         }
+
+        let leaves: Vec<(u64, H256)> = (0..epoch)
+            .filter(|x| x % 3 != 1)
+            .map(|x| (x, H256::from_low_u64_be(x + 65536)))
+            .collect();
+        let positions: Vec<u64> = leaves.iter().map(|x| x.0).collect();
+        let proof = tree.prove_multi(&positions);
+        assert!(StaticMerkleTree::verify_multi(&root, &leaves, &proof));
+        assert!(!StaticMerkleTree::verify_multi(&root, &leaves[1..], &proof));
     }
 }
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The kind of `DBAccess` flushing writes to the backend.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PutKind {
+    Data = 0,
+    InnerNode = 1,
+    SubtreeRoot = 2,
+    Merkle = 3,
+}
+
+/// Counters of a single `LvmtDB` instance. It is shared by all the `DBColumn`s (and thus all the
+/// `DBAccess`es) of the instance.
+#[derive(Default, Debug)]
+pub struct LvmtMetrics {
+    put_count: [AtomicU64; 4],
+    inc_key_count: AtomicU64,
+    inc_tree_count: AtomicU64,
+    inc_key_level_sum: AtomicU64,
+}
+
+#[derive(Default, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LvmtMetricsSnapshot {
+    pub put_count: [u64; 4],
+    pub inc_key_count: u64,
+    pub inc_tree_count: u64,
+    pub inc_key_level_sum: u64,
+}
+
+impl LvmtMetrics {
+    pub(crate) fn record_puts(&self, kind: PutKind, count: usize) {
+        self.put_count[kind as usize].fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_inc_keys(&self, count: u64, level_sum: u64) {
+        self.inc_key_count.fetch_add(count, Ordering::Relaxed);
+        self.inc_key_level_sum.fetch_add(level_sum, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_inc_trees(&self, count: u64) {
+        self.inc_tree_count.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LvmtMetricsSnapshot {
+        let load = |x: &AtomicU64| x.load(Ordering::Relaxed);
+        LvmtMetricsSnapshot {
+            put_count: [
+                load(&self.put_count[0]),
+                load(&self.put_count[1]),
+                load(&self.put_count[2]),
+                load(&self.put_count[3]),
+            ],
+            inc_key_count: load(&self.inc_key_count),
+            inc_tree_count: load(&self.inc_tree_count),
+            inc_key_level_sum: load(&self.inc_key_level_sum),
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 
 use lvmt_serde_derive::{MyFromBytes, MyToBytes};
 
-use super::{TreeName, DEPTHS};
+use super::TreeName;
 
 #[derive(Default, Debug, Hash, PartialEq, Eq, Clone, PartialOrd, Ord, MyToBytes, MyFromBytes)]
 pub struct Key(pub Vec<u8>);
@@ -35,19 +35,19 @@ impl Key {
         return entry >> (start_bit + (128 - length));
     }
 
-    pub fn tree_at_level(&self, level: u8) -> TreeName {
+    pub fn tree_at_level(&self, level: u8, depths: usize) -> TreeName {
         TreeName(
             (0..level)
-                .map(|level| self.index_at_level(level) as u32)
+                .map(|level| self.index_at_level(level, depths) as u32)
                 .collect(),
         )
     }
 
-    pub fn index_at_level(&self, level: u8) -> usize {
+    pub fn index_at_level(&self, level: u8, depths: usize) -> usize {
         // if level > 3 {
         //     dbg!(level);
         // }
-        let length = (level as usize) * DEPTHS;
-        self.mid(length, DEPTHS) as usize
+        let length = (level as usize) * depths;
+        self.mid(length, depths) as usize
     }
 }
@@ -11,26 +11,62 @@ pub use self::{
 };
 use crate::{
     amt::{AMTConfigTrait, AMTree},
-    crypto::export::{Pairing, G1},
+    crypto::export::{Bls12_381, Bn254, Pairing, G1},
+    crypto::{TypeDepths, TypeUInt},
     storage::{FlattenArray, FlattenTree},
 };
+use std::marker::PhantomData;
 
+/// The configuration of the AMTs in LVMT, parameterized by the pairing engine and the depth of
+/// each AMT. The default one is fixed by the compile-time features.
 #[derive(Copy, Clone)]
-pub struct AMTConfig;
-
-impl AMTConfigTrait for AMTConfig {
-    type PE = Pairing;
-    type Name = TreeName;
-    type Data = Node;
-    type Commitment = G1<Pairing>;
-    type DataLayout = FlattenArray;
-    type TreeLayout = FlattenTree;
-    type Height = crate::crypto::TypeDepths;
+pub struct AMTConfig<PE = Pairing, D = TypeDepths>(PhantomData<(PE, D)>);
+
+macro_rules! impl_amt_config {
+    ($pe: ty) => {
+        impl<D: TypeUInt> AMTConfigTrait for AMTConfig<$pe, D> {
+            type PE = $pe;
+            type Name = TreeName;
+            type Data = Node;
+            type Commitment = G1<$pe>;
+            type DataLayout = FlattenArray;
+            type TreeLayout = FlattenTree;
+            type Height = D;
+        }
+    };
 }
 
-type Tree = AMTree<AMTConfig>;
-pub type Commitment = G1<<AMTConfig as AMTConfigTrait>::PE>;
+impl_amt_config!(Bn254);
+impl_amt_config!(Bls12_381);
+
+/// The AMT configurations that `LvmtDB` can work with.
+pub trait LvmtConfig:
+    AMTConfigTrait<
+        Name = TreeName,
+        Data = Node,
+        DataLayout = FlattenArray,
+        TreeLayout = FlattenTree,
+    > + Copy
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<C> LvmtConfig for C where
+    C: AMTConfigTrait<
+            Name = TreeName,
+            Data = Node,
+            DataLayout = FlattenArray,
+            TreeLayout = FlattenTree,
+        > + Copy
+        + Send
+        + Sync
+        + 'static
+{
+}
 
-const DEPTHS: usize = <AMTConfig as AMTConfigTrait>::DEPTHS;
+type Tree<C> = AMTree<C>;
+pub type Commitment<C> = G1<<C as AMTConfigTrait>::PE>;
 
 //TODO: Store Key for non-existent proof
@@ -1,20 +1,18 @@
 use crate::amt::AMTData;
-use crate::crypto::export::{
-    FpParameters, Fr as FrGeneric, FrInt as FrIntGeneric, Pairing, PrimeField,
-};
+use crate::crypto::export::{BigInteger, Bls12_381, Bn254, FpParameters, Fr, PrimeField};
 use lvmt_serde_derive::{MyFromBytes, MyToBytes};
+use std::convert::TryInto;
 use std::ops::{Deref, DerefMut};
 
-pub(super) type Fr = FrGeneric<Pairing>;
-pub(super) type FrInt = FrIntGeneric<Pairing>;
-
 pub const VERSION_BITS: usize = 40;
 pub const MAX_VERSION_NUMBER: u64 = (1 << VERSION_BITS) - 1;
 
 #[allow(dead_code)]
 fn const_assert() {
-    const CAPACITY: u32 = <Fr as PrimeField>::Params::CAPACITY;
-    const_assert!(CAPACITY > 40 * 6);
+    const BN254_CAPACITY: u32 = <Fr<Bn254> as PrimeField>::Params::CAPACITY;
+    const_assert!(BN254_CAPACITY > 40 * 6);
+    const BLS12_381_CAPACITY: u32 = <Fr<Bls12_381> as PrimeField>::Params::CAPACITY;
+    const_assert!(BLS12_381_CAPACITY > 40 * 6);
 }
 
 #[derive(Default, Clone, Debug, MyFromBytes, MyToBytes)]
@@ -46,9 +44,8 @@ pub struct Node {
     pub(crate) tree_position: EpochPosition,
 }
 
-impl AMTData<Fr> for Node {
-    #[cfg(target_endian = "little")]
-    fn as_fr_int(&self) -> FrInt {
+impl<P: PrimeField> AMTData<P> for Node {
+    fn as_fr_int(&self) -> P::BigInt {
         assert!(self.key_versions.len() <= 5);
         let mut result = [0u8; 32];
 
@@ -59,15 +56,22 @@ impl AMTData<Fr> for Node {
         }
         result[0..5].copy_from_slice(&self.tree_version.to_le_bytes()[0..5]);
 
-        let result = unsafe { std::mem::transmute::<[u8; 32], [u64; 4]>(result) };
-        FrInt::new(result)
+        let mut fr_int = P::BigInt::default();
+        for (limb, bytes) in fr_int.as_mut().iter_mut().zip(result.chunks(8)) {
+            *limb = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        fr_int
     }
 }
 
 impl Node {
-    pub fn versions_from_fr_int(fr_int: &FrInt, index: usize) -> u64 {
+    pub fn versions_from_fr_int<I: BigInteger>(fr_int: &I, index: usize) -> u64 {
         assert!(index < 6);
-        let byte_array = unsafe { std::mem::transmute::<&[u64; 4], &[u8; 32]>(&fr_int.0) };
+        let byte_array: Vec<u8> = fr_int
+            .as_ref()
+            .iter()
+            .flat_map(|limb| limb.to_le_bytes())
+            .collect();
         let mut answer = [0u8; 8];
         answer[..5].copy_from_slice(&byte_array[index * 5..(index + 1) * 5]);
         u64::from_le_bytes(answer)
@@ -77,6 +81,10 @@ impl Node {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::crypto::export::{FrInt as FrIntGeneric, Pairing};
+
+    type Fr = super::Fr<Pairing>;
+    type FrInt = FrIntGeneric<Pairing>;
     use rand::{prelude::ThreadRng, Rng};
 
     #[test]
@@ -98,7 +106,7 @@ mod test {
         answer[3] += 6 * (1 << VERSION_BITS * 5 - 192);
         let answer = FrInt::new(answer);
 
-        assert_eq!(node.as_fr_int(), answer);
+        assert_eq!(AMTData::<Fr>::as_fr_int(&node), answer);
     }
 
     #[cfg(test)]
@@ -122,7 +130,7 @@ mod test {
             answer.add_nocarry(&fr_int);
         }
 
-        assert_eq!(node.as_fr_int(), answer);
+        assert_eq!(AMTData::<Fr>::as_fr_int(&node), answer);
 
         assert_eq!(node.tree_version, Node::versions_from_fr_int(&answer, 0));
         for i in 0..5 {
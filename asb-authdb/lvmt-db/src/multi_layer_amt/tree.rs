@@ -3,51 +3,49 @@ use std::sync::Arc;
 
 use hashbrown::{HashMap, HashSet};
 use kvdb::{DBKey, DBOp, DBTransaction};
+use rayon::prelude::*;
 
 use crate::amt::AMTConfigTrait;
 use lvmt_serde_derive::{MyFromBytes, MyToBytes};
 
 use crate::crypto::export::Zero;
 use crate::crypto::{
-    export::{
-        instances::{FrInt, G1Aff, G1},
-        BigInteger, Pairing, ProjectiveCurve,
-    },
+    export::{BigInteger, FrInt, PairingEngine, ProjectiveCurve},
     AMTParams,
 };
 use crate::multi_layer_amt::AMTConfig;
 use crate::serde::{MyFromBytes, MyToBytes};
 use crate::storage::DBColumn;
 
-use super::{Commitment, EpochPosition, Key, Tree, TreeName, MAX_VERSION_NUMBER};
+use super::{Commitment, EpochPosition, Key, LvmtConfig, Tree, TreeName, MAX_VERSION_NUMBER};
 
 type NodeIndex = u32;
-type TreesLayer = HashMap<Vec<NodeIndex>, TreeWithInfo>;
-pub type AMTNodeIndex = crate::amt::NodeIndex<<AMTConfig as AMTConfigTrait>::Height>;
+type TreesLayer<C> = HashMap<Vec<NodeIndex>, TreeWithInfo<C>>;
+pub type AMTNodeIndex<C = AMTConfig> = crate::amt::NodeIndex<<C as AMTConfigTrait>::Height>;
 
 const ROOT_KEY: [u8; 2] = [0, 0];
 
 /// The `VersionTree`
 #[derive(Clone)]
-pub struct VersionTree {
-    producer: TreeProducer,
-    forest: Vec<TreesLayer>,
+pub struct VersionTree<C: LvmtConfig = AMTConfig> {
+    producer: TreeProducer<C>,
+    forest: Vec<TreesLayer<C>>,
 
-    shard_node: Option<AMTNodeIndex>,
+    shard_node: Option<AMTNodeIndex<C>>,
 }
 
-impl VersionTree {
+impl<C: LvmtConfig> VersionTree<C> {
     pub fn new(
         db: DBColumn,
-        pp: Arc<AMTParams<Pairing>>,
-        shard_node: Option<AMTNodeIndex>,
+        pp: Arc<AMTParams<C::PE>>,
+        shard_node: Option<AMTNodeIndex<C>>,
     ) -> Self {
-        let mut forest = Vec::<TreesLayer>::with_capacity(8);
+        let mut forest = Vec::<TreesLayer<C>>::with_capacity(8);
         forest.push(Default::default());
         let root = db
             .get(ROOT_KEY.as_ref())
             .unwrap()
-            .map_or(G1::zero(), |x| G1::from_bytes_local(&x).unwrap());
+            .map_or(Commitment::<C>::zero(), |x| Commitment::<C>::from_bytes_local(&x).unwrap());
         let producer = TreeProducer {
             db,
             pp,
@@ -69,7 +67,7 @@ impl VersionTree {
         }
     }
 
-    pub(crate) fn get_tree_mut(&mut self, name: &TreeName) -> &mut Tree {
+    pub(crate) fn get_tree_mut(&mut self, name: &TreeName) -> &mut Tree<C> {
         let (ancestor_layers, tree_layer) = {
             let level = name.0.len();
             if self.forest.len() < level + 1 {
@@ -116,6 +114,53 @@ impl VersionTree {
     }
 
     pub fn inc_key_ver(&mut self, key: &Key, version: Option<VerInfo>) -> VerInfo {
+        let (ver_info, update) = self.plan_key_ver(key, version);
+        update.apply(self.get_tree_mut(&key.tree_at_level(ver_info.level, C::DEPTHS)));
+        ver_info
+    }
+
+    /// Increases the versions of a batch of keys. The vacant slots are allocated sequentially in
+    /// the order of `keys`, while the commitment updates are grouped by tree and different trees
+    /// are updated concurrently in the current rayon thread pool.
+    pub fn inc_key_vers_in_batch<'a>(
+        &mut self,
+        keys: impl IntoIterator<Item = (&'a Key, Option<VerInfo>)>,
+    ) -> Vec<VerInfo> {
+        let mut grouped_updates: HashMap<Vec<NodeIndex>, Vec<KeyVerUpdate>> = HashMap::new();
+        let mut ver_infos = Vec::new();
+        for (key, version) in keys {
+            let (ver_info, update) = self.plan_key_ver(key, version);
+            grouped_updates
+                .entry(key.tree_at_level(ver_info.level, C::DEPTHS).0)
+                .or_default()
+                .push(update);
+            ver_infos.push(ver_info);
+        }
+
+        // Load the touched trees and mark them in their ancestors before splitting the borrows.
+        for name in grouped_updates.keys() {
+            self.get_tree_mut(&TreeName(name.clone()));
+        }
+
+        let mut jobs = Vec::with_capacity(grouped_updates.len());
+        for layer in self.forest.iter_mut() {
+            for (name, tree_with_info) in layer.iter_mut() {
+                if let Some(updates) = grouped_updates.get(name) {
+                    jobs.push((&mut tree_with_info.tree, updates));
+                }
+            }
+        }
+
+        jobs.into_par_iter().for_each(|(tree, updates)| {
+            for update in updates.iter() {
+                update.apply(tree);
+            }
+        });
+
+        ver_infos
+    }
+
+    fn plan_key_ver(&mut self, key: &Key, version: Option<VerInfo>) -> (VerInfo, KeyVerUpdate) {
         let VerInfo {
             version,
             level,
@@ -125,28 +170,25 @@ impl VersionTree {
             Some(ver_info) => ver_info,
         };
 
-        let shard_node = self.shard_node.clone();
-        let visit_amt = self.get_tree_mut(&key.tree_at_level(level));
-        let node = key.index_at_level(level);
-
-        let in_proof_shard = if let Some(shard_node) = shard_node {
-            AMTNodeIndex::leaf(key.index_at_level(0)).needs_maintain(&shard_node)
+        let in_proof_shard = if let Some(ref shard_node) = self.shard_node {
+            AMTNodeIndex::<C>::leaf(key.index_at_level(0, C::DEPTHS))
+                .needs_maintain(shard_node)
         } else {
             false
         };
 
-        if !in_proof_shard {
-            visit_amt.update(node, fr_int_pow_2((slot_index as u32 + 1) * 40));
-        } else {
-            // Maintain necessary data for proof.
-            visit_amt.write_versions(node).key_versions[slot_index as usize] += 1;
-        }
         assert!(version < MAX_VERSION_NUMBER);
-        return VerInfo {
+        let ver_info = VerInfo {
             version: version + 1,
             level,
             slot_index,
         };
+        let update = KeyVerUpdate {
+            node: key.index_at_level(level, C::DEPTHS),
+            slot_index,
+            in_proof_shard,
+        };
+        (ver_info, update)
     }
 
     pub fn allocate_vacant_slot(&mut self, key: &Key) -> VerInfo {
@@ -154,8 +196,8 @@ impl VersionTree {
             if level >= 3 {
                 println!("Level {}, allocate slot for {:?}", level, key.0);
             }
-            let visit_amt = self.get_tree_mut(&key.tree_at_level(level));
-            let node_index = key.index_at_level(level);
+            let visit_amt = self.get_tree_mut(&key.tree_at_level(level, C::DEPTHS));
+            let node_index = key.index_at_level(level, C::DEPTHS);
 
             if visit_amt.get(node_index).key_versions.len() < 5 {
                 let mut data = visit_amt.write_versions(node_index);
@@ -177,9 +219,9 @@ impl VersionTree {
         name: &TreeName,
         epoch: u64,
         start_pos: u64, //TODO: ugly.
-        layers: &mut [TreesLayer],
-        updates: &mut SubTreeRootRecorder,
-    ) -> (bool, Commitment) {
+        layers: &mut [TreesLayer<C>],
+        updates: &mut SubTreeRootRecorder<Commitment<C>>,
+    ) -> (bool, Commitment<C>) {
         let (this_layer, rest_layers) = layers.split_first_mut().unwrap();
 
         let tree_with_info = this_layer.get_mut(&name.0).unwrap();
@@ -221,7 +263,7 @@ impl VersionTree {
         &mut self,
         epoch: u64,
         start_pos: u64,
-    ) -> (Commitment, impl IntoIterator<Item = (TreeName, u64, G1Aff)>) {
+    ) -> (Commitment<C>, impl IntoIterator<Item = (TreeName, u64, Commitment<C>)>) {
         let mut updates = SubTreeRootRecorder::with_capacity(1 << 20);
         let (_, commitment) = Self::commit_tree(
             &TreeName::root(),
@@ -239,7 +281,7 @@ impl VersionTree {
     }
 
     pub fn flush_all(&mut self) {
-        let commitment: G1 = self.get_tree_mut(&TreeName::root()).flush();
+        let commitment: Commitment<C> = self.get_tree_mut(&TreeName::root()).flush();
         self.producer.db.write_buffered(DBTransaction {
             ops: vec![DBOp::Insert {
                 col: 0,
@@ -250,14 +292,15 @@ impl VersionTree {
     }
 }
 
-pub struct SubTreeRootRecorderIter(SubTreeRootRecorder);
+pub struct SubTreeRootRecorderIter<G: ProjectiveCurve>(SubTreeRootRecorder<G>);
 
-impl Iterator for SubTreeRootRecorderIter {
-    type Item = (TreeName, u64, G1Aff);
+// The commitments have been normalized in batch, so converting them into affine is cheap.
+impl<G: ProjectiveCurve> Iterator for SubTreeRootRecorderIter<G> {
+    type Item = (TreeName, u64, G);
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some((tree_name, version)) = self.0.tree_version.pop_front() {
-            let commitment: G1Aff = self.0.commitments.pop_front().unwrap().into();
+            let commitment = self.0.commitments.pop_front().unwrap();
             Some((tree_name, version, commitment))
         } else {
             None
@@ -265,12 +308,12 @@ impl Iterator for SubTreeRootRecorderIter {
     }
 }
 
-pub struct SubTreeRootRecorder {
+pub struct SubTreeRootRecorder<G: ProjectiveCurve> {
     tree_version: VecDeque<(TreeName, u64)>,
-    commitments: VecDeque<G1>,
+    commitments: VecDeque<G>,
 }
 
-impl SubTreeRootRecorder {
+impl<G: ProjectiveCurve> SubTreeRootRecorder<G> {
     fn with_capacity(n: usize) -> Self {
         Self {
             tree_version: VecDeque::with_capacity(n),
@@ -278,7 +321,7 @@ impl SubTreeRootRecorder {
         }
     }
 
-    fn push(&mut self, name: TreeName, version: u64, commitment: G1) {
+    fn push(&mut self, name: TreeName, version: u64, commitment: G) {
         self.commitments.push_back(commitment);
         self.tree_version.push_back((name, version));
     }
@@ -294,9 +337,9 @@ impl SubTreeRootRecorder {
     }
 }
 
-impl IntoIterator for SubTreeRootRecorder {
-    type Item = (TreeName, u64, G1Aff);
-    type IntoIter = SubTreeRootRecorderIter;
+impl<G: ProjectiveCurve> IntoIterator for SubTreeRootRecorder<G> {
+    type Item = (TreeName, u64, G);
+    type IntoIter = SubTreeRootRecorderIter<G>;
 
     fn into_iter(self) -> Self::IntoIter {
         SubTreeRootRecorderIter(self)
@@ -304,21 +347,21 @@ impl IntoIterator for SubTreeRootRecorder {
 }
 
 #[derive(Clone)]
-struct TreeProducer {
+struct TreeProducer<C: LvmtConfig> {
     pub db: DBColumn,
-    pub pp: Arc<AMTParams<Pairing>>,
-    pub shard_node: Option<AMTNodeIndex>,
+    pub pp: Arc<AMTParams<C::PE>>,
+    pub shard_node: Option<AMTNodeIndex<C>>,
 }
 
-impl TreeProducer {
-    fn new_tree(&self, name: &TreeName) -> Tree {
+impl<C: LvmtConfig> TreeProducer<C> {
+    fn new_tree(&self, name: &TreeName) -> Tree<C> {
         let shard_root = if let Some(ref shard_node) = self.shard_node {
             if name.0.len() == 0 {
                 Some(shard_node.clone())
             } else {
-                let root_tree_leaf = AMTNodeIndex::leaf(name.0[0] as usize);
+                let root_tree_leaf = AMTNodeIndex::<C>::leaf(name.0[0] as usize);
                 if root_tree_leaf.needs_maintain(shard_node) {
-                    Some(AMTNodeIndex::root())
+                    Some(AMTNodeIndex::<C>::root())
                 } else {
                     None
                 }
@@ -337,15 +380,32 @@ pub struct VerInfo {
     pub slot_index: u8,
 }
 
+struct KeyVerUpdate {
+    node: usize,
+    slot_index: u8,
+    in_proof_shard: bool,
+}
+
+impl KeyVerUpdate {
+    fn apply<C: LvmtConfig>(&self, tree: &mut Tree<C>) {
+        if !self.in_proof_shard {
+            tree.update(self.node, fr_int_pow_2::<C::PE>((self.slot_index as u32 + 1) * 40));
+        } else {
+            // Maintain necessary data for proof.
+            tree.write_versions(self.node).key_versions[self.slot_index as usize] += 1;
+        }
+    }
+}
+
 #[derive(Clone)]
-struct TreeWithInfo {
-    tree: Tree,
+struct TreeWithInfo<C: LvmtConfig> {
+    tree: Tree<C>,
     mark_in_parent: bool,
     children_marks: HashSet<NodeIndex>,
 }
 
-fn fr_int_pow_2(power: u32) -> FrInt {
-    let mut fr_int = FrInt::from(1);
+fn fr_int_pow_2<PE: PairingEngine>(power: u32) -> FrInt<PE> {
+    let mut fr_int = FrInt::<PE>::from(1);
     fr_int.muln(power);
     fr_int
 }
@@ -1,7 +1,6 @@
 use crate::amt::{AMTConfigTrait, AMTData, AMTree, NodeIndex};
 use crate::crypto::export::{
-    instances::{Fr, FrInt, G1Aff, G1},
-    Pairing, ProjectiveCurve, Zero,
+    BigInteger, Bls12_381, Bn254, G1Aff, PrimeField, ProjectiveCurve, Zero, G1,
 };
 use crate::crypto::{AMTParams, TypeUInt};
 use crate::serde::{MyFromBytes, MyToBytes, SerdeType};
@@ -9,13 +8,17 @@ use crate::storage::{DBColumn, FlattenArray, FlattenTree};
 use keccak_hash::{keccak, H256};
 use kvdb::{DBKey, DBOp, DBTransaction, KeyValueDB};
 use lvmt_serde_derive::{MyFromBytes, MyToBytes};
+use std::convert::TryInto;
 use std::io::Write;
+use std::marker::PhantomData;
 use std::sync::{Arc, RwLock};
 
+pub use crate::crypto::Height;
+
 const ROOT_KEY: [u8; 2] = [0, 0];
 
 #[derive(Copy, Clone)]
-struct TreeName;
+pub struct TreeName;
 
 impl MyToBytes for TreeName {
     fn write<W: Write>(&self, mut writer: W, _ty: SerdeType) -> std::io::Result<()> {
@@ -25,62 +28,65 @@ impl MyToBytes for TreeName {
 }
 
 #[derive(Default, Clone, Debug, MyFromBytes, MyToBytes)]
-struct Node {
+pub struct Node {
     data: Vec<u8>,
     hash: H256,
 }
 
-impl AMTData<Fr> for Node {
-    #[cfg(target_endian = "little")]
-    fn as_fr_int(&self) -> FrInt {
-        let mut result = unsafe { std::mem::transmute::<[u8; 32], [u64; 4]>(self.hash.0.clone()) };
-        result[3] &= 0x3fffffff;
-        FrInt::new(result)
+impl<P: PrimeField> AMTData<P> for Node {
+    fn as_fr_int(&self) -> P::BigInt {
+        let mut result = P::BigInt::default();
+        for (limb, bytes) in result.as_mut().iter_mut().zip(self.hash.0.chunks(8)) {
+            *limb = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        result.as_mut()[3] &= 0x3fffffff;
+        result
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-pub struct Height<const N: usize>;
-
-impl<const N: usize> TypeUInt for Height<N> {
-    const USIZE: usize = N;
-}
-
+/// The configuration of a single AMT, parameterized by the pairing engine and the height.
 #[derive(Copy, Clone)]
-struct AMTConfig<const N: usize>;
-
-impl<const N: usize> AMTConfigTrait for AMTConfig<N> {
-    type PE = Pairing;
-    type Name = TreeName;
-    type Data = Node;
-    type Commitment = G1;
-    type DataLayout = FlattenArray;
-    type TreeLayout = FlattenTree;
-    type Height = Height<N>;
+pub struct AMTConfig<PE, D>(PhantomData<(PE, D)>);
+
+macro_rules! impl_amt_config {
+    ($pe: ty) => {
+        impl<D: TypeUInt> AMTConfigTrait for AMTConfig<$pe, D> {
+            type PE = $pe;
+            type Name = TreeName;
+            type Data = Node;
+            type Commitment = G1<$pe>;
+            type DataLayout = FlattenArray;
+            type TreeLayout = FlattenTree;
+            type Height = D;
+        }
+    };
 }
 
+impl_amt_config!(Bn254);
+impl_amt_config!(Bls12_381);
+
 #[derive(Clone)]
-pub struct AmtDB<const N: usize> {
-    root: G1,
-    amt: Arc<RwLock<AMTree<AMTConfig<N>>>>,
+pub struct AmtDB<C: AMTConfigTrait<Name = TreeName, Data = Node>> {
+    root: G1<C::PE>,
+    amt: Arc<RwLock<AMTree<C>>>,
     pub db: Arc<dyn KeyValueDB>,
 }
 
-impl<const N: usize> AmtDB<N> {
+impl<C: AMTConfigTrait<Name = TreeName, Data = Node>> AmtDB<C> {
     pub fn new(
         db: Arc<dyn KeyValueDB>,
-        pp: Arc<AMTParams<Pairing>>,
+        pp: Arc<AMTParams<C::PE>>,
         shard_node: Option<(usize, usize)>,
     ) -> Self {
         let db_col = DBColumn::from_kvdb(db.clone(), 0);
         let root = db_col
             .get(ROOT_KEY.as_ref())
             .unwrap()
-            .map_or(G1::zero(), |x| G1::from_bytes_local(&x).unwrap());
+            .map_or(C::Commitment::zero(), |x| C::Commitment::from_bytes_local(&x).unwrap());
 
-        let shard_root = shard_node.map(|(depth, index)| NodeIndex::<Height<N>>::new(depth, index));
+        let shard_root = shard_node.map(|(depth, index)| NodeIndex::<C::Height>::new(depth, index));
 
-        let mut amt = AMTree::<AMTConfig<N>>::new(TreeName, db_col, pp, shard_root);
+        let mut amt = AMTree::<C>::new(TreeName, db_col, pp, shard_root);
         amt.set_commitment(&root);
 
         Self {
@@ -91,12 +97,12 @@ impl<const N: usize> AmtDB<N> {
     }
 
     fn index(key: &[u8]) -> usize {
-        let bytes = (N + 7) / 8;
+        let bytes = (C::DEPTHS + 7) / 8;
         assert!(key.len() >= bytes);
         let mut index: [u8; 8] = [0u8; 8];
         index.copy_from_slice(&key[..bytes]);
         let mut index = u64::from_le_bytes(index);
-        index &= (1 << N) - 1;
+        index &= (1 << C::DEPTHS) - 1;
         return index as usize;
     }
 
@@ -119,7 +125,7 @@ impl<const N: usize> AmtDB<N> {
         *amt.write_versions(Self::index(key)) = new_node;
     }
 
-    pub fn commit(&mut self) -> G1Aff {
+    pub fn commit(&mut self) -> G1Aff<C::PE> {
         self.root = self.amt.write().unwrap().flush();
         self.db.write_buffered(DBTransaction {
             ops: vec![DBOp::Insert {
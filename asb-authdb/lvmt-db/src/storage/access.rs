@@ -2,18 +2,15 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::marker::PhantomData;
 
-use global::Global;
 use hashbrown::HashMap;
 use kvdb::{DBOp, DBTransaction};
 
+use crate::metrics::PutKind;
 use crate::serde::{MyFromBytes, MyToBytes};
 
 use super::layout::LayoutTrait;
 use super::DBColumn;
 
-pub static PUT_COUNT: Global<[u64; 4]> = Global::INIT;
-pub static PUT_MODE: Global<usize> = Global::INIT;
-
 #[derive(Clone)]
 pub struct DBAccess<
     K: Copy + Clone + Debug + Eq + Hash,
@@ -70,7 +67,7 @@ impl<
         self.cache.insert(*node_index, (value, true));
     }
 
-    pub fn flush_cache(&mut self) {
+    pub fn flush_cache(&mut self, kind: PutKind) {
         let prefix = &self.prefix;
         let ops: Vec<DBOp> = self
             .cache
@@ -87,7 +84,7 @@ impl<
             })
             .collect();
 
-        (*PUT_COUNT.lock_mut().unwrap())[*PUT_MODE.lock().unwrap()] += ops.len() as u64;
+        self.db.metrics().record_puts(kind, ops.len());
 
         self.db.write_buffered(DBTransaction { ops });
         self.cache.clear();
@@ -133,7 +130,7 @@ mod test {
             }
         }
 
-        tree.flush_cache();
+        tree.flush_cache(PutKind::Data);
 
         for depth in 0..DEPTHS {
             for index in 0..(1 << depth) {
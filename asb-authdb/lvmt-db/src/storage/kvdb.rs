@@ -2,15 +2,31 @@ use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB};
 use std::io::Result;
 use std::sync::Arc;
 
+use crate::metrics::LvmtMetrics;
+
 #[derive(Clone)]
 pub struct DBColumn {
     db: Arc<dyn KeyValueDB>,
     col: u32,
+    metrics: Arc<LvmtMetrics>,
 }
 
 impl DBColumn {
     pub fn from_kvdb(db: Arc<dyn KeyValueDB>, col: u32) -> Self {
-        Self { db, col }
+        Self {
+            db,
+            col,
+            metrics: Default::default(),
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<LvmtMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics(&self) -> &LvmtMetrics {
+        &self.metrics
     }
 
     pub fn get(&self, key: &[u8]) -> Result<Option<DBValue>> {
@@ -42,16 +42,16 @@ use DB_PREFIX_LEN;
 /// write operations out to disk. Unlike `OverlayDB`, `remove()` operations do not take effect
 /// immediately. As this is an "archive" database, nothing is ever removed. This means
 /// that the states of any block the node has ever processed will be accessible.
-pub struct ArchiveDB {
-    overlay: MemoryDB<DBHasher, DBValue>,
+pub struct ArchiveDB<H: DBHasher> {
+    overlay: MemoryDB<H, DBValue>,
     backing: Arc<dyn KeyValueDB>,
     latest_era: Option<u64>,
     column: u32,
 }
 
-impl ArchiveDB {
+impl<H: DBHasher> ArchiveDB<H> {
     /// Create a new instance from a key-value db.
-    pub fn new(backing: Arc<dyn KeyValueDB>, column: u32) -> ArchiveDB {
+    pub fn new(backing: Arc<dyn KeyValueDB>, column: u32) -> ArchiveDB<H> {
         let latest_era = backing
             .get(column, &LATEST_ERA_KEY)
             .expect("Low-level database error.")
@@ -71,7 +71,7 @@ impl ArchiveDB {
     }
 }
 
-impl HashDB<DBHasher, DBValue> for ArchiveDB {
+impl<H: DBHasher> HashDB<H, DBValue> for ArchiveDB<H> {
     fn get(&self, key: &H256) -> Option<DBValue> {
         if let Some((d, rc)) = self.overlay.raw(key) {
             if rc > 0 {
@@ -82,7 +82,7 @@ impl HashDB<DBHasher, DBValue> for ArchiveDB {
     }
 
     fn contains(&self, key: &H256) -> bool {
-        HashDB::<DBHasher, DBValue>::get(self, key).is_some()
+        HashDB::<H, DBValue>::get(self, key).is_some()
     }
 
     fn insert(&mut self, value: &[u8]) -> H256 {
@@ -98,7 +98,7 @@ impl HashDB<DBHasher, DBValue> for ArchiveDB {
     }
 }
 
-impl ::traits::KeyedHashDB for ArchiveDB {
+impl<H: DBHasher> ::traits::KeyedHashDB<H> for ArchiveDB<H> {
     fn keys(&self) -> HashMap<H256, i32> {
         let mut ret: HashMap<H256, i32> = self
             .backing
@@ -120,8 +120,8 @@ impl ::traits::KeyedHashDB for ArchiveDB {
     }
 }
 
-impl JournalDB for ArchiveDB {
-    fn boxed_clone(&self) -> Box<dyn JournalDB> {
+impl<H: DBHasher> JournalDB<H> for ArchiveDB<H> {
+    fn boxed_clone(&self) -> Box<dyn JournalDB<H>> {
         Box::new(ArchiveDB {
             overlay: self.overlay.clone(),
             backing: self.backing.clone(),
@@ -214,7 +214,7 @@ impl JournalDB for ArchiveDB {
         &self.backing
     }
 
-    fn consolidate(&mut self, with: MemoryDB<DBHasher, DBValue>) {
+    fn consolidate(&mut self, with: MemoryDB<H, DBValue>) {
         self.overlay.consolidate(with);
     }
 
@@ -232,9 +232,11 @@ mod tests {
     use crate::InMemoryWithMetrics;
     use hash_db::HashDB;
     use keccak::keccak;
+    use keccak_hasher::KeccakHasher;
     use JournalDB;
 
-    type TestHashDB = dyn HashDB<DBHasher, DBValue>;
+    type TestHashDB = dyn HashDB<KeccakHasher, DBValue>;
+    type ArchiveDB = super::ArchiveDB<KeccakHasher>;
 
     #[test]
     fn insert_same_in_fork() {
@@ -33,55 +33,55 @@ use hash_db15::{AsHashDB as AsHashDB15, HashDB as HashDB15, Prefix};
 use keccak_hasher15::DBHasher as DBHasher15;
 
 macro_rules! wrap_hash_db {
-    ($name: ty) => {
-        impl HashDB<DBHasher, DBValue> for $name {
+    ($name: ident) => {
+        impl<H: DBHasher> HashDB<H, DBValue> for $name<H> {
             fn get(&self, key: &H256) -> Option<DBValue> {
-                HashDB::<DBHasher, KVDBValue>::get(self, key).map(|x| DBValue::from_vec(x))
+                HashDB::<H, KVDBValue>::get(self, key).map(|x| DBValue::from_vec(x))
             }
 
             fn contains(&self, key: &H256) -> bool {
-                HashDB::<DBHasher, KVDBValue>::contains(self, key)
+                HashDB::<H, KVDBValue>::contains(self, key)
             }
 
             fn insert(&mut self, value: &[u8]) -> H256 {
-                HashDB::<DBHasher, KVDBValue>::insert(self, value)
+                HashDB::<H, KVDBValue>::insert(self, value)
             }
 
             fn emplace(&mut self, key: H256, value: DBValue) {
-                HashDB::<DBHasher, KVDBValue>::emplace(self, key, value.into_vec())
+                HashDB::<H, KVDBValue>::emplace(self, key, value.into_vec())
             }
 
             fn remove(&mut self, key: &H256) {
-                HashDB::<DBHasher, KVDBValue>::remove(self, key)
+                HashDB::<H, KVDBValue>::remove(self, key)
             }
         }
 
-        impl AsHashDB<DBHasher, DBValue> for $name {
-            fn as_hash_db(&self) -> &dyn HashDB<DBHasher, DBValue> {
+        impl<H: DBHasher> AsHashDB<H, DBValue> for $name<H> {
+            fn as_hash_db(&self) -> &dyn HashDB<H, DBValue> {
                 self
             }
-            fn as_hash_db_mut(&mut self) -> &mut dyn HashDB<DBHasher, DBValue> {
+            fn as_hash_db_mut(&mut self) -> &mut dyn HashDB<H, DBValue> {
                 self
             }
         }
 
-        impl AsHashDB<DBHasher, KVDBValue> for $name {
-            fn as_hash_db(&self) -> &dyn HashDB<DBHasher, KVDBValue> {
+        impl<H: DBHasher> AsHashDB<H, KVDBValue> for $name<H> {
+            fn as_hash_db(&self) -> &dyn HashDB<H, KVDBValue> {
                 self
             }
-            fn as_hash_db_mut(&mut self) -> &mut dyn HashDB<DBHasher, KVDBValue> {
+            fn as_hash_db_mut(&mut self) -> &mut dyn HashDB<H, KVDBValue> {
                 self
             }
         }
 
-        impl AsKeyedHashDB for $name {
-            fn as_keyed_hash_db(&self) -> &dyn KeyedHashDB {
+        impl<H: DBHasher> AsKeyedHashDB<H> for $name<H> {
+            fn as_keyed_hash_db(&self) -> &dyn KeyedHashDB<H> {
                 self
             }
         }
 
         #[cfg(feature = "hash15")]
-        impl AsHashDB15<DBHasher15, DBValue> for $name {
+        impl<H: DBHasher> AsHashDB15<DBHasher15, DBValue> for $name<H> {
             fn as_hash_db(&self) -> &dyn HashDB15<DBHasher15, DBValue> {
                 self
             }
@@ -92,28 +92,28 @@ macro_rules! wrap_hash_db {
         }
 
         #[cfg(feature = "hash15")]
-        impl HashDB15<DBHasher15, DBValue> for $name {
+        impl<H: DBHasher> HashDB15<DBHasher15, DBValue> for $name<H> {
             // The key function `HashKey` in `memory-db` (v0.28.0) omits `prefix`.
             // The example code in `TrieDB` uses `HashKey` as key function.
             // So here we also omit `prefix`.
             fn get(&self, key: &[u8; 32], _prefix: Prefix) -> Option<DBValue> {
-                HashDB::<DBHasher, DBValue>::get(self, to_h256_ref(key))
+                HashDB::<H, DBValue>::get(self, to_h256_ref(key))
             }
 
             fn contains(&self, key: &[u8; 32], _prefix: Prefix) -> bool {
-                HashDB::<DBHasher, DBValue>::contains(self, to_h256_ref(key))
+                HashDB::<H, DBValue>::contains(self, to_h256_ref(key))
             }
 
             fn insert(&mut self, _prefix: Prefix, value: &[u8]) -> [u8; 32] {
-                HashDB::<DBHasher, DBValue>::insert(self, value).into()
+                HashDB::<H, DBValue>::insert(self, value).into()
             }
 
             fn emplace(&mut self, key: [u8; 32], _prefix: Prefix, value: DBValue) {
-                HashDB::<DBHasher, DBValue>::emplace(self, key.into(), value)
+                HashDB::<H, DBValue>::emplace(self, key.into(), value)
             }
 
             fn remove(&mut self, key: &[u8; 32], _prefix: Prefix) {
-                HashDB::<DBHasher, DBValue>::remove(self, to_h256_ref(key))
+                HashDB::<H, DBValue>::remove(self, to_h256_ref(key))
             }
         }
     };
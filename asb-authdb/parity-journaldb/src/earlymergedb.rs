@@ -106,18 +106,18 @@ enum RemoveFrom {
 /// ```
 ///
 /// TODO: `store_reclaim_period`
-pub struct EarlyMergeDB {
-    overlay: MemoryDB<DBHasher, DBValue>,
+pub struct EarlyMergeDB<H: DBHasher> {
+    overlay: MemoryDB<H, DBValue>,
     backing: Arc<dyn KeyValueDB>,
     refs: Option<Arc<RwLock<HashMap<H256, RefInfo>>>>,
     latest_era: Option<u64>,
     column: u32,
 }
 
-impl EarlyMergeDB {
+impl<H: DBHasher> EarlyMergeDB<H> {
     /// Create a new instance from file
-    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> EarlyMergeDB {
-        let (latest_era, refs) = EarlyMergeDB::read_refs(&*backing, col);
+    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> EarlyMergeDB<H> {
+        let (latest_era, refs) = Self::read_refs(&*backing, col);
         let refs = Some(Arc::new(RwLock::new(refs)));
         EarlyMergeDB {
             overlay: ::new_memory_db(),
@@ -339,7 +339,7 @@ impl EarlyMergeDB {
     }
 }
 
-impl HashDB<DBHasher, DBValue> for EarlyMergeDB {
+impl<H: DBHasher> HashDB<H, DBValue> for EarlyMergeDB<H> {
     fn get(&self, key: &H256) -> Option<DBValue> {
         if let Some((d, rc)) = self.overlay.raw(key) {
             if rc > 0 {
@@ -350,7 +350,7 @@ impl HashDB<DBHasher, DBValue> for EarlyMergeDB {
     }
 
     fn contains(&self, key: &H256) -> bool {
-        HashDB::<DBHasher, DBValue>::get(self, key).is_some()
+        HashDB::<H, DBValue>::get(self, key).is_some()
     }
 
     fn insert(&mut self, value: &[u8]) -> H256 {
@@ -364,7 +364,7 @@ impl HashDB<DBHasher, DBValue> for EarlyMergeDB {
     }
 }
 
-impl ::traits::KeyedHashDB for EarlyMergeDB {
+impl<H: DBHasher> ::traits::KeyedHashDB<H> for EarlyMergeDB<H> {
     fn keys(&self) -> HashMap<H256, i32> {
         let mut ret: HashMap<H256, i32> = self
             .backing
@@ -386,8 +386,8 @@ impl ::traits::KeyedHashDB for EarlyMergeDB {
     }
 }
 
-impl JournalDB for EarlyMergeDB {
-    fn boxed_clone(&self) -> Box<dyn JournalDB> {
+impl<H: DBHasher> JournalDB<H> for EarlyMergeDB<H> {
+    fn boxed_clone(&self) -> Box<dyn JournalDB<H>> {
         Box::new(EarlyMergeDB {
             overlay: self.overlay.clone(),
             backing: self.backing.clone(),
@@ -607,7 +607,7 @@ impl JournalDB for EarlyMergeDB {
         Ok(ops)
     }
 
-    fn consolidate(&mut self, with: MemoryDB<DBHasher, DBValue>) {
+    fn consolidate(&mut self, with: MemoryDB<H, DBValue>) {
         self.overlay.consolidate(with);
     }
 
@@ -624,15 +624,17 @@ mod tests {
     use super::{super::traits::JournalDB, *};
     use hash_db::HashDB;
     use keccak::keccak;
+    use keccak_hasher::KeccakHasher;
 
-    type TestHashDB = dyn HashDB<DBHasher, DBValue>;
+    type TestHashDB = dyn HashDB<KeccakHasher, DBValue>;
+    type EarlyMergeDB = super::EarlyMergeDB<KeccakHasher>;
 
     #[test]
     fn insert_same_in_fork() {
         // history is 1
         let mut jdb = new_db();
 
-        let x: H256 = HashDB::<DBHasher, DBValue>::insert(&mut jdb, b"X");
+        let x: H256 = HashDB::<KeccakHasher, DBValue>::insert(&mut jdb, b"X");
         jdb.commit_batch(1, &keccak(b"1"), None).unwrap();
         assert!(jdb.can_reconstruct_refs());
         jdb.commit_batch(2, &keccak(b"2"), None).unwrap();
@@ -1,4 +1,8 @@
-#[cfg(feature = "light-hash")]
-pub use blake2_hasher::Blake2bHasher as DBHasher;
-#[cfg(not(feature = "light-hash"))]
-pub use keccak_hasher::KeccakHasher as DBHasher;
+use ethereum_types::H256;
+use hash_db::Hasher;
+
+/// The hasher of the nodes in a journal database. Each database takes it as a type parameter, so
+/// the databases in one process may hash with different functions.
+pub trait DBHasher: Hasher<Out = H256> + Clone + PartialEq + 'static {}
+
+impl<H: Hasher<Out = H256> + Clone + PartialEq + 'static> DBHasher for H {}
@@ -18,14 +18,11 @@
 
 #[macro_use]
 extern crate log;
-#[cfg(feature = "blake2-hasher")]
-extern crate blake2_hasher;
 extern crate ethereum_types;
 extern crate fastmap;
 extern crate hash_db;
 #[cfg(feature = "hash15")]
 extern crate hash_db15;
-extern crate keccak_hasher;
 #[cfg(feature = "hash15")]
 extern crate keccak_hasher15;
 extern crate kvdb;
@@ -41,6 +38,8 @@ extern crate env_logger;
 #[cfg(test)]
 extern crate keccak_hash as keccak;
 #[cfg(test)]
+extern crate keccak_hasher;
+#[cfg(test)]
 extern crate kvdb_memorydb;
 
 use std::{fmt, io, str, sync::Arc};
@@ -161,11 +160,11 @@ impl fmt::Display for Algorithm {
 }
 
 /// Create a new `JournalDB` trait object over a generic key-value database.
-pub fn new(
+pub fn new<H: DBHasher>(
     backing: Arc<dyn crate::KeyValueDB>,
     algorithm: Algorithm,
     col: u32,
-) -> Box<dyn JournalDB> {
+) -> Box<dyn JournalDB<H>> {
     match algorithm {
         Algorithm::Archive => Box::new(archivedb::ArchiveDB::new(backing, col)),
         Algorithm::EarlyMerge => Box::new(earlymergedb::EarlyMergeDB::new(backing, col)),
@@ -192,7 +191,7 @@ fn error_negatively_reference_hash(hash: &ethereum_types::H256) -> io::Error {
     )
 }
 
-pub fn new_memory_db() -> memory_db::MemoryDB<crate::hasher::DBHasher, kvdb::DBValue> {
+pub fn new_memory_db<H: DBHasher>() -> memory_db::MemoryDB<H, kvdb::DBValue> {
     memory_db::MemoryDB::from_null_node(&rlp::NULL_RLP, rlp::NULL_RLP.as_ref().into())
 }
 
@@ -40,8 +40,8 @@ use rlp::{decode, encode, Decodable, DecoderError, Encodable, Rlp, RlpStream};
 /// `lookup()` and `contains()` maintain normal behaviour - all `insert()` and `remove()`
 /// queries have an immediate effect in terms of these functions.
 #[derive(Clone)]
-pub struct OverlayDB {
-    overlay: MemoryDB<DBHasher, DBValue>,
+pub struct OverlayDB<H: DBHasher> {
+    overlay: MemoryDB<H, DBValue>,
     backing: Arc<dyn KeyValueDB>,
     column: u32,
 }
@@ -76,9 +76,9 @@ impl Decodable for Payload {
     }
 }
 
-impl OverlayDB {
+impl<H: DBHasher> OverlayDB<H> {
     /// Create a new instance of OverlayDB given a `backing` database.
-    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> OverlayDB {
+    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> OverlayDB<H> {
         OverlayDB {
             overlay: ::new_memory_db(),
             backing: backing,
@@ -88,7 +88,7 @@ impl OverlayDB {
 
     /// Create a new instance of OverlayDB with an anonymous temporary database.
     #[cfg(test)]
-    pub fn new_temp() -> OverlayDB {
+    pub fn new_temp() -> OverlayDB<H> {
         let backing = Arc::new(crate::InMemoryWithMetrics::create(1));
         Self::new(backing, 0)
     }
@@ -172,7 +172,7 @@ impl OverlayDB {
     }
 }
 
-impl crate::KeyedHashDB for OverlayDB {
+impl<H: DBHasher> crate::KeyedHashDB<H> for OverlayDB<H> {
     fn keys(&self) -> HashMap<H256, i32> {
         let mut ret: HashMap<H256, i32> = self
             .backing
@@ -198,7 +198,7 @@ impl crate::KeyedHashDB for OverlayDB {
     }
 }
 
-impl HashDB<DBHasher, DBValue> for OverlayDB {
+impl<H: DBHasher> HashDB<H, DBValue> for OverlayDB<H> {
     fn get(&self, key: &H256) -> Option<DBValue> {
         // return ok if positive; if negative, check backing - might be enough references there to make
         // it positive again.
@@ -257,11 +257,13 @@ impl HashDB<DBHasher, DBValue> for OverlayDB {
 }
 
 #[cfg(test)]
-type TestHashDB = dyn HashDB<DBHasher, DBValue>;
+use keccak_hasher::KeccakHasher;
+#[cfg(test)]
+type TestHashDB = dyn HashDB<KeccakHasher, DBValue>;
 
 #[test]
 fn overlaydb_revert() {
-    let mut m = OverlayDB::new_temp();
+    let mut m = OverlayDB::<KeccakHasher>::new_temp();
     let foo = TestHashDB::insert(&mut m, b"foo"); // insert foo.
     let mut batch = m.backing.transaction();
     m.commit_to_batch(&mut batch).unwrap(); // commit - new operations begin here...
@@ -277,7 +279,7 @@ fn overlaydb_revert() {
 
 #[test]
 fn overlaydb_overlay_insert_and_remove() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let h = TestHashDB::insert(&mut trie, b"hello world");
     assert_eq!(
         TestHashDB::get(&trie, &h).unwrap(),
@@ -289,7 +291,7 @@ fn overlaydb_overlay_insert_and_remove() {
 
 #[test]
 fn overlaydb_backing_insert_revert() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let h = TestHashDB::insert(&mut trie, b"hello world");
     assert_eq!(
         TestHashDB::get(&trie, &h).unwrap(),
@@ -309,7 +311,7 @@ fn overlaydb_backing_insert_revert() {
 
 #[test]
 fn overlaydb_backing_remove() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let h = TestHashDB::insert(&mut trie, b"hello world");
     trie.commit().unwrap();
     TestHashDB::remove(&mut trie, &h);
@@ -322,7 +324,7 @@ fn overlaydb_backing_remove() {
 
 #[test]
 fn overlaydb_backing_remove_revert() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let h = TestHashDB::insert(&mut trie, b"hello world");
     trie.commit().unwrap();
     TestHashDB::remove(&mut trie, &h);
@@ -336,7 +338,7 @@ fn overlaydb_backing_remove_revert() {
 
 #[test]
 fn overlaydb_negative() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let h = TestHashDB::insert(&mut trie, b"hello world");
     trie.commit().unwrap();
     TestHashDB::remove(&mut trie, &h);
@@ -347,7 +349,7 @@ fn overlaydb_negative() {
 
 #[test]
 fn overlaydb_complex() {
-    let mut trie = OverlayDB::new_temp();
+    let mut trie = OverlayDB::<KeccakHasher>::new_temp();
     let hfoo = TestHashDB::insert(&mut trie, b"foo");
     assert_eq!(TestHashDB::get(&trie, &hfoo).unwrap(), (b"foo").to_vec());
     let hbar = TestHashDB::insert(&mut trie, b"bar");
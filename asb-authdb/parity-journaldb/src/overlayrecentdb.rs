@@ -67,10 +67,10 @@ use util::DatabaseKey;
 /// the removed key is not present in the history overlay.
 /// 7. Delete ancient record from memory and disk.
 
-pub struct OverlayRecentDB {
-    transaction_overlay: MemoryDB<DBHasher, DBValue>,
+pub struct OverlayRecentDB<H: DBHasher> {
+    transaction_overlay: MemoryDB<H, DBValue>,
     backing: Arc<dyn KeyValueDB>,
-    journal_overlay: Arc<RwLock<JournalOverlay>>,
+    journal_overlay: Arc<RwLock<JournalOverlay<H>>>,
     read_cache: Arc<RwLock<HashMap<H256, Option<DBValue>>>>,
     column: u32,
 }
@@ -126,8 +126,8 @@ impl<'a> Encodable for DatabaseValueRef<'a> {
 }
 
 #[derive(PartialEq)]
-struct JournalOverlay {
-    backing_overlay: MemoryDB<DBHasher, DBValue>, // Nodes added in the history period
+struct JournalOverlay<H: DBHasher> {
+    backing_overlay: MemoryDB<H, DBValue>, // Nodes added in the history period
     pending_overlay: H256FastMap<DBValue>, // Nodes being transfered from backing_overlay to backing db
     journal: HashMap<u64, Vec<JournalEntry>>,
     latest_era: Option<u64>,
@@ -142,8 +142,8 @@ struct JournalEntry {
     deletions: Vec<H256>,
 }
 
-impl Clone for OverlayRecentDB {
-    fn clone(&self) -> OverlayRecentDB {
+impl<H: DBHasher> Clone for OverlayRecentDB<H> {
+    fn clone(&self) -> OverlayRecentDB<H> {
         OverlayRecentDB {
             transaction_overlay: self.transaction_overlay.clone(),
             backing: self.backing.clone(),
@@ -154,10 +154,10 @@ impl Clone for OverlayRecentDB {
     }
 }
 
-impl OverlayRecentDB {
+impl<H: DBHasher> OverlayRecentDB<H> {
     /// Create a new instance.
-    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> OverlayRecentDB {
-        let journal_overlay = Arc::new(RwLock::new(OverlayRecentDB::read_overlay(&*backing, col)));
+    pub fn new(backing: Arc<dyn KeyValueDB>, col: u32) -> OverlayRecentDB<H> {
+        let journal_overlay = Arc::new(RwLock::new(Self::read_overlay(&*backing, col)));
         OverlayRecentDB {
             transaction_overlay: ::new_memory_db(),
             backing: backing,
@@ -184,7 +184,7 @@ impl OverlayRecentDB {
             .expect("Low-level database error. Some issue with your hard disk?")
     }
 
-    fn read_overlay(db: &dyn KeyValueDB, col: u32) -> JournalOverlay {
+    fn read_overlay(db: &dyn KeyValueDB, col: u32) -> JournalOverlay<H> {
         let mut journal = HashMap::new();
         let mut overlay = ::new_memory_db();
         let mut count = 0;
@@ -260,7 +260,7 @@ fn to_short_key(key: &H256) -> H256 {
     k
 }
 
-impl ::traits::KeyedHashDB for OverlayRecentDB {
+impl<H: DBHasher> ::traits::KeyedHashDB<H> for OverlayRecentDB<H> {
     fn keys(&self) -> HashMap<H256, i32> {
         let mut ret: HashMap<H256, i32> = self
             .backing
@@ -282,8 +282,8 @@ impl ::traits::KeyedHashDB for OverlayRecentDB {
     }
 }
 
-impl JournalDB for OverlayRecentDB {
-    fn boxed_clone(&self) -> Box<dyn JournalDB> {
+impl<H: DBHasher> JournalDB<H> for OverlayRecentDB<H> {
+    fn boxed_clone(&self) -> Box<dyn JournalDB<H>> {
         Box::new(self.clone())
     }
 
@@ -513,7 +513,7 @@ impl JournalDB for OverlayRecentDB {
         Ok(ops)
     }
 
-    fn consolidate(&mut self, with: MemoryDB<DBHasher, DBValue>) {
+    fn consolidate(&mut self, with: MemoryDB<H, DBValue>) {
         self.transaction_overlay.consolidate(with);
     }
 
@@ -532,7 +532,7 @@ impl JournalDB for OverlayRecentDB {
     }
 }
 
-impl HashDB<DBHasher, DBValue> for OverlayRecentDB {
+impl<H: DBHasher> HashDB<H, DBValue> for OverlayRecentDB<H> {
     fn get(&self, key: &H256) -> Option<DBValue> {
         if let Some((d, rc)) = self.transaction_overlay.raw(key) {
             if rc > 0 {
@@ -559,7 +559,7 @@ impl HashDB<DBHasher, DBValue> for OverlayRecentDB {
     }
 
     fn contains(&self, key: &H256) -> bool {
-        HashDB::<DBHasher, DBValue>::get(self, key).is_some()
+        HashDB::<H, DBValue>::get(self, key).is_some()
     }
 
     fn insert(&mut self, value: &[u8]) -> H256 {
@@ -579,9 +579,11 @@ mod tests {
     use super::*;
     use hash_db::HashDB;
     use keccak::keccak;
+    use keccak_hasher::KeccakHasher;
     use JournalDB;
 
-    type TestHashDB = dyn HashDB<DBHasher, DBValue>;
+    type TestHashDB = dyn HashDB<KeccakHasher, DBValue>;
+    type OverlayRecentDB = super::OverlayRecentDB<KeccakHasher>;
 
     fn new_db() -> OverlayRecentDB {
         let backing = Arc::new(crate::InMemoryWithMetrics::create(1));
@@ -56,8 +56,8 @@ use DB_PREFIX_LEN;
 /// we remove all of its removes assuming it is canonical and all
 /// of its inserts otherwise.
 // TODO: store last_era, reclaim_period.
-pub struct RefCountedDB {
-    forward: OverlayDB,
+pub struct RefCountedDB<H: DBHasher> {
+    forward: OverlayDB<H>,
     backing: Arc<dyn KeyValueDB>,
     latest_era: Option<u64>,
     inserts: Vec<H256>,
@@ -65,9 +65,9 @@ pub struct RefCountedDB {
     column: u32,
 }
 
-impl RefCountedDB {
+impl<H: DBHasher> RefCountedDB<H> {
     /// Create a new instance given a `backing` database.
-    pub fn new(backing: Arc<dyn KeyValueDB>, column: u32) -> RefCountedDB {
+    pub fn new(backing: Arc<dyn KeyValueDB>, column: u32) -> RefCountedDB<H> {
         let latest_era = backing
             .get(column, &LATEST_ERA_KEY)
             .expect("Low-level database error.")
@@ -84,21 +84,21 @@ impl RefCountedDB {
     }
 }
 
-impl HashDB<DBHasher, DBValue> for RefCountedDB {
+impl<H: DBHasher> HashDB<H, DBValue> for RefCountedDB<H> {
     fn get(&self, key: &H256) -> Option<DBValue> {
-        HashDB::<DBHasher, DBValue>::get(&self.forward, key)
+        HashDB::<H, DBValue>::get(&self.forward, key)
     }
     fn contains(&self, key: &H256) -> bool {
-        HashDB::<DBHasher, DBValue>::contains(&self.forward, key)
+        HashDB::<H, DBValue>::contains(&self.forward, key)
     }
     fn insert(&mut self, value: &[u8]) -> H256 {
-        let r = HashDB::<DBHasher, DBValue>::insert(&mut self.forward, value);
+        let r = HashDB::<H, DBValue>::insert(&mut self.forward, value);
         self.inserts.push(r.clone());
         r
     }
     fn emplace(&mut self, key: H256, value: DBValue) {
         self.inserts.push(key.clone());
-        HashDB::<DBHasher, DBValue>::emplace(&mut self.forward, key, value);
+        HashDB::<H, DBValue>::emplace(&mut self.forward, key, value);
     }
 
     fn remove(&mut self, key: &H256) {
@@ -106,14 +106,14 @@ impl HashDB<DBHasher, DBValue> for RefCountedDB {
     }
 }
 
-impl ::traits::KeyedHashDB for RefCountedDB {
+impl<H: DBHasher> ::traits::KeyedHashDB<H> for RefCountedDB<H> {
     fn keys(&self) -> HashMap<H256, i32> {
         self.forward.keys()
     }
 }
 
-impl JournalDB for RefCountedDB {
-    fn boxed_clone(&self) -> Box<dyn JournalDB> {
+impl<H: DBHasher> JournalDB<H> for RefCountedDB<H> {
+    fn boxed_clone(&self) -> Box<dyn JournalDB<H>> {
         Box::new(RefCountedDB {
             forward: self.forward.clone(),
             backing: self.backing.clone(),
@@ -220,7 +220,7 @@ impl JournalDB for RefCountedDB {
             .expect("rlp read from db; qed");
             trace!(target: "rcdb", "delete journal for time #{}.{}=>{}, (canon was {}): deleting {:?}", end_era, db_key.index, our_id, canon_id, to_remove);
             for i in &to_remove {
-                HashDB::<DBHasher, DBValue>::remove(&mut self.forward, i);
+                HashDB::<H, DBValue>::remove(&mut self.forward, i);
             }
             batch.delete(self.column, &last);
             db_key.index += 1;
@@ -233,19 +233,19 @@ impl JournalDB for RefCountedDB {
     fn inject(&mut self, batch: &mut DBTransaction) -> io::Result<u32> {
         self.inserts.clear();
         for remove in self.removes.drain(..) {
-            HashDB::<DBHasher, DBValue>::remove(&mut self.forward, &remove);
+            HashDB::<H, DBValue>::remove(&mut self.forward, &remove);
         }
         self.forward.commit_to_batch(batch)
     }
 
-    fn consolidate(&mut self, mut with: MemoryDB<DBHasher, DBValue>) {
+    fn consolidate(&mut self, mut with: MemoryDB<H, DBValue>) {
         for (key, (value, rc)) in with.drain() {
             for _ in 0..rc {
                 self.emplace(key, value.clone());
             }
 
             for _ in rc..0 {
-                HashDB::<DBHasher, DBValue>::remove(self, &key);
+                HashDB::<H, DBValue>::remove(self, &key);
             }
         }
     }
@@ -263,9 +263,11 @@ mod tests {
     use super::*;
     use hash_db::HashDB;
     use keccak::keccak;
+    use keccak_hasher::KeccakHasher;
     use JournalDB;
 
-    type TestHashDB = dyn HashDB<DBHasher, DBValue>;
+    type TestHashDB = dyn HashDB<KeccakHasher, DBValue>;
+    type RefCountedDB = super::RefCountedDB<KeccakHasher>;
 
     fn new_db() -> RefCountedDB {
         let backing = Arc::new(crate::InMemoryWithMetrics::create(1));
@@ -27,22 +27,22 @@ use kvdb::{DBTransaction, DBValue};
 use std::collections::{BTreeMap, HashMap};
 
 /// expose keys of a hashDB for debugging or tests (slow).
-pub trait KeyedHashDB: HashDB<DBHasher, trie_db::DBValue> {
+pub trait KeyedHashDB<H: DBHasher>: HashDB<H, trie_db::DBValue> {
     /// Primarily use for tests, highly inefficient.
     fn keys(&self) -> HashMap<H256, i32>;
 }
 
 /// Upcast to `KeyedHashDB`
-pub trait AsKeyedHashDB: AsHashDB<DBHasher, trie_db::DBValue> {
+pub trait AsKeyedHashDB<H: DBHasher>: AsHashDB<H, trie_db::DBValue> {
     /// Perform upcast to KeyedHashDB.
-    fn as_keyed_hash_db(&self) -> &dyn KeyedHashDB;
+    fn as_keyed_hash_db(&self) -> &dyn KeyedHashDB<H>;
 }
 
 /// A `HashDB` which can manage a short-term journal potentially containing many forks of mutually
 /// exclusive actions.
-pub trait JournalDB: KeyedHashDB {
+pub trait JournalDB<H: DBHasher>: KeyedHashDB<H> {
     /// Return a copy of ourself, in a box.
-    fn boxed_clone(&self) -> Box<dyn JournalDB>;
+    fn boxed_clone(&self) -> Box<dyn JournalDB<H>>;
 
     /// Returns heap memory size used
     fn get_sizes(&self, sizes: &mut BTreeMap<String, usize>);
@@ -95,7 +95,7 @@ pub trait JournalDB: KeyedHashDB {
     fn flush(&self) {}
 
     /// Consolidate all the insertions and deletions in the given memory overlay.
-    fn consolidate(&mut self, overlay: ::memory_db::MemoryDB<DBHasher, DBValue>);
+    fn consolidate(&mut self, overlay: ::memory_db::MemoryDB<H, DBValue>);
 
     /// State data query
     fn state(&self, id: &H256) -> Option<Bytes>;
@@ -17,6 +17,7 @@
 //! Façade crate for `patricia_trie` for Ethereum specific impls
 
 extern crate blake2_hasher;
+extern crate blake3_hasher;
 extern crate elastic_array;
 extern crate ethereum_types;
 extern crate hash_db;
@@ -28,7 +29,7 @@ pub extern crate trie_db as trie; // `pub` because we need to import this crate
 
 mod rlp_node_codec;
 
-pub use rlp_node_codec::RlpNodeCodec;
+pub use rlp_node_codec::{HashedNullNode, RlpNodeCodec};
 
 use ethereum_types::H256;
 use keccak_hasher::KeccakHasher;
@@ -64,7 +65,7 @@ pub type RlpCodec = RlpNodeCodec<KeccakHasher>;
 /// type DBValue = ElasticArray128<u8>;
 ///
 /// fn main() {
-///   let mut memdb = journaldb::new_memory_db();
+///   let mut memdb = journaldb::new_memory_db::<KeccakHasher>();
 ///   let mut root = H256::default();
 ///   TrieDBMut::new(&mut memdb, &mut root).insert(b"foo", b"bar").unwrap();
 ///   let t = TrieDB::new(&memdb, &root).unwrap();
@@ -108,7 +109,7 @@ pub type FatDB<'db> = trie::FatDB<'db, KeccakHasher, RlpCodec>;
 /// type DBValue = ElasticArray128<u8>;
 ///
 /// fn main() {
-///   let mut memdb = journaldb::new_memory_db();
+///   let mut memdb = journaldb::new_memory_db::<KeccakHasher>();
 ///   let mut root = H256::default();
 ///   let mut t = TrieDBMut::new(&mut memdb, &mut root);
 ///   assert!(t.is_empty());
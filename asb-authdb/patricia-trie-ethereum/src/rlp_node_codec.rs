@@ -17,6 +17,7 @@
 //! `NodeCodec` implementation for Rlp
 
 use blake2_hasher::{Blake2bHasher, Blake2sHasher};
+use blake3_hasher::Blake3Hasher;
 use elastic_array::ElasticArray128;
 use ethereum_types::H256;
 use hash_db::Hasher;
@@ -32,31 +33,43 @@ pub struct RlpNodeCodec<H: Hasher> {
 }
 
 pub trait HashedNullNode: Hasher {
-    const HASHED_NULL_NODE: H256;
+    fn hashed_null_node() -> H256;
 }
 
 impl HashedNullNode for KeccakHasher {
-    const HASHED_NULL_NODE: H256 = H256([
-        0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8,
-        0x6e, 0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63,
-        0xb4, 0x21,
-    ]);
+    fn hashed_null_node() -> H256 {
+        H256([
+            0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0,
+            0xf8, 0x6e, 0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5,
+            0xe3, 0x63, 0xb4, 0x21,
+        ])
+    }
 }
 
 impl HashedNullNode for Blake2sHasher {
-    const HASHED_NULL_NODE: H256 = H256([
-        0xfc, 0x84, 0x47, 0xd5, 0x56, 0x41, 0xbe, 0xee, 0x0c, 0x65, 0xd3, 0x85, 0xe8, 0xd8, 0x53,
-        0x9a, 0xbe, 0x95, 0x13, 0xc2, 0x3b, 0x2a, 0x0a, 0xea, 0xef, 0x1f, 0x29, 0x91, 0xd6, 0x91,
-        0xc6, 0x27,
-    ]);
+    fn hashed_null_node() -> H256 {
+        H256([
+            0xfc, 0x84, 0x47, 0xd5, 0x56, 0x41, 0xbe, 0xee, 0x0c, 0x65, 0xd3, 0x85, 0xe8, 0xd8,
+            0x53, 0x9a, 0xbe, 0x95, 0x13, 0xc2, 0x3b, 0x2a, 0x0a, 0xea, 0xef, 0x1f, 0x29, 0x91,
+            0xd6, 0x91, 0xc6, 0x27,
+        ])
+    }
 }
 
 impl HashedNullNode for Blake2bHasher {
-    const HASHED_NULL_NODE: H256 = H256([
-        0x24, 0xfc, 0x3b, 0x2d, 0x20, 0x85, 0x2c, 0xa3, 0xf2, 0x66, 0x06, 0xcb, 0x64, 0x9f, 0xa0,
-        0x1c, 0x76, 0x8e, 0xf1, 0xe7, 0xb7, 0x3c, 0x1f, 0xb1, 0x01, 0x1c, 0xba, 0xb1, 0xb9, 0xd4,
-        0xd3, 0x40,
-    ]);
+    fn hashed_null_node() -> H256 {
+        H256([
+            0x24, 0xfc, 0x3b, 0x2d, 0x20, 0x85, 0x2c, 0xa3, 0xf2, 0x66, 0x06, 0xcb, 0x64, 0x9f,
+            0xa0, 0x1c, 0x76, 0x8e, 0xf1, 0xe7, 0xb7, 0x3c, 0x1f, 0xb1, 0x01, 0x1c, 0xba, 0xb1,
+            0xb9, 0xd4, 0xd3, 0x40,
+        ])
+    }
+}
+
+impl HashedNullNode for Blake3Hasher {
+    fn hashed_null_node() -> H256 {
+        Blake3Hasher::hash(&rlp::NULL_RLP)
+    }
 }
 
 // NOTE: what we'd really like here is:
@@ -66,7 +79,7 @@ impl HashedNullNode for Blake2bHasher {
 impl<H: Hasher<Out = H256> + HashedNullNode> NodeCodec<H> for RlpNodeCodec<H> {
     type Error = DecoderError;
     fn hashed_null_node() -> H::Out {
-        H::HASHED_NULL_NODE
+        H::hashed_null_node()
     }
     fn decode(data: &[u8]) -> ::std::result::Result<Node, Self::Error> {
         let r = Rlp::new(data);
@@ -0,0 +1,93 @@
+#![feature(test)]
+extern crate test;
+
+use keccak_hasher::KeccakHasher;
+use kvdb::KeyValueDB;
+use rainblock_trie::MerklePatriciaTree;
+use rand::prelude::*;
+use std::sync::{Arc, Mutex};
+use test::{black_box, Bencher};
+
+const KEYS: usize = 100_000;
+const READS: usize = 4096;
+const DEPTH: usize = 3;
+
+type Trie = MerklePatriciaTree<DEPTH, KeccakHasher>;
+
+fn prepare() -> (Trie, Vec<Vec<u8>>) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(1));
+
+    let mut trie = Trie::new(db.clone());
+    let keys: Vec<Vec<u8>> = (0..KEYS).map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+    for key in &keys {
+        trie.put(key.clone(), key.clone());
+    }
+    trie.commit().unwrap();
+
+    let reads = keys.choose_multiple(&mut rng, READS).cloned().collect();
+    (Trie::new(db), reads)
+}
+
+// Each thread reads a share of the keys. The nodes below the top layer are dropped on commit, so
+// the reads load them from db again in every iteration.
+fn bench_shared_reads(b: &mut Bencher, threads: usize) {
+    let (mut trie, reads) = prepare();
+    b.iter(|| {
+        trie.commit().unwrap();
+        let trie = &trie;
+        std::thread::scope(|s| {
+            for chunk in reads.chunks(READS / threads) {
+                s.spawn(move || {
+                    for key in chunk {
+                        black_box(trie.get(key.clone()));
+                    }
+                });
+            }
+        });
+    });
+}
+
+// The baseline where the readers take turns to access the trie, as with an exclusive lock.
+fn bench_exclusive_reads(b: &mut Bencher, threads: usize) {
+    let (trie, reads) = prepare();
+    let trie = Mutex::new(trie);
+    b.iter(|| {
+        trie.lock().unwrap().commit().unwrap();
+        let trie = &trie;
+        std::thread::scope(|s| {
+            for chunk in reads.chunks(READS / threads) {
+                s.spawn(move || {
+                    for key in chunk {
+                        black_box(trie.lock().unwrap().get(key.clone()));
+                    }
+                });
+            }
+        });
+    });
+}
+
+#[bench]
+fn bench_read_1_thread(b: &mut Bencher) {
+    bench_shared_reads(b, 1);
+}
+
+#[bench]
+fn bench_read_4_threads(b: &mut Bencher) {
+    bench_shared_reads(b, 4);
+}
+
+#[bench]
+fn bench_read_8_threads(b: &mut Bencher) {
+    bench_shared_reads(b, 8);
+}
+
+#[bench]
+fn bench_read_4_threads_exclusive(b: &mut Bencher) {
+    bench_exclusive_reads(b, 4);
+}
+
+#[bench]
+fn bench_read_8_threads_exclusive(b: &mut Bencher) {
+    bench_exclusive_reads(b, 8);
+}
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use rainblock_trie::remote::StorageNode;
+
+const USAGE: &str = "Usage: rain-node <host:port | unix:path> [--latency-us <microseconds>]";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut address = None;
+    let mut latency = Duration::ZERO;
+    while let Some(arg) = args.next() {
+        if arg == "--latency-us" {
+            let value = args.next().and_then(|x| x.parse().ok()).expect(USAGE);
+            latency = Duration::from_micros(value);
+        } else if address.is_none() {
+            address = Some(arg);
+        } else {
+            panic!("{}", USAGE);
+        }
+    }
+    let address = address.expect(USAGE);
+
+    println!("Storage node listening on {} with latency {:?}", address, latency);
+    StorageNode::new(latency).serve(&address).unwrap();
+}
@@ -6,6 +6,7 @@ use std::{
 
 use crate::{
     nibble::Nibble, trie_node::TrieNode, trie_node_ext::TrieNodeExt, NodePtr, NodePtrWeak,
+    RlpHasher,
 };
 use ethereum_types::H256;
 use kvdb::KeyValueDB;
@@ -14,14 +15,14 @@ use rlp::{Decodable, Encodable};
 type Bytes = Vec<u8>;
 
 #[derive(Clone, Default)]
-pub enum ChildRef {
+pub enum ChildRef<H: RlpHasher> {
     #[default]
     Null,
     Ref(H256),
-    Owned(NodePtr),
+    Owned(NodePtr<H>),
 }
 
-impl PartialEq for ChildRef {
+impl<H: RlpHasher> PartialEq for ChildRef<H> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Null, Self::Null) => true,
@@ -32,16 +33,16 @@ impl PartialEq for ChildRef {
     }
 }
 
-impl Eq for ChildRef {}
+impl<H: RlpHasher> Eq for ChildRef<H> {}
 
-pub type ChildRefCell = RefCell<ChildRef>;
+pub type ChildRefCell<H> = RefCell<ChildRef<H>>;
 
-impl ChildRef {
+impl<H: RlpHasher> ChildRef<H> {
     pub fn is_null(&self) -> bool {
         *self == ChildRef::Null
     }
 
-    pub fn owned_mut(&mut self) -> Option<&mut NodePtr> {
+    pub fn owned_mut(&mut self) -> Option<&mut NodePtr<H>> {
         if let ChildRef::Owned(node) = self {
             Some(node)
         } else {
@@ -49,7 +50,7 @@ impl ChildRef {
         }
     }
 
-    pub fn loaded_mut(&mut self, db: &Arc<dyn KeyValueDB>) -> Option<&mut NodePtr> {
+    pub fn loaded_mut(&mut self, db: &Arc<dyn KeyValueDB>) -> Option<&mut NodePtr<H>> {
         if let ChildRef::Ref(digest) = self {
             let node = TrieNodeExt::load(db, digest.clone()).seal();
             *self = ChildRef::Owned(node);
@@ -62,9 +63,9 @@ impl ChildRef {
     }
 
     pub fn owned_or_load(
-        me: &RefCell<ChildRef>,
+        me: &RefCell<ChildRef<H>>,
         db: &Arc<dyn KeyValueDB>,
-    ) -> Option<(NodePtr, bool)> {
+    ) -> Option<(NodePtr<H>, bool)> {
         let borrowed_ref = me.borrow();
         match &*borrowed_ref {
             ChildRef::Null => None,
@@ -80,7 +81,7 @@ impl ChildRef {
     }
 
     #[inline]
-    pub fn truncate(me: &RefCell<ChildRef>) {
+    pub fn truncate(me: &RefCell<ChildRef<H>>) {
         let mut replaced_hash = None;
         if let Self::Owned(node) = &*me.borrow() {
             if node.as_ref().is_small_node() {
@@ -94,7 +95,7 @@ impl ChildRef {
     }
 
     #[inline]
-    pub fn exile<const N: usize>(&self, depth: usize, exile_nodes: &mut Vec<NodePtrWeak>) {
+    pub fn exile<const N: usize>(&self, depth: usize, exile_nodes: &mut Vec<NodePtrWeak<H>>) {
         if let Self::Owned(node) = self {
             TrieNodeExt::exile::<N>(node, depth, exile_nodes);
         }
@@ -113,7 +114,6 @@ impl ChildRef {
     }
 
     #[inline]
-    #[cfg(test)]
     pub fn loaded_nodes_count(me: &RefCell<Self>) -> usize {
         if let Self::Owned(node) = &*me.borrow() {
             if node.as_ref().get_rlp_encode().len() >= 32 {
@@ -126,6 +126,15 @@ impl ChildRef {
         }
     }
 
+    #[inline]
+    pub fn loaded_nodes_size(me: &RefCell<Self>) -> usize {
+        if let Self::Owned(node) = &*me.borrow() {
+            node.as_ref().loaded_nodes_size()
+        } else {
+            0
+        }
+    }
+
     #[inline]
     #[cfg(test)]
     pub fn print_node(me: &RefCell<Self>, ident: usize) {
@@ -141,7 +150,7 @@ impl ChildRef {
     }
 }
 
-impl Encodable for ChildRef {
+impl<H: RlpHasher> Encodable for ChildRef<H> {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
         match self {
             ChildRef::Null => Bytes::new().rlp_append(s),
@@ -157,7 +166,7 @@ impl Encodable for ChildRef {
         }
     }
 }
-impl Decodable for ChildRef {
+impl<H: RlpHasher> Decodable for ChildRef<H> {
     fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
         Ok(if rlp.is_empty() {
             ChildRef::Null
@@ -172,10 +181,10 @@ impl Decodable for ChildRef {
 }
 
 #[derive(Default, Clone, Eq, PartialEq)]
-pub struct ChildRefGroup([ChildRefCell; 16]);
+pub struct ChildRefGroup<H: RlpHasher>([ChildRefCell<H>; 16]);
 
-impl ChildRefGroup {
-    pub fn enumerate_mut(&mut self) -> impl Iterator<Item = (Nibble, &mut ChildRef)> {
+impl<H: RlpHasher> ChildRefGroup<H> {
+    pub fn enumerate_mut(&mut self) -> impl Iterator<Item = (Nibble, &mut ChildRef<H>)> {
         self.0
             .iter_mut()
             .enumerate()
@@ -190,7 +199,7 @@ impl ChildRefGroup {
         self.0.iter().filter(|cell| !cell.borrow().is_null()).count()
     }
 
-    pub fn only_child_mut(&mut self) -> Option<(Nibble, &mut ChildRef)> {
+    pub fn only_child_mut(&mut self) -> Option<(Nibble, &mut ChildRef<H>)> {
         let mut non_null_child = None;
         for (idx, _) in self.enumerate_mut().filter(|(_, child)| !child.is_null()) {
             if non_null_child.is_some() {
@@ -204,22 +213,22 @@ impl ChildRefGroup {
     }
 }
 
-impl Deref for ChildRefGroup {
-    type Target = [ChildRefCell; 16];
+impl<H: RlpHasher> Deref for ChildRefGroup<H> {
+    type Target = [ChildRefCell<H>; 16];
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for ChildRefGroup {
+impl<H: RlpHasher> DerefMut for ChildRefGroup<H> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl Index<Nibble> for ChildRefGroup {
-    type Output = ChildRefCell;
+impl<H: RlpHasher> Index<Nibble> for ChildRefGroup<H> {
+    type Output = ChildRefCell<H>;
 
     fn index(&self, index: Nibble) -> &Self::Output {
         // SAFETY: a nibble must belongs to [0, 16)
@@ -227,7 +236,7 @@ impl Index<Nibble> for ChildRefGroup {
     }
 }
 
-impl IndexMut<Nibble> for ChildRefGroup {
+impl<H: RlpHasher> IndexMut<Nibble> for ChildRefGroup<H> {
     fn index_mut(&mut self, index: Nibble) -> &mut Self::Output {
         // SAFETY: a nibble must belongs to [0, 16)
         unsafe { self.0.get_unchecked_mut(index.inner() as usize) }
@@ -1,6 +1,10 @@
 mod child_ref;
 mod nibble;
+mod node_loader;
+mod proof;
+mod pruner;
 mod rain_mpt;
+pub mod remote;
 #[cfg(test)]
 mod tests;
 mod trie_node;
@@ -11,13 +15,14 @@ mod thread_non_safe;
 #[cfg(not(feature = "thread-safe"))]
 pub use thread_non_safe::{Node, NodePtr, NodePtrWeak};
 #[cfg(not(feature = "thread-safe"))]
-unsafe impl<const N: usize> Send for MerklePatriciaTree<N> {} // As internal Rc does not expose to outsize, it is safe to declare it as Send.
+unsafe impl<const N: usize, H: RlpHasher> Send for MerklePatriciaTree<N, H> {} // As internal Rc does not expose to outsize, it is safe to declare it as Send.
 #[cfg(feature = "thread-safe")]
 mod thread_safe;
 #[cfg(feature = "thread-safe")]
 pub use thread_safe::{Node, NodePtr, NodePtrWeak};
 
-pub use rain_mpt::MerklePatriciaTree;
+pub use proof::{verify_proof, ProofError};
+pub use rain_mpt::{MerklePatriciaTree, EMPTY_ROOT};
 
 fn common_prefix_iter<'a, T: Eq>(a: &'a [T], b: &'a [T]) -> impl Iterator<Item = &'a T> {
     a.iter()
@@ -30,7 +35,11 @@ fn add_prefix<T: Copy>(base: &mut Vec<T>, prefix: &[T]) {
     *base = [prefix, &base[..]].concat()
 }
 
-#[cfg(feature = "light-hash")]
-pub use blake2_hasher::Blake2bHasher as RlpHasher;
-#[cfg(not(feature = "light-hash"))]
-pub use keccak_hasher::KeccakHasher as RlpHasher;
+use ethereum_types::H256;
+use hash_db::Hasher;
+
+/// The hasher of the trie nodes. Each trie takes it as a type parameter, so the tries in one
+/// process may hash with different functions.
+pub trait RlpHasher: Hasher<Out = H256> + Default + Clone + PartialEq + 'static {}
+
+impl<H: Hasher<Out = H256> + Default + Clone + PartialEq + 'static> RlpHasher for H {}
@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+
+use ethereum_types::H256;
+use kvdb::KeyValueDB;
+
+use crate::{
+    child_ref::ChildRef, nibble::Nibble, trie_node_ext::TrieNodeExt, NodePtr, NodePtrWeak,
+    RlpHasher,
+};
+
+const STRIPES: usize = 64;
+
+/// Loads the children of the nodes in memory from db, which only needs a shared reference to the
+/// trie. The parent is not locked during reading db, so the readers in different threads load
+/// nodes in parallel. The loads of the same node are serialized by a lock stripe on its hash, so
+/// the node is read only once.
+pub struct NodeLoader<H: RlpHasher> {
+    stripes: Vec<Mutex<()>>,
+    loaded_nodes: Mutex<Vec<NodePtrWeak<H>>>,
+}
+
+impl<H: RlpHasher> Default for NodeLoader<H> {
+    fn default() -> Self {
+        Self {
+            stripes: (0..STRIPES).map(|_| Mutex::new(())).collect(),
+            loaded_nodes: Default::default(),
+        }
+    }
+}
+
+impl<H: RlpHasher> NodeLoader<H> {
+    /// Returns the child of the parent at the index, and whether it is loaded in this call. The
+    /// child must not be null.
+    pub fn load_child(
+        &self,
+        parent: &NodePtr<H>,
+        index: Nibble,
+        db: &Arc<dyn KeyValueDB>,
+    ) -> (NodePtr<H>, bool) {
+        let digest = match Self::owned_child(parent, index) {
+            Ok(node) => return (node, false),
+            Err(digest) => digest,
+        };
+
+        let _stripe = self.stripes[digest.0[0] as usize % STRIPES].lock().unwrap();
+        // Another reader may have loaded the child while waiting for the stripe.
+        let digest = match Self::owned_child(parent, index) {
+            Ok(node) => return (node, false),
+            Err(digest) => digest,
+        };
+
+        let node = TrieNodeExt::load(db, digest).seal();
+        *parent.as_ref().child(index).unwrap().borrow_mut() = ChildRef::Owned(node.clone());
+        (node, true)
+    }
+
+    fn owned_child(parent: &NodePtr<H>, index: Nibble) -> Result<NodePtr<H>, H256> {
+        match &*parent.as_ref().child(index).unwrap().borrow() {
+            ChildRef::Owned(node) => Ok(node.clone()),
+            ChildRef::Ref(digest) => Err(*digest),
+            ChildRef::Null => unreachable!("Load a null child"),
+        }
+    }
+
+    /// Records a node whose children are loaded beyond the top layer, which are truncated on
+    /// commit.
+    pub fn push_loaded(&self, node: &NodePtr<H>) {
+        self.loaded_nodes
+            .lock()
+            .unwrap()
+            .push(NodePtr::downgrade(node));
+    }
+
+    pub fn drain_loaded(&self) -> Vec<NodePtrWeak<H>> {
+        std::mem::take(&mut *self.loaded_nodes.lock().unwrap())
+    }
+}
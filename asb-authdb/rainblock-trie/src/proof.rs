@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use ethereum_types::H256;
+use rlp::{Decodable, Rlp};
+
+use crate::{
+    child_ref::ChildRef,
+    nibble::bytes_to_nibble_list,
+    rain_mpt::EMPTY_ROOT,
+    trie_node::TrieNode,
+    RlpHasher,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProofError {
+    /// The proof does not contain the node with the given hash on the path of the key.
+    MissingNode(H256),
+    /// A node in the proof is not a valid RLP-encoded trie node.
+    InvalidNode(rlp::DecoderError),
+    /// The key is empty, which the trie does not support.
+    EmptyKey,
+}
+
+fn decode_node<H: RlpHasher>(data: &[u8]) -> Result<TrieNode<H>, ProofError> {
+    TrieNode::decode(&Rlp::new(data)).map_err(ProofError::InvalidNode)
+}
+
+/// Checks a proof generated by `MerklePatriciaTree::prove` against the root. Returns the value of
+/// the key, or `None` if the proof shows the key does not exist.
+pub fn verify_proof<H: RlpHasher>(
+    root: H256,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, ProofError> {
+    if key.is_empty() {
+        return Err(ProofError::EmptyKey);
+    }
+    if root == EMPTY_ROOT {
+        return Ok(None);
+    }
+
+    let nodes: HashMap<H256, &[u8]> = proof
+        .iter()
+        .map(|node| (H::hash(node), &node[..]))
+        .collect();
+    let load = |digest: H256| -> Result<TrieNode<H>, ProofError> {
+        let data = nodes.get(&digest).ok_or(ProofError::MissingNode(digest))?;
+        decode_node(data)
+    };
+
+    let mut node = load(root)?;
+    let nibbles = bytes_to_nibble_list(key.to_vec());
+    let mut remainder = &nibbles[..];
+    loop {
+        let next = match node {
+            TrieNode::Leaf { key, value } => {
+                return Ok(if key[..] == remainder[..] {
+                    Some(value)
+                } else {
+                    None
+                });
+            }
+            TrieNode::Branch {
+                mut children,
+                value,
+            } => {
+                if let Some((&idx, rest)) = remainder.split_first() {
+                    remainder = rest;
+                    std::mem::take(children[idx].get_mut())
+                } else {
+                    return Ok(if value.is_empty() { None } else { Some(value) });
+                }
+            }
+            TrieNode::Extension { key, child } => {
+                if remainder.starts_with(&key) {
+                    remainder = &remainder[key.len()..];
+                    child.into_inner()
+                } else {
+                    return Ok(None);
+                }
+            }
+        };
+
+        node = match next {
+            ChildRef::Null => return Ok(None),
+            ChildRef::Ref(digest) => load(digest)?,
+            // A small node is embedded in its parent.
+            ChildRef::Owned(embedded) => (**embedded.as_ref()).clone(),
+        };
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use ethereum_types::H256;
+use kvdb::{DBTransaction, KeyValueDB};
+
+// The reference count of a node is stored in column 0 with the key `REFCOUNT_PREFIX ++ hash`,
+// which never conflicts with the node keys (32 bytes) and the root key (1 byte).
+const REFCOUNT_PREFIX: u8 = b'r';
+const PRUNE_BATCH: usize = 1024;
+
+fn refcount_key(digest: &H256) -> Vec<u8> {
+    [&[REFCOUNT_PREFIX][..], &digest.0[..]].concat()
+}
+
+// The reference count and the size of each node, or `None` if the node is not on disk.
+type Refcounts = HashMap<H256, Option<(u32, u32)>>;
+
+enum Message {
+    Prune(Vec<(H256, u32)>),
+    Sync(mpsc::Sender<()>),
+}
+
+struct Shared {
+    db: Arc<dyn KeyValueDB>,
+    // The reference counts written but not flushed yet, which the backend may not return until
+    // flushed. The lock also serializes the updates in commit and the deletions in background.
+    pending: Mutex<Refcounts>,
+    pruned_nodes: AtomicU64,
+    pruned_bytes: AtomicU64,
+}
+
+impl Shared {
+    fn run(&self, receiver: mpsc::Receiver<Message>) {
+        for message in receiver {
+            match message {
+                Message::Prune(nodes) => {
+                    for chunk in nodes.chunks(PRUNE_BATCH) {
+                        // The nodes failed to delete are left on disk.
+                        if let Err(e) = self.prune(chunk) {
+                            eprintln!("Cannot prune nodes: {}", e);
+                        }
+                    }
+                }
+                Message::Sync(sender) => {
+                    let _ = sender.send(());
+                }
+            }
+        }
+    }
+
+    // Returns the reference count and the size of the node.
+    fn read_refcount(&self, pending: &Refcounts, digest: &H256) -> io::Result<Option<(u32, u32)>> {
+        if let Some(refcount) = pending.get(digest) {
+            return Ok(*refcount);
+        }
+        Ok(self.db.get(0, &refcount_key(digest))?.map(|value| {
+            let count = u32::from_le_bytes(value[0..4].try_into().unwrap());
+            let size = u32::from_le_bytes(value[4..8].try_into().unwrap());
+            (count, size)
+        }))
+    }
+
+    fn prune(&self, nodes: &[(H256, u32)]) -> io::Result<()> {
+        let pending = self.pending.lock().unwrap();
+        let mut transaction = DBTransaction::new();
+        let (mut pruned_nodes, mut pruned_bytes) = (0, 0);
+        for (digest, size) in nodes {
+            // The node may be written again after it is released.
+            if self.read_refcount(&pending, digest)?.is_some() {
+                continue;
+            }
+            transaction.delete(0, &digest.0);
+            pruned_nodes += 1;
+            pruned_bytes += *size as u64;
+        }
+        self.db.write(transaction)?;
+
+        self.pruned_nodes.fetch_add(pruned_nodes, Ordering::Relaxed);
+        self.pruned_bytes.fetch_add(pruned_bytes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Reference counts the nodes on disk, and keeps the nodes obsoleted in the last `retain` commits,
+/// so the tries of the last `retain + 1` roots remain on disk. The nodes released from all the
+/// retained tries are deleted in background. The retained history lives in memory only, so the
+/// nodes pending in it are not pruned after reopening the trie.
+pub struct Pruner {
+    retain: usize,
+    history: VecDeque<Vec<H256>>,
+    shared: Arc<Shared>,
+    sender: Mutex<Option<mpsc::Sender<Message>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Pruner {
+    pub fn new(db: Arc<dyn KeyValueDB>, retain: usize) -> Self {
+        let shared = Arc::new(Shared {
+            db,
+            pending: Mutex::new(HashMap::new()),
+            pruned_nodes: AtomicU64::new(0),
+            pruned_bytes: AtomicU64::new(0),
+        });
+        let (sender, receiver) = mpsc::channel();
+        let handle = {
+            let shared = shared.clone();
+            thread::spawn(move || shared.run(receiver))
+        };
+        Self {
+            retain,
+            history: VecDeque::new(),
+            shared,
+            sender: Mutex::new(Some(sender)),
+            handle: Some(handle),
+        }
+    }
+
+    /// Writes the new nodes and the reference counts, and releases the nodes obsoleted `retain`
+    /// commits ago. The writes are buffered, and `flushed` must be called after flushing them.
+    pub fn commit(
+        &mut self,
+        put_ops: Vec<(H256, Vec<u8>)>,
+        obsolete: Vec<H256>,
+    ) -> io::Result<()> {
+        self.history.push_back(obsolete);
+        let mut expired = Vec::new();
+        while self.history.len() > self.retain {
+            expired.extend(self.history.pop_front().unwrap());
+        }
+        self.update(put_ops, expired)
+    }
+
+    /// Writes the nodes out of a commit, e.g., the top layer in flushing.
+    pub fn write_nodes(&mut self, put_ops: Vec<(H256, Vec<u8>)>) -> io::Result<()> {
+        self.update(put_ops, vec![])
+    }
+
+    /// Forgets the pending reference counts once the database is flushed.
+    pub fn flushed(&self) {
+        self.shared.pending.lock().unwrap().clear();
+    }
+
+    fn update(&self, put_ops: Vec<(H256, Vec<u8>)>, expired: Vec<H256>) -> io::Result<()> {
+        let shared = &*self.shared;
+        let mut pending = shared.pending.lock().unwrap();
+
+        // The reference counts updated in this commit.
+        let mut refcounts = Refcounts::new();
+        let mut transaction = DBTransaction::new();
+
+        for (digest, rlp) in put_ops {
+            let refcount = match refcounts.get(&digest) {
+                Some(refcount) => *refcount,
+                None => shared.read_refcount(&pending, &digest)?,
+            };
+            let (count, _) = refcount.unwrap_or((0, 0));
+            refcounts.insert(digest, Some((count + 1, rlp.len() as u32)));
+            if count == 0 {
+                transaction.put_vec(0, &digest.0, rlp);
+            }
+        }
+
+        let mut released = Vec::new();
+        for digest in expired {
+            let refcount = match refcounts.get(&digest) {
+                Some(refcount) => *refcount,
+                None => shared.read_refcount(&pending, &digest)?,
+            };
+            match refcount {
+                // The node is in the top layer and has never been written.
+                None => {}
+                Some((1, size)) => {
+                    refcounts.insert(digest, None);
+                    released.push((digest, size));
+                }
+                Some((count, size)) => {
+                    refcounts.insert(digest, Some((count - 1, size)));
+                }
+            }
+        }
+
+        for (digest, refcount) in refcounts {
+            if let Some((count, size)) = refcount {
+                let value = [count.to_le_bytes(), size.to_le_bytes()].concat();
+                transaction.put_vec(0, &refcount_key(&digest), value);
+            } else {
+                transaction.delete(0, &refcount_key(&digest));
+            }
+            pending.insert(digest, refcount);
+        }
+        shared.db.write_buffered(transaction);
+
+        if !released.is_empty() {
+            self.send(Message::Prune(released));
+        }
+        Ok(())
+    }
+
+    /// Waits for the pending deletions.
+    pub fn sync(&self) {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Message::Sync(sender));
+        let _ = receiver.recv();
+    }
+
+    pub fn pruned_nodes(&self) -> u64 {
+        self.shared.pruned_nodes.load(Ordering::Relaxed)
+    }
+
+    pub fn pruned_bytes(&self) -> u64 {
+        self.shared.pruned_bytes.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, message: Message) {
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            sender.send(message).expect("The pruning thread exits");
+        }
+    }
+}
+
+impl Drop for Pruner {
+    fn drop(&mut self) {
+        // Close the channel, so the pruning thread exits after the pending deletions.
+        self.sender.get_mut().unwrap().take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
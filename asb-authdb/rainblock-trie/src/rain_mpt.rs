@@ -1,4 +1,4 @@
-use std::{io, sync::Arc};
+use std::{collections::HashSet, io, sync::Arc};
 
 use ethereum_types::H256;
 use kvdb::{DBOp, DBTransaction, KeyValueDB};
@@ -8,29 +8,35 @@ use crate::{
     child_ref::{ChildRef, ChildRefGroup},
     common_prefix_iter,
     nibble::{bytes_to_nibble_list, Nibble},
+    node_loader::NodeLoader,
+    pruner::Pruner,
     trie_node::{NextResult, TrieNode},
     trie_node_ext::TrieNodeExt,
-    NodePtrWeak,
+    NodePtrWeak, RlpHasher,
 };
 
 use crate::NodePtr;
 
-pub struct MerklePatriciaTree<const TOP_LAYER_DEPTH: usize> {
+pub struct MerklePatriciaTree<const TOP_LAYER_DEPTH: usize, H: RlpHasher> {
     pub db: Arc<dyn KeyValueDB>,
-    root: Option<NodePtr>,
+    root: Option<NodePtr<H>>,
     del_ops: Vec<H256>,
-    loaded_node: Vec<NodePtrWeak>,
-    exile_nodes: Vec<NodePtrWeak>,
+    loader: NodeLoader<H>,
+    exile_nodes: Vec<NodePtrWeak<H>>,
+    pruner: Option<Pruner>,
+    // The top-layer nodes written by `flush_all` and counted by the pruner. They are not counted
+    // again when flushed again or exiled, until they become obsolete.
+    flushed: HashSet<H256>,
 }
 
-struct SearchResult {
-    stack: Vec<(NodePtr, Option<Nibble>)>,
+struct SearchResult<H: RlpHasher> {
+    stack: Vec<(NodePtr<H>, Option<Nibble>)>,
     matched: bool,
     remainder: Vec<Nibble>,
 }
 
-impl SearchResult {
-    fn matched(&self) -> Option<&NodePtr> {
+impl<H: RlpHasher> SearchResult<H> {
+    fn matched(&self) -> Option<&NodePtr<H>> {
         if self.matched {
             Some(&self.stack.last().unwrap().0)
         } else {
@@ -38,7 +44,7 @@ impl SearchResult {
         }
     }
 
-    fn matched_mut(&mut self) -> Option<&mut NodePtr> {
+    fn matched_mut(&mut self) -> Option<&mut NodePtr<H>> {
         if self.matched {
             Some(&mut self.stack.last_mut().unwrap().0)
         } else {
@@ -50,8 +56,8 @@ impl SearchResult {
 const ROOT_KEY: [u8; 1] = [0x80];
 pub const EMPTY_ROOT: H256 = H256::zero();
 
-impl<const N: usize> MerklePatriciaTree<N> {
-    pub fn new(db: Arc<dyn KeyValueDB>) -> MerklePatriciaTree<N> {
+impl<const N: usize, H: RlpHasher> MerklePatriciaTree<N, H> {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> MerklePatriciaTree<N, H> {
         let root = if let Some(digest) = db.get(0, &ROOT_KEY).expect("Cannot load db") {
             let digest = H256::from_slice(&digest);
             let node = TrieNodeExt::load(&db, digest).seal();
@@ -65,24 +71,36 @@ impl<const N: usize> MerklePatriciaTree<N> {
             root,
             db,
             del_ops: vec![],
-            loaded_node: vec![],
+            loader: Default::default(),
             exile_nodes: vec![],
+            pruner: None,
+            flushed: HashSet::new(),
         }
     }
 
+    /// Opens the trie with pruning. The obsolete nodes are kept for the last `retain` commits,
+    /// and then deleted in background if no retained trie refers to them.
+    pub fn with_retention(db: Arc<dyn KeyValueDB>, retain: usize) -> MerklePatriciaTree<N, H> {
+        let mut trie = Self::new(db.clone());
+        trie.pruner = Some(Pruner::new(db, retain));
+        trie
+    }
+
     pub fn root(&self) -> Option<H256> {
         self.root.as_ref().map(|x| x.as_ref().hash())
     }
 
-    pub fn get(&mut self, key: Vec<u8>) -> Option<Vec<u8>> {
+    /// Reads the key. The nodes below the top layer are loaded on demand, so concurrent readers
+    /// are allowed with the feature `thread-safe`.
+    pub fn get(&self, key: Vec<u8>) -> Option<Vec<u8>> {
         if key.is_empty() {
             panic!("Empty key is not supported")
         }
         if self.root.is_none() {
             return None;
         }
-        let mut result = self.search(key);
-        if let Some(matched_node) = result.matched_mut() {
+        let result = self.search(key);
+        if let Some(matched_node) = result.matched() {
             if let Some(value) = matched_node.as_ref().value() {
                 return Some(value.clone());
             }
@@ -132,6 +150,9 @@ impl<const N: usize> MerklePatriciaTree<N> {
         for node in self.exile_nodes.drain(..) {
             if let Some(node) = node.upgrade() {
                 let hash = node.as_ref().hash();
+                if self.flushed.remove(&hash) {
+                    continue;
+                }
                 let rlp = node.as_ref().get_rlp_encode();
                 put_ops.push((hash, rlp))
             }
@@ -148,12 +169,20 @@ impl<const N: usize> MerklePatriciaTree<N> {
             })
         }
 
-        for node in self.loaded_node.drain(..) {
+        for node in self.loader.drain_loaded() {
             if let Some(node) = node.upgrade() {
                 node.as_ref().truncate();
             }
         }
 
+        if let Some(pruner) = &mut self.pruner {
+            let del_ops = std::mem::take(&mut self.del_ops);
+            for digest in &del_ops {
+                self.flushed.remove(digest);
+            }
+            pruner.commit(std::mem::take(&mut put_ops), del_ops)?;
+        }
+
         for del_op in self.del_ops.drain(..) {
             self.db.write_buffered(DBTransaction {
                 ops: vec![DBOp::Delete {
@@ -187,6 +216,9 @@ impl<const N: usize> MerklePatriciaTree<N> {
         };
 
         self.db.flush()?;
+        if let Some(pruner) = &self.pruner {
+            pruner.flushed();
+        }
 
         Ok(hash)
     }
@@ -196,6 +228,11 @@ impl<const N: usize> MerklePatriciaTree<N> {
         if let Some(root) = &self.root {
             root.as_ref().commit::<N>(0, &mut put_ops, true);
         }
+        if let Some(pruner) = &mut self.pruner {
+            let flushed = &mut self.flushed;
+            put_ops.retain(|(digest, _)| flushed.insert(*digest));
+            pruner.write_nodes(std::mem::take(&mut put_ops))?;
+        }
 
         for (key, value) in put_ops.drain(..) {
             self.db.write_buffered(DBTransaction {
@@ -207,10 +244,44 @@ impl<const N: usize> MerklePatriciaTree<N> {
             })
         }
         self.db.flush()?;
+        if let Some(pruner) = &self.pruner {
+            pruner.flushed();
+            pruner.sync();
+        }
         Ok(())
     }
 
-    #[cfg(test)]
+    /// The number of nodes deleted by pruning.
+    pub fn pruned_nodes(&self) -> u64 {
+        self.pruner.as_ref().map_or(0, Pruner::pruned_nodes)
+    }
+
+    /// The total size of the nodes deleted by pruning.
+    pub fn pruned_bytes(&self) -> u64 {
+        self.pruner.as_ref().map_or(0, Pruner::pruned_bytes)
+    }
+
+    /// Returns the RLP encodings of the nodes on the path of the key, starting from the root. The
+    /// small nodes are embedded in their parents and not listed separately. If the key does not
+    /// exist, the path ends at the node showing the divergence. Check it with `verify_proof`.
+    pub fn prove(&self, key: Vec<u8>) -> Vec<Vec<u8>> {
+        if key.is_empty() {
+            panic!("Empty key is not supported")
+        }
+        if self.root.is_none() {
+            return vec![];
+        }
+        let result = self.search(key);
+        result
+            .stack
+            .iter()
+            .enumerate()
+            .filter(|(depth, (node, _))| *depth == 0 || !node.as_ref().is_small_node())
+            .map(|(_, (node, _))| node.as_ref().get_rlp_encode())
+            .collect()
+    }
+
+    /// The number of nodes in memory, excluding the small nodes embedded in their parents.
     pub fn loaded_nodes_count(&self) -> usize {
         if let Some(root) = &self.root {
             root.as_ref().loaded_nodes_count()
@@ -219,6 +290,15 @@ impl<const N: usize> MerklePatriciaTree<N> {
         }
     }
 
+    /// An estimation of the memory (in bytes) taken by the nodes in memory.
+    pub fn loaded_nodes_size(&self) -> usize {
+        if let Some(root) = &self.root {
+            root.as_ref().loaded_nodes_size()
+        } else {
+            0
+        }
+    }
+
     #[cfg(test)]
     pub fn print_trie(&self) {
         if let Some(root) = &self.root {
@@ -229,7 +309,10 @@ impl<const N: usize> MerklePatriciaTree<N> {
         }
     }
 
-    fn del(&mut self, key: Vec<u8>) {
+    pub fn del(&mut self, key: Vec<u8>) {
+        if key.is_empty() {
+            panic!("Empty key is not supported")
+        }
         if self.root.is_none() {
             return;
         }
@@ -245,7 +328,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
     }
 
     // Reset the pointers to the child which will be changed. This can save memory cost in Arc::make_mut
-    fn reset_pointers(&mut self, stack: &mut Vec<(NodePtr, Option<Nibble>)>) {
+    fn reset_pointers(&mut self, stack: &mut Vec<(NodePtr<H>, Option<Nibble>)>) {
         self.root = None;
         for (node, child_idx) in stack.iter_mut() {
             if let Some(&child_idx) = child_idx.as_ref() {
@@ -255,8 +338,8 @@ impl<const N: usize> MerklePatriciaTree<N> {
         }
     }
 
-    fn recover_pointers(&mut self, stack: Vec<(NodePtr, Option<Nibble>)>) {
-        let mut last_child: Option<NodePtr> = None;
+    fn recover_pointers(&mut self, stack: Vec<(NodePtr<H>, Option<Nibble>)>) {
+        let mut last_child: Option<NodePtr<H>> = None;
         for (mut node, child_idx) in stack.into_iter().rev() {
             if let Some(&child_idx) = child_idx.as_ref() {
                 let trie_node = &mut *TrieNodeExt::make_mut(&mut node, &mut self.del_ops);
@@ -268,7 +351,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
         self.root = last_child;
     }
 
-    fn search(&mut self, key: Vec<u8>) -> SearchResult {
+    fn search(&self, key: Vec<u8>) -> SearchResult<H> {
         let mut node = self.root.clone().unwrap();
         let mut stack = vec![(node.clone(), None)];
         let mut remainder = bytes_to_nibble_list(key);
@@ -278,7 +361,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
                 &node,
                 &mut remainder,
                 &self.db,
-                &mut self.loaded_node,
+                &self.loader,
                 depth,
             ) {
                 NextResult::Matched => {
@@ -304,10 +387,10 @@ impl<const N: usize> MerklePatriciaTree<N> {
     }
 }
 
-impl<const N: usize> MerklePatriciaTree<N> {
+impl<const N: usize, H: RlpHasher> MerklePatriciaTree<N, H> {
     fn insert(
         &mut self,
-        last_node: &mut TrieNode,
+        last_node: &mut TrieNode<H>,
         remainder: Vec<Nibble>,
         val: Vec<u8>,
         depth: usize,
@@ -325,7 +408,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
 
     fn insert_on_branch(
         &mut self,
-        children: &mut ChildRefGroup,
+        children: &mut ChildRefGroup<H>,
         remainder: Vec<Nibble>,
         val: Vec<u8>,
         depth: usize,
@@ -380,7 +463,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
 
     fn insert_on_extension(
         &mut self,
-        last: &mut TrieNode,
+        last: &mut TrieNode<H>,
         remainder: Vec<Nibble>,
         val: Vec<u8>,
         depth: usize,
@@ -430,7 +513,12 @@ impl<const N: usize> MerklePatriciaTree<N> {
         }
     }
 
-    fn insert_on_leaf(&mut self, last_leaf: &mut TrieNode, remainder: Vec<Nibble>, val: Vec<u8>) {
+    fn insert_on_leaf(
+        &mut self,
+        last_leaf: &mut TrieNode<H>,
+        remainder: Vec<Nibble>,
+        val: Vec<u8>,
+    ) {
         let (leaf_key, leaf_value) = last_leaf.as_leaf_mut().unwrap();
         let intersection: Vec<Nibble> = common_prefix_iter(&remainder, leaf_key).cloned().collect();
         let rest_remainder = &remainder[intersection.len()..];
@@ -462,8 +550,8 @@ impl<const N: usize> MerklePatriciaTree<N> {
     }
 }
 
-impl<const N: usize> MerklePatriciaTree<N> {
-    fn remove(&mut self, stack: &mut Vec<(NodePtr, Option<Nibble>)>) {
+impl<const N: usize, H: RlpHasher> MerklePatriciaTree<N, H> {
+    fn remove(&mut self, stack: &mut Vec<(NodePtr<H>, Option<Nibble>)>) {
         // #[cfg(test)] {
         //     println!("Show stack");
         //     for (node, _) in stack.iter() {
@@ -523,7 +611,7 @@ impl<const N: usize> MerklePatriciaTree<N> {
         }
     }
 
-    fn drop_single_child_branch(&mut self, idx: Nibble, mut child_ref: ChildRef) -> TrieNode {
+    fn drop_single_child_branch(&mut self, idx: Nibble, mut child_ref: ChildRef<H>) -> TrieNode<H> {
         let child = child_ref.loaded_mut(&self.db).unwrap();
 
         if child.as_ref().key().is_some() {
@@ -539,8 +627,8 @@ impl<const N: usize> MerklePatriciaTree<N> {
 
     fn push_non_branch_to_stack(
         &mut self,
-        mut node: TrieNode,
-        stack: &mut Vec<(NodePtr, Option<Nibble>)>,
+        mut node: TrieNode<H>,
+        stack: &mut Vec<(NodePtr<H>, Option<Nibble>)>,
     ) {
         if let Some((last_parent, idx)) = stack.pop() {
             let last_parent_borrow = last_parent.as_ref();
@@ -0,0 +1,343 @@
+//! A simulation of the storage nodes in RainBlock, which hold the trie nodes in memory and serve
+//! them to the clients over the network.
+//!
+//! The protocol is a request-response exchange of little-endian binary frames on a TCP or Unix
+//! socket. A request starts with an opcode:
+//!
+//! - `OP_GET`: `col: u32`, `key_len: u32`, `key`. The response is `found: u8`, followed by
+//!   `value_len: u32` and `value` if found.
+//! - `OP_WRITE`: `ops: u32`, followed by each operation: `kind: u8` (0 for insert and 1 for
+//!   delete), `col: u32`, `key_len: u32`, `key` and, for insert, `value_len: u32`, `value`. The
+//!   response is a single byte `0`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+use stats::io_stats::RunningStats;
+
+const OP_GET: u8 = 0;
+const OP_WRITE: u8 = 1;
+
+const KIND_INSERT: u8 = 0;
+const KIND_DELETE: u8 = 1;
+
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+// An address is either `host:port` for TCP, or `unix:<path>` for a Unix socket.
+fn connect(address: &str) -> io::Result<Box<dyn Stream>> {
+    Ok(if let Some(path) = address.strip_prefix("unix:") {
+        Box::new(UnixStream::connect(path)?)
+    } else {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        Box::new(stream)
+    })
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; read_u32(reader)? as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn put_bytes(frame: &mut Vec<u8>, data: &[u8]) {
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(data);
+}
+
+type Table = HashMap<(u32, Vec<u8>), Vec<u8>>;
+
+/// The server side of a storage node. It keeps all the data in memory and delays every response
+/// by the given latency to simulate the network round trips.
+pub struct StorageNode {
+    table: RwLock<Table>,
+    latency: Duration,
+}
+
+impl StorageNode {
+    pub fn new(latency: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            table: Default::default(),
+            latency,
+        })
+    }
+
+    /// Listens on the address and serves each connection in a thread. It never returns unless
+    /// the listener fails.
+    pub fn serve(self: Arc<Self>, address: &str) -> io::Result<()> {
+        if let Some(path) = address.strip_prefix("unix:") {
+            let _ = std::fs::remove_file(path);
+            self.serve_unix(UnixListener::bind(path)?)
+        } else {
+            self.serve_tcp(TcpListener::bind(address)?)
+        }
+    }
+
+    pub fn serve_unix(self: Arc<Self>, listener: UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            self.clone().spawn(Box::new(stream?));
+        }
+        Ok(())
+    }
+
+    pub fn serve_tcp(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            self.clone().spawn(Box::new(stream));
+        }
+        Ok(())
+    }
+
+    fn spawn(self: Arc<Self>, stream: Box<dyn Stream>) {
+        thread::spawn(move || {
+            if let Err(e) = self.handle(stream) {
+                if e.kind() != io::ErrorKind::UnexpectedEof {
+                    eprintln!("Connection closed: {}", e);
+                }
+            }
+        });
+    }
+
+    fn handle(&self, stream: Box<dyn Stream>) -> io::Result<()> {
+        let mut stream = BufReader::new(stream);
+        loop {
+            let mut response = Vec::new();
+            match read_u8(&mut stream)? {
+                OP_GET => {
+                    let col = read_u32(&mut stream)?;
+                    let key = read_bytes(&mut stream)?;
+                    if let Some(value) = self.table.read().unwrap().get(&(col, key)) {
+                        response.push(1);
+                        put_bytes(&mut response, value);
+                    } else {
+                        response.push(0);
+                    }
+                }
+                OP_WRITE => {
+                    let count = read_u32(&mut stream)?;
+                    let mut ops = Vec::with_capacity(count as usize);
+                    for _ in 0..count {
+                        let kind = read_u8(&mut stream)?;
+                        let col = read_u32(&mut stream)?;
+                        let key = read_bytes(&mut stream)?;
+                        let value = if kind == KIND_INSERT {
+                            Some(read_bytes(&mut stream)?)
+                        } else {
+                            None
+                        };
+                        ops.push(((col, key), value));
+                    }
+
+                    let mut table = self.table.write().unwrap();
+                    for (key, value) in ops {
+                        if let Some(value) = value {
+                            table.insert(key, value);
+                        } else {
+                            table.remove(&key);
+                        }
+                    }
+                    response.push(0);
+                }
+                op => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown opcode {}", op),
+                    ));
+                }
+            }
+
+            if !self.latency.is_zero() {
+                thread::sleep(self.latency);
+            }
+            stream.get_mut().write_all(&response)?;
+        }
+    }
+}
+
+// The storage node serves the point lookups only. An empty result would be taken for an empty
+// store, so the iteration fails loudly.
+fn unsupported_iteration() -> ! {
+    panic!("RemoteDB does not support iteration")
+}
+
+/// The client side of a storage node, which can be used as the backend of `MerklePatriciaTree`.
+/// The writes are buffered locally and sent in one round trip on flush.
+pub struct RemoteDB {
+    stream: Mutex<BufWriter<Box<dyn Stream>>>,
+    buffer: Mutex<Vec<DBOp>>,
+    round_trips: AtomicU64,
+    stats: RunningStats,
+}
+
+impl RemoteDB {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        Ok(Self {
+            stream: Mutex::new(BufWriter::new(connect(address)?)),
+            buffer: Default::default(),
+            round_trips: AtomicU64::new(0),
+            stats: RunningStats::new(),
+        })
+    }
+
+    /// The number of round trips to the storage node.
+    pub fn round_trips(&self) -> u64 {
+        self.round_trips.load(Ordering::Relaxed)
+    }
+
+    /// The total size of the values read from the storage node.
+    pub fn read_bytes(&self) -> u64 {
+        self.stats.overall().bytes_read
+    }
+
+    fn request(&self, frame: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut stream = self.stream.lock().unwrap();
+        stream.write_all(frame)?;
+        stream.flush()?;
+        self.round_trips.fetch_add(1, Ordering::Relaxed);
+
+        let reader = stream.get_mut();
+        Ok(if frame[0] == OP_GET && read_u8(reader)? == 1 {
+            Some(read_bytes(reader)?)
+        } else {
+            read_u8(reader)?;
+            None
+        })
+    }
+}
+
+impl KeyValueDB for RemoteDB {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        // The buffered writes are not visible to the storage node yet.
+        let buffered = self.buffer.lock().unwrap().iter().rev().find_map(|op| match op {
+            DBOp::Insert {
+                col: op_col,
+                key: op_key,
+                value,
+            } if *op_col == col && &op_key[..] == key => Some(Some(value.clone())),
+            DBOp::Delete {
+                col: op_col,
+                key: op_key,
+            } if *op_col == col && &op_key[..] == key => Some(None),
+            _ => None,
+        });
+        if let Some(value) = buffered {
+            let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+            self.stats.tally(|t| {
+                t.reads += 1;
+                t.cache_reads += 1;
+                t.cache_read_bytes += bytes;
+            });
+            return Ok(value);
+        }
+
+        let mut frame = vec![OP_GET];
+        frame.extend_from_slice(&col.to_le_bytes());
+        put_bytes(&mut frame, key);
+        let value = self.request(&frame)?;
+        let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+        self.stats.tally(|t| {
+            t.reads += 1;
+            t.bytes_read += bytes;
+        });
+        Ok(value)
+    }
+
+    fn get_by_prefix(&self, _col: u32, _prefix: &[u8]) -> Option<Box<[u8]>> {
+        unsupported_iteration()
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.buffer.lock().unwrap().extend(transaction.ops);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let ops = std::mem::take(&mut *self.buffer.lock().unwrap());
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        let (writes, mut bytes_written) = (ops.len() as u64, 0);
+        let mut frame = vec![OP_WRITE];
+        frame.extend_from_slice(&(ops.len() as u32).to_le_bytes());
+        for op in ops {
+            bytes_written += op.key().len() as u64;
+            match op {
+                DBOp::Insert { col, key, value } => {
+                    frame.push(KIND_INSERT);
+                    frame.extend_from_slice(&col.to_le_bytes());
+                    put_bytes(&mut frame, &key);
+                    put_bytes(&mut frame, &value);
+                    bytes_written += value.len() as u64;
+                }
+                DBOp::Delete { col, key } => {
+                    frame.push(KIND_DELETE);
+                    frame.extend_from_slice(&col.to_le_bytes());
+                    put_bytes(&mut frame, &key);
+                }
+            }
+        }
+        self.request(&frame)?;
+        self.stats.tally(|t| {
+            t.transactions += 1;
+            t.writes += writes;
+            t.bytes_written += bytes_written;
+        });
+        Ok(())
+    }
+
+    fn iter<'a>(&'a self, _col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        unsupported_iteration()
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        _col: u32,
+        _prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        unsupported_iteration()
+    }
+
+    fn restore(&self, _new_db: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The storage node cannot be restored from a database",
+        ))
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.stats.io_stats(kind)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for RemoteDB {
+    fn size_of(&self, _ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        // The data are held by the storage node.
+        0
+    }
+}
@@ -3,6 +3,8 @@ use std::sync::Arc;
 use crate::rain_mpt::EMPTY_ROOT;
 
 use super::*;
+use ethereum_types::H256;
+use keccak_hasher::KeccakHasher;
 use kvdb::KeyValueDB;
 use kvdb_memorydb;
 use rand::prelude::*;
@@ -18,7 +20,7 @@ fn new_db() -> Arc<dyn KeyValueDB> {
 fn test_put_random() {
     let mut rng = StdRng::seed_from_u64(123);
 
-    let mut trie = MerklePatriciaTree::<3>::new(new_db());
+    let mut trie = MerklePatriciaTree::<3, KeccakHasher>::new(new_db());
     let mut tasks: Vec<(Bytes, Bytes)> = (0..=255u8).map(|x| (vec![x], vec![x])).collect();
     tasks.shuffle(&mut rng);
 
@@ -46,7 +48,7 @@ fn test_put_random() {
         );
     }
 
-    let mut another_trie = MerklePatriciaTree::<3>::new(new_db());
+    let mut another_trie = MerklePatriciaTree::<3, KeccakHasher>::new(new_db());
 
     for i in 0..=255 {
         another_trie.put(vec![i], vec![i]);
@@ -65,7 +67,7 @@ fn comprehensive_test_with_similar_prefix() {
     let make_value = |x: usize| -> Vec<u8> { vec![x as u8] };
     const SAMPLES: usize = 256;
 
-    let mut trie = MerklePatriciaTree::<1000>::new(new_db());
+    let mut trie = MerklePatriciaTree::<1000, KeccakHasher>::new(new_db());
     let mut tasks: Vec<usize> = (0..SAMPLES).collect();
     tasks.shuffle(&mut rng);
 
@@ -96,7 +98,7 @@ fn comprehensive_test_with_similar_prefix() {
 
     // Check put consistensy
     let db2 = new_db();
-    let mut trie2 = MerklePatriciaTree::<3>::new(db2.clone());
+    let mut trie2 = MerklePatriciaTree::<3, KeccakHasher>::new(db2.clone());
     for i in 0..SAMPLES {
         trie2.put(make_key(i), make_value(i));
     }
@@ -123,7 +125,7 @@ fn comprehensive_test_with_similar_prefix() {
     assert_eq!(cached_nodes + dumped_nodes, new_dumped_nodes);
 
     // Check reload trie from db
-    let mut trie2 = MerklePatriciaTree::<3>::new(db2.clone());
+    let mut trie2 = MerklePatriciaTree::<3, KeccakHasher>::new(db2.clone());
     trie2.commit().unwrap();
     assert!(trie2.loaded_nodes_count() <= 5);
 
@@ -148,3 +150,336 @@ fn comprehensive_test_with_similar_prefix() {
     // Check no leak on db
     assert!(db2.iter_from_prefix(0, &vec![]).next().is_none());
 }
+
+fn root_of(keys: &[Bytes]) -> H256 {
+    let mut trie = MerklePatriciaTree::<3, KeccakHasher>::new(new_db());
+    for key in keys {
+        trie.put(key.clone(), key.clone());
+    }
+    trie.commit().unwrap()
+}
+
+#[test]
+fn test_delete_collapse_branch() {
+    // Each case lists the keys in the trie and the deleted key.
+    let cases: Vec<(Vec<Bytes>, Bytes)> = vec![
+        // A branch left with a single leaf collapses into a leaf.
+        (vec![vec![0x10], vec![0x20]], vec![0x10]),
+        (vec![vec![0x12, 0x34], vec![0x12, 0x35]], vec![0x12, 0x34]),
+        // A branch left with a single branch collapses into an extension.
+        (vec![vec![0x10], vec![0x21], vec![0x22]], vec![0x10]),
+        // A branch left with a single extension merges into the extension.
+        (
+            vec![vec![0x10], vec![0x21, 0x31], vec![0x21, 0x32]],
+            vec![0x10],
+        ),
+        // A branch left with a value and no child becomes a leaf.
+        (vec![vec![0x12], vec![0x12, 0x34]], vec![0x12, 0x34]),
+        // A branch without value and left with a single child collapses.
+        (vec![vec![0x12], vec![0x12, 0x34]], vec![0x12]),
+        // A branch without value but with multiple children is kept.
+        (
+            vec![vec![0x12], vec![0x12, 0x34], vec![0x12, 0x56]],
+            vec![0x12],
+        ),
+    ];
+
+    for (keys, deleted) in cases {
+        let rest: Vec<Bytes> = keys.iter().filter(|x| **x != deleted).cloned().collect();
+        let expected = root_of(&rest);
+
+        // Delete before commit
+        let mut trie = MerklePatriciaTree::<3, KeccakHasher>::new(new_db());
+        for key in &keys {
+            trie.put(key.clone(), key.clone());
+        }
+        trie.del(deleted.clone());
+        assert_eq!(trie.commit().unwrap(), expected, "Fail on deleting {:x?}", deleted);
+
+        // Delete nodes loaded from db
+        let db = new_db();
+        let mut trie = MerklePatriciaTree::<1, KeccakHasher>::new(db.clone());
+        for key in &keys {
+            trie.put(key.clone(), key.clone());
+        }
+        trie.commit().unwrap();
+        let mut trie = MerklePatriciaTree::<1, KeccakHasher>::new(db);
+        trie.del(deleted.clone());
+        assert_eq!(trie.commit().unwrap(), expected, "Fail on deleting {:x?}", deleted);
+
+        assert_eq!(trie.get(deleted.clone()), None);
+        for key in rest {
+            assert_eq!(trie.get(key.clone()), Some(key));
+        }
+    }
+
+    // Deleting a missing key changes nothing.
+    let keys = vec![vec![0x12], vec![0x12, 0x34]];
+    let mut trie = MerklePatriciaTree::<3, KeccakHasher>::new(new_db());
+    for key in &keys {
+        trie.put(key.clone(), key.clone());
+    }
+    trie.del(vec![0x12, 0x35]);
+    trie.del(vec![0x13]);
+    assert_eq!(trie.commit().unwrap(), root_of(&keys));
+}
+
+#[test]
+fn test_proof() {
+    let mut rng = StdRng::seed_from_u64(125);
+    let keys: Vec<Bytes> = (0..256).map(|_| rng.gen::<[u8; 4]>().to_vec()).collect();
+
+    let db = new_db();
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::new(db.clone());
+    for key in &keys {
+        trie.put(key.clone(), key.clone());
+    }
+    trie.commit().unwrap();
+
+    // Reload the trie, so the nodes below the top layer are loaded from db in proving.
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::new(db);
+    let root = trie.root().unwrap();
+    for key in &keys {
+        let proof = trie.prove(key.clone());
+        assert_eq!(verify_proof::<KeccakHasher>(root, key, &proof), Ok(Some(key.clone())));
+    }
+    for _ in 0..256 {
+        let key = rng.gen::<[u8; 4]>().to_vec();
+        if keys.contains(&key) {
+            continue;
+        }
+        let proof = trie.prove(key.clone());
+        assert_eq!(verify_proof::<KeccakHasher>(root, &key, &proof), Ok(None));
+    }
+
+    // Reject an incomplete proof
+    let proof = trie.prove(keys[0].clone());
+    assert!(matches!(
+        verify_proof::<KeccakHasher>(root, &keys[0], &proof[..proof.len() - 1]),
+        Err(ProofError::MissingNode(_))
+    ));
+    assert_eq!(
+        verify_proof::<KeccakHasher>(root, &[], &proof),
+        Err(ProofError::EmptyKey)
+    );
+
+    // Reject a stale proof and accept the new one
+    trie.put(keys[0].clone(), vec![0xff]);
+    let new_root = trie.root().unwrap();
+    assert!(verify_proof::<KeccakHasher>(new_root, &keys[0], &proof).is_err());
+    let proof = trie.prove(keys[0].clone());
+    assert_eq!(verify_proof::<KeccakHasher>(new_root, &keys[0], &proof), Ok(Some(vec![0xff])));
+
+    // Proofs of an empty trie
+    let trie = MerklePatriciaTree::<2, KeccakHasher>::new(new_db());
+    assert!(trie.prove(keys[0].clone()).is_empty());
+    assert_eq!(verify_proof::<KeccakHasher>(EMPTY_ROOT, &keys[0], &[]), Ok(None));
+}
+
+#[test]
+fn test_remote_storage_node() {
+    use crate::remote::{RemoteDB, StorageNode};
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir().join(format!("rain-node-{}.sock", std::process::id()));
+    let address = format!("unix:{}", path.display());
+    let listener = UnixListener::bind(&path).unwrap();
+    let node = StorageNode::new(Default::default());
+    std::thread::spawn(move || node.serve_unix(listener));
+
+    let mut rng = StdRng::seed_from_u64(126);
+    let keys: Vec<Bytes> = (0..256).map(|_| rng.gen::<[u8; 4]>().to_vec()).collect();
+
+    let remote = Arc::new(RemoteDB::connect(&address).unwrap());
+    let mut trie = MerklePatriciaTree::<1, KeccakHasher>::new(remote.clone());
+    for key in &keys {
+        trie.put(key.clone(), key.clone());
+    }
+    let root = trie.commit().unwrap();
+    assert_eq!(root, root_of(&keys));
+
+    let round_trips = remote.round_trips();
+    let trie = MerklePatriciaTree::<1, KeccakHasher>::new(remote.clone());
+    for key in &keys {
+        assert_eq!(trie.get(key.clone()), Some(key.clone()));
+    }
+    assert!(remote.round_trips() > round_trips);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "thread-safe")]
+#[test]
+fn test_concurrent_get() {
+    let mut rng = StdRng::seed_from_u64(127);
+    let keys: Vec<Bytes> = (0..1024).map(|_| rng.gen::<[u8; 8]>().to_vec()).collect();
+
+    let db = new_db();
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::new(db.clone());
+    for key in &keys {
+        trie.put(key.clone(), key.clone());
+    }
+    trie.commit().unwrap();
+
+    // The readers load the same nodes below the top layer at the same time.
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::new(db);
+    for _ in 0..2 {
+        let trie_ref = &trie;
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for key in &keys {
+                        assert_eq!(trie_ref.get(key.clone()), Some(key.clone()));
+                    }
+                });
+            }
+        });
+        trie.commit().unwrap();
+        assert!(trie.loaded_nodes_count() <= 1 + 16);
+    }
+}
+
+#[test]
+fn test_pruning() {
+    const RETAIN: usize = 2;
+    let mut rng = StdRng::seed_from_u64(128);
+    let keys: Vec<Bytes> = (0..256).map(|_| rng.gen::<[u8; 4]>().to_vec()).collect();
+
+    let db = new_db();
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::with_retention(db.clone(), RETAIN);
+    for round in 0..4u8 {
+        for key in &keys {
+            trie.put(key.clone(), [&key[..], &[round; 32]].concat());
+        }
+        trie.commit().unwrap();
+    }
+    trie.flush_all().unwrap();
+    assert!(trie.pruned_nodes() > 0);
+    assert!(trie.pruned_bytes() > 0);
+    for key in &keys {
+        assert_eq!(trie.get(key.clone()), Some([&key[..], &[3; 32]].concat()));
+    }
+
+    for key in &keys {
+        trie.del(key.clone());
+    }
+    trie.commit().unwrap();
+    trie.flush_all().unwrap();
+    // The nodes of the retained roots are kept.
+    assert!(db.iter_from_prefix(0, &[]).next().is_some());
+
+    for _ in 0..RETAIN {
+        trie.commit().unwrap();
+    }
+    trie.flush_all().unwrap();
+    assert!(db.iter_from_prefix(0, &[]).next().is_none());
+}
+
+#[test]
+fn test_pruning_repeated_flush() {
+    const RETAIN: usize = 1;
+    let mut rng = StdRng::seed_from_u64(129);
+    let keys: Vec<Bytes> = (0..256).map(|_| rng.gen::<[u8; 4]>().to_vec()).collect();
+
+    let db = new_db();
+    let mut trie = MerklePatriciaTree::<2, KeccakHasher>::with_retention(db.clone(), RETAIN);
+    for key in &keys {
+        trie.put(key.clone(), [&key[..], &[0; 32]].concat());
+    }
+    trie.commit().unwrap();
+    trie.flush_all().unwrap();
+    trie.flush_all().unwrap();
+    let old_root = trie.root().unwrap();
+    assert!(db.get(0, &old_root.0).unwrap().is_some());
+
+    for key in &keys {
+        trie.put(key.clone(), [&key[..], &[1; 32]].concat());
+    }
+    for _ in 0..=RETAIN {
+        trie.commit().unwrap();
+    }
+    trie.flush_all().unwrap();
+    // The replaced top-layer nodes are counted once, however many times they are flushed.
+    assert!(db.get(0, &old_root.0).unwrap().is_none());
+    for key in &keys {
+        assert_eq!(trie.get(key.clone()), Some([&key[..], &[1; 32]].concat()));
+    }
+}
+
+// A backend which, like many on-disk ones, does not return the buffered writes until flushed.
+struct HiddenBufferDB {
+    inner: kvdb_memorydb::InMemory,
+    buffer: std::sync::Mutex<Vec<kvdb::DBOp>>,
+}
+
+impl KeyValueDB for HiddenBufferDB {
+    fn get(&self, col: u32, key: &[u8]) -> std::io::Result<Option<kvdb::DBValue>> {
+        self.inner.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.inner.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: kvdb::DBTransaction) {
+        self.buffer.lock().unwrap().extend(transaction.ops);
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        let ops = std::mem::take(&mut *self.buffer.lock().unwrap());
+        self.inner.write(kvdb::DBTransaction { ops })
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> std::io::Result<()> {
+        self.inner.restore(new_db)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for HiddenBufferDB {
+    fn size_of(&self, _ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        0
+    }
+}
+
+#[test]
+fn test_pruning_hidden_buffer() {
+    use crate::pruner::Pruner;
+
+    let db = Arc::new(HiddenBufferDB {
+        inner: kvdb_memorydb::create(1),
+        buffer: Default::default(),
+    });
+    let node = (H256::repeat_byte(1), vec![1u8; 40]);
+    let mut pruner = Pruner::new(db.clone(), 0);
+
+    // The node is referenced twice before the counts are flushed.
+    pruner.commit(vec![node.clone()], vec![]).unwrap();
+    pruner.commit(vec![node.clone()], vec![]).unwrap();
+    db.flush().unwrap();
+    pruner.flushed();
+
+    // Releasing one reference keeps the node.
+    pruner.commit(vec![], vec![node.0]).unwrap();
+    db.flush().unwrap();
+    pruner.flushed();
+    pruner.sync();
+    assert_eq!(db.get(0, &node.0 .0).unwrap(), Some(node.1.clone()));
+
+    pruner.commit(vec![], vec![node.0]).unwrap();
+    db.flush().unwrap();
+    pruner.flushed();
+    pruner.sync();
+    assert_eq!(db.get(0, &node.0 .0).unwrap(), None);
+    assert_eq!(pruner.pruned_nodes(), 1);
+}
@@ -3,50 +3,50 @@ use std::{
     rc::{Rc, Weak},
 };
 
-use crate::{trie_node::TrieNode, trie_node_ext::TrieNodeExt};
+use crate::{trie_node::TrieNode, trie_node_ext::TrieNodeExt, RlpHasher};
 
 #[derive(Clone)]
-pub struct Node(pub TrieNodeExt);
+pub struct Node<H: RlpHasher>(pub TrieNodeExt<H>);
 
-impl Node {
-    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt> + '_ {
+impl<H: RlpHasher> Node<H> {
+    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt<H>> + '_ {
         &self.0
     }
 
-    pub fn as_mut(&mut self) -> impl DerefMut<Target = TrieNodeExt> + '_ {
+    pub fn as_mut(&mut self) -> impl DerefMut<Target = TrieNodeExt<H>> + '_ {
         &mut self.0
     }
 
-    pub fn as_mut_inner(&mut self) -> impl DerefMut<Target = TrieNode> + '_ {
+    pub fn as_mut_inner(&mut self) -> impl DerefMut<Target = TrieNode<H>> + '_ {
         &mut *self.0
     }
 }
 
 #[derive(Clone)]
-pub struct NodePtr(pub Rc<Node>);
+pub struct NodePtr<H: RlpHasher>(pub Rc<Node<H>>);
 
-impl NodePtr {
+impl<H: RlpHasher> NodePtr<H> {
     pub fn ptr_eq(me: &Self, other: &Self) -> bool {
         Rc::ptr_eq(&me.0, &other.0)
     }
 
-    pub fn downgrade(me: &Self) -> NodePtrWeak {
+    pub fn downgrade(me: &Self) -> NodePtrWeak<H> {
         NodePtrWeak(Rc::downgrade(&me.0))
     }
 
-    pub fn make_mut(me: &mut Self) -> &mut Node {
+    pub fn make_mut(me: &mut Self) -> &mut Node<H> {
         Rc::make_mut(&mut me.0)
     }
 
-    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt> + '_ {
+    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt<H>> + '_ {
         &self.0.deref().0
     }
 }
 
-pub struct NodePtrWeak(pub Weak<Node>);
+pub struct NodePtrWeak<H: RlpHasher>(pub Weak<Node<H>>);
 
-impl NodePtrWeak {
-    pub fn upgrade(&self) -> Option<NodePtr> {
+impl<H: RlpHasher> NodePtrWeak<H> {
+    pub fn upgrade(&self) -> Option<NodePtr<H>> {
         self.0.upgrade().map(|x| NodePtr(x))
     }
 }
@@ -3,70 +3,71 @@ use std::{
     sync::{Arc, Mutex, MutexGuard, Weak},
 };
 
-use crate::{trie_node::TrieNode, trie_node_ext::TrieNodeExt};
+use crate::{trie_node::TrieNode, trie_node_ext::TrieNodeExt, RlpHasher};
 
-pub struct Node(pub Mutex<TrieNodeExt>);
+pub struct Node<H: RlpHasher>(pub Mutex<TrieNodeExt<H>>);
 
-impl Clone for Node {
+impl<H: RlpHasher> Clone for Node<H> {
     fn clone(&self) -> Self {
         Self(Mutex::new(self.0.try_lock().unwrap().clone()))
     }
 }
 
-impl Node {
-    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt> + '_ {
-        self.0.try_lock().unwrap()
+impl<H: RlpHasher> Node<H> {
+    // Concurrent readers may visit the same node, so the read access waits for the lock.
+    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt<H>> + '_ {
+        self.0.lock().unwrap()
     }
 
-    pub fn as_mut(&mut self) -> impl DerefMut<Target = TrieNodeExt> + '_ {
+    pub fn as_mut(&mut self) -> impl DerefMut<Target = TrieNodeExt<H>> + '_ {
         self.0.try_lock().unwrap()
     }
 
-    pub fn as_mut_inner(&mut self) -> impl DerefMut<Target = TrieNode> + '_ {
+    pub fn as_mut_inner(&mut self) -> impl DerefMut<Target = TrieNode<H>> + '_ {
         NodeGuard(self.0.try_lock().unwrap())
     }
 }
 
 #[derive(Clone)]
-pub struct NodePtr(pub Arc<Node>);
+pub struct NodePtr<H: RlpHasher>(pub Arc<Node<H>>);
 
-impl NodePtr {
+impl<H: RlpHasher> NodePtr<H> {
     pub fn ptr_eq(me: &Self, other: &Self) -> bool {
         Arc::ptr_eq(&me.0, &other.0)
     }
 
-    pub fn downgrade(me: &Self) -> NodePtrWeak {
+    pub fn downgrade(me: &Self) -> NodePtrWeak<H> {
         NodePtrWeak(Arc::downgrade(&me.0))
     }
 
-    pub fn make_mut(me: &mut Self) -> &mut Node {
+    pub fn make_mut(me: &mut Self) -> &mut Node<H> {
         Arc::make_mut(&mut me.0)
     }
 
-    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt> + '_ {
-        self.0.deref().0.try_lock().unwrap()
+    pub fn as_ref(&self) -> impl Deref<Target = TrieNodeExt<H>> + '_ {
+        self.0.deref().as_ref()
     }
 }
 
-pub struct NodePtrWeak(pub Weak<Node>);
+pub struct NodePtrWeak<H: RlpHasher>(pub Weak<Node<H>>);
 
-impl NodePtrWeak {
-    pub fn upgrade(&self) -> Option<NodePtr> {
+impl<H: RlpHasher> NodePtrWeak<H> {
+    pub fn upgrade(&self) -> Option<NodePtr<H>> {
         self.0.upgrade().map(|x| NodePtr(x))
     }
 }
 
-pub struct NodeGuard<'a>(MutexGuard<'a, TrieNodeExt>);
+pub struct NodeGuard<'a, H: RlpHasher>(MutexGuard<'a, TrieNodeExt<H>>);
 
-impl Deref for NodeGuard<'_> {
-    type Target = TrieNode;
+impl<H: RlpHasher> Deref for NodeGuard<'_, H> {
+    type Target = TrieNode<H>;
 
     fn deref(&self) -> &Self::Target {
         self.0.deref().deref()
     }
 }
 
-impl DerefMut for NodeGuard<'_> {
+impl<H: RlpHasher> DerefMut for NodeGuard<'_, H> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.0.deref_mut().deref_mut()
     }
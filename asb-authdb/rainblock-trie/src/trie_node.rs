@@ -3,8 +3,9 @@ use std::{cell::RefCell, sync::Arc};
 use crate::{
     child_ref::{ChildRef, ChildRefCell, ChildRefGroup},
     nibble::{from_mpt_key, to_mpt_key, Nibble},
+    node_loader::NodeLoader,
     trie_node_ext::TrieNodeExt,
-    Node, NodePtr, NodePtrWeak,
+    Node, NodePtr, RlpHasher,
 };
 use kvdb::KeyValueDB;
 use rlp::{Decodable, Encodable, Rlp};
@@ -12,30 +13,30 @@ use rlp::{Decodable, Encodable, Rlp};
 type Bytes = Vec<u8>;
 
 #[derive(Clone, Eq, PartialEq)]
-pub enum TrieNode {
+pub enum TrieNode<H: RlpHasher> {
     Leaf {
         key: Vec<Nibble>,
         value: Bytes,
     },
     Branch {
-        children: ChildRefGroup,
+        children: ChildRefGroup<H>,
         value: Bytes,
     },
     Extension {
         key: Vec<Nibble>,
-        child: ChildRefCell,
+        child: ChildRefCell<H>,
     },
 }
 
-pub enum NextResult {
+pub enum NextResult<H: RlpHasher> {
     Matched,
     NotMatched,
-    Next((NodePtr, Nibble)),
+    Next((NodePtr<H>, Nibble)),
 }
 
 use TrieNode::*;
 
-impl Default for TrieNode {
+impl<H: RlpHasher> Default for TrieNode<H> {
     fn default() -> Self {
         Self::Leaf {
             key: vec![],
@@ -44,7 +45,7 @@ impl Default for TrieNode {
     }
 }
 
-impl TrieNode {
+impl<H: RlpHasher> TrieNode<H> {
     pub fn new(data: Vec<u8>) -> Self {
         let rlp = Rlp::new(&data);
         TrieNode::decode(&rlp).unwrap()
@@ -64,7 +65,7 @@ impl TrieNode {
         }
     }
 
-    pub fn new_extention(new_key: Vec<Nibble>, child: ChildRef) -> Self {
+    pub fn new_extention(new_key: Vec<Nibble>, child: ChildRef<H>) -> Self {
         Self::Extension {
             key: new_key.into(),
             child: RefCell::new(child),
@@ -85,7 +86,15 @@ impl TrieNode {
         }
     }
 
-    pub fn child_mut(&mut self, index: Nibble) -> Option<&mut ChildRef> {
+    pub fn child(&self, index: Nibble) -> Option<&ChildRefCell<H>> {
+        match self {
+            Branch { children, .. } => Some(&children[index]),
+            Extension { child, .. } if index.is_zero() => Some(child),
+            _ => None,
+        }
+    }
+
+    pub fn child_mut(&mut self, index: Nibble) -> Option<&mut ChildRef<H>> {
         match self {
             Branch { children, .. } => Some(children[index].get_mut()),
             Extension { child, .. } if index.is_zero() => Some(child.get_mut()),
@@ -107,14 +116,14 @@ impl TrieNode {
         }
     }
 
-    pub fn as_branch_mut(&mut self) -> Option<(&mut ChildRefGroup, &mut Vec<u8>)> {
+    pub fn as_branch_mut(&mut self) -> Option<(&mut ChildRefGroup<H>, &mut Vec<u8>)> {
         match self {
             Branch { children, value } => Some((children, value)),
             _ => None,
         }
     }
 
-    pub fn as_extension_mut(&mut self) -> Option<(&mut Vec<Nibble>, &mut ChildRef)> {
+    pub fn as_extension_mut(&mut self) -> Option<(&mut Vec<Nibble>, &mut ChildRef<H>)> {
         match self {
             Extension { key, child } => Some((key, child.get_mut())),
             _ => None,
@@ -129,56 +138,52 @@ impl TrieNode {
     }
 
     pub fn next<const N: usize>(
-        me: &NodePtr,
+        me: &NodePtr<H>,
         nibbles: &mut Vec<Nibble>,
         db: &Arc<dyn KeyValueDB>,
-        truncate_ops: &mut Vec<NodePtrWeak>,
+        loader: &NodeLoader<H>,
         depth: usize,
-    ) -> NextResult {
-        match &**me.as_ref() {
+    ) -> NextResult<H> {
+        // Find the next child with the node locked, and release the lock before loading it.
+        let (index, consumed) = match &**me.as_ref() {
             Branch { children, .. } => {
                 if nibbles.len() == 0 {
                     return NextResult::Matched;
                 }
 
                 let branch_key = *nibbles.first().unwrap();
-                let branch = &children[branch_key];
-                if let Some((next, is_loaded)) = ChildRef::owned_or_load(branch, db) {
-                    if is_loaded && depth >= N {
-                        truncate_ops.push(NodePtr::downgrade(&me));
-                    }
-                    *nibbles = nibbles[1..].to_vec();
-                    NextResult::Next((next, branch_key))
-                } else {
-                    NextResult::NotMatched
+                if children[branch_key].borrow().is_null() {
+                    return NextResult::NotMatched;
                 }
+                (branch_key, 1)
             }
-            Extension { key, child } => {
-                if nibbles[..].starts_with(&key) {
-                    *nibbles = nibbles[key.len()..].to_vec();
-                    let (node, is_loaded) = ChildRef::owned_or_load(child, db).unwrap();
-                    if is_loaded && depth >= N {
-                        truncate_ops.push(NodePtr::downgrade(&me));
-                    }
-                    NextResult::Next((node, Nibble::zero()))
-                } else {
-                    NextResult::NotMatched
+            Extension { key, .. } => {
+                if !nibbles[..].starts_with(&key) {
+                    return NextResult::NotMatched;
                 }
+                (Nibble::zero(), key.len())
             }
             Leaf { key, .. } => {
-                if key[..] == nibbles[..] {
+                return if key[..] == nibbles[..] {
                     *nibbles = vec![];
                     NextResult::Matched
                 } else {
                     NextResult::NotMatched
-                }
+                };
             }
+        };
+
+        let (next, is_loaded) = loader.load_child(me, index, db);
+        if is_loaded && depth >= N {
+            loader.push_loaded(me);
         }
+        *nibbles = nibbles[consumed..].to_vec();
+        NextResult::Next((next, index))
     }
 
     #[cfg(feature = "thread-safe")]
     #[inline]
-    pub fn seal(self) -> NodePtr {
+    pub fn seal(self) -> NodePtr<H> {
         use std::sync::Mutex;
 
         NodePtr(Arc::new(Node(Mutex::new(TrieNodeExt::new(self)))))
@@ -186,19 +191,19 @@ impl TrieNode {
 
     #[cfg(not(feature = "thread-safe"))]
     #[inline]
-    pub fn seal(self) -> NodePtr {
+    pub fn seal(self) -> NodePtr<H> {
         use std::rc::Rc;
 
         NodePtr(Rc::new(Node(TrieNodeExt::new(self))))
     }
 
     #[inline]
-    pub fn seal_ref(self) -> ChildRef {
+    pub fn seal_ref(self) -> ChildRef<H> {
         ChildRef::Owned(self.seal())
     }
 }
 
-impl Encodable for TrieNode {
+impl<H: RlpHasher> Encodable for TrieNode<H> {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
         match self {
             Leaf { key, value } => {
@@ -222,7 +227,7 @@ impl Encodable for TrieNode {
     }
 }
 
-impl Decodable for TrieNode {
+impl<H: RlpHasher> Decodable for TrieNode<H> {
     fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
         Ok(match rlp.item_count()? {
             2 => {
@@ -240,7 +245,7 @@ impl Decodable for TrieNode {
                 }
             }
             17 => {
-                let mut children: ChildRefGroup = Default::default();
+                let mut children: ChildRefGroup<H> = Default::default();
                 for i in Nibble::all() {
                     children[i] = RefCell::new(rlp.val_at(i.inner() as usize)?);
                 }
@@ -1,11 +1,11 @@
 use std::{
     cell::{Cell, RefCell},
+    marker::PhantomData,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
 
 use ethereum_types::H256;
-use hash_db::Hasher;
 use kvdb::KeyValueDB;
 use rlp::Encodable;
 
@@ -28,24 +28,27 @@ pub enum Commit {
 use Commit::*;
 
 #[derive(Clone)]
-pub struct TrieNodeExt {
-    node: TrieNode,
+pub struct TrieNodeExt<H: RlpHasher> {
+    node: TrieNode<H>,
     hash: Cell<Option<H256>>,
     rlp_encode: RefCell<Option<Vec<u8>>>,
     commited: Cell<Commit>,
+    // The trie nodes refer to the hasher only recursively through this struct, so it is marked here.
+    hasher: PhantomData<H>,
 }
 
-impl TrieNodeExt {
-    pub fn new(node: TrieNode) -> Self {
+impl<H: RlpHasher> TrieNodeExt<H> {
+    pub fn new(node: TrieNode<H>) -> Self {
         Self {
             node,
             hash: Cell::new(None),
             rlp_encode: RefCell::new(None),
             commited: Cell::new(Working),
+            hasher: PhantomData,
         }
     }
 
-    pub fn from_child_ref(node: TrieNode, rlp_encode: Vec<u8>) -> Self {
+    pub fn from_child_ref(node: TrieNode<H>, rlp_encode: Vec<u8>) -> Self {
         // I have not find a case with a long rlp_encode, since this function will only be called in loading nodes.
         assert!(rlp_encode.len() < 32);
         Self {
@@ -53,6 +56,7 @@ impl TrieNodeExt {
             hash: Cell::new(None),
             rlp_encode: RefCell::new(Some(rlp_encode)),
             commited: Cell::new(Light),
+            hasher: PhantomData,
         }
     }
 
@@ -69,28 +73,29 @@ impl TrieNodeExt {
             hash: Cell::new(Some(digest)),
             rlp_encode: RefCell::new(Some(loaded)),
             commited: Cell::new(Committed),
+            hasher: PhantomData,
         }
     }
 
     #[cfg(feature = "thread-safe")]
     #[inline]
-    pub fn seal(self) -> NodePtr {
+    pub fn seal(self) -> NodePtr<H> {
         use std::sync::Mutex;
         NodePtr(Arc::new(Node(Mutex::new(self))))
     }
 
     #[cfg(not(feature = "thread-safe"))]
     #[inline]
-    pub fn seal(self) -> NodePtr {
+    pub fn seal(self) -> NodePtr<H> {
         use std::rc::Rc;
 
         NodePtr(Rc::new(Node(self)))
     }
 
     pub fn make_mut<'a>(
-        me: &'a mut NodePtr,
+        me: &'a mut NodePtr<H>,
         del_ops: &mut Vec<H256>,
-    ) -> impl DerefMut<Target = TrieNode> + 'a {
+    ) -> impl DerefMut<Target = TrieNode<H>> + 'a {
         let cached_node = NodePtr::make_mut(me);
         if cached_node.as_ref().commited.get() == Committed {
             if let Some(digest) = cached_node.as_ref().hash.get() {
@@ -110,7 +115,7 @@ impl TrieNodeExt {
     #[inline]
     pub fn hash(&self) -> H256 {
         if self.hash.get().is_none() {
-            let hash = RlpHasher::hash(&self.get_rlp_encode());
+            let hash = H::hash(&self.get_rlp_encode());
             self.hash.set(Some(hash));
         }
         self.hash.get().unwrap()
@@ -216,7 +221,11 @@ impl TrieNodeExt {
         }
     }
 
-    pub fn exile<const N: usize>(me: &NodePtr, depth: usize, exile_nodes: &mut Vec<NodePtrWeak>) {
+    pub fn exile<const N: usize>(
+        me: &NodePtr<H>,
+        depth: usize,
+        exile_nodes: &mut Vec<NodePtrWeak<H>>,
+    ) {
         if depth > N {
             return;
         }
@@ -238,7 +247,6 @@ impl TrieNodeExt {
         }
     }
 
-    #[cfg(test)]
     pub fn loaded_nodes_count(&self) -> usize {
         1 + match &self.node {
             TrieNode::Branch { children, .. } => {
@@ -249,6 +257,25 @@ impl TrieNodeExt {
         }
     }
 
+    pub fn loaded_nodes_size(&self) -> usize {
+        // The node behind the reference counted pointer, with two reference counters.
+        let node_size = std::mem::size_of::<Node<H>>() + 2 * std::mem::size_of::<usize>();
+        let rlp_size = self.rlp_encode.borrow().as_ref().map_or(0, Vec::capacity);
+        node_size
+            + rlp_size
+            + match &self.node {
+                TrieNode::Branch { children, value } => {
+                    let children_size: usize =
+                        children.iter().map(ChildRef::loaded_nodes_size).sum();
+                    value.capacity() + children_size
+                }
+                TrieNode::Extension { key, child } => {
+                    key.capacity() + ChildRef::loaded_nodes_size(child)
+                }
+                TrieNode::Leaf { key, value } => key.capacity() + value.capacity(),
+            }
+    }
+
     #[cfg(test)]
     pub fn print_node(&self, ident: usize) {
         let prefix = String::from_utf8(vec![b' '; (ident + 1) * 4]).unwrap();
@@ -278,15 +305,15 @@ impl TrieNodeExt {
     }
 }
 
-impl Deref for TrieNodeExt {
-    type Target = TrieNode;
+impl<H: RlpHasher> Deref for TrieNodeExt<H> {
+    type Target = TrieNode<H>;
 
     fn deref(&self) -> &Self::Target {
         &self.node
     }
 }
 
-impl DerefMut for TrieNodeExt {
+impl<H: RlpHasher> DerefMut for TrieNodeExt<H> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.node
     }
@@ -2,18 +2,25 @@ use std::sync::Arc;
 
 use kvdb::KeyValueDB;
 
-use lvmt_db::{lvmt_db::cached_pp_with_depth, single_amt::AmtDB};
+use lvmt_db::amt::AMTConfigTrait;
+use lvmt_db::{
+    lvmt_db::cached_pp_with_depth,
+    single_amt::{AmtDB, Node, TreeName},
+};
 
 use asb_options::Options;
 use authdb::AuthDB;
 
-pub struct Amt<const N: usize> {
-    amt: AmtDB<N>,
+pub struct Amt<C: AMTConfigTrait<Name = TreeName, Data = Node>> {
+    amt: AmtDB<C>,
     print_root_period: Option<usize>,
 }
 
-pub fn new<const N: usize>(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Amt<N> {
-    let pp = cached_pp_with_depth("./pp", N);
+pub fn new<C: AMTConfigTrait<Name = TreeName, Data = Node>>(
+    backend: Arc<dyn KeyValueDB>,
+    opts: &Options,
+) -> Amt<C> {
+    let pp = cached_pp_with_depth::<C::PE>("./pp", C::DEPTHS, opts.gen_pp);
     pp.warm_quotient();
     let shard_info = opts.shards.map(|size| (size.trailing_zeros() as usize, 0));
     Amt {
@@ -26,7 +33,7 @@ pub fn new<const N: usize>(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Amt<
     }
 }
 
-impl<const N: usize> AuthDB for Amt<N> {
+impl<C: AMTConfigTrait<Name = TreeName, Data = Node>> AuthDB for Amt<C> {
     fn get(&self, key: Vec<u8>) -> Option<Box<[u8]>> {
         // println!("read");
         self.amt.get(&key).map(Into::into)
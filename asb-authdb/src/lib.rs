@@ -3,18 +3,25 @@ mod hot;
 #[cfg(feature = "lmpts")]
 mod lmpts;
 mod lvmt;
-mod mpt;
+pub mod mpt;
 mod rain_mpt;
 mod raw;
 
 use lvmt::LvmtCounter;
 use mpt::MptCounter;
 
-use asb_options::{AuthAlgo, Options};
+use asb_options::{AuthAlgo, Curve, HashAlgo, Options};
 use asb_profile::CounterTrait;
 use asb_profile::{Counter, Reporter};
 use authdb::AuthDB;
+use blake2_hasher::Blake2bHasher;
+use blake3_hasher::Blake3Hasher;
+use keccak_hasher::KeccakHasher;
 use kvdb::KeyValueDB;
+use lvmt_db::crypto::export::{Bls12_381, Bn254};
+use lvmt_db::crypto::{Height, TypeDepths};
+use lvmt_db::multi_layer_amt::AMTConfig as LvmtAMTConfig;
+use lvmt_db::single_amt::AMTConfig as SingleAMTConfig;
 use std::sync::Arc;
 
 fn open_lmpts(dir: &str) -> Box<dyn AuthDB> {
@@ -25,31 +32,51 @@ fn open_lmpts(dir: &str) -> Box<dyn AuthDB> {
     #[cfg(not(feature = "lmpts"))]
     {
         let _ = dir;
-        panic!("LMPTs can only work with feature asb-backend!")
+        panic!("LMPTs can only work with feature asb-authdb/lmpts!")
     }
 }
 
 pub fn new<'a>(backend: Arc<dyn KeyValueDB>, opts: &'a Options) -> (Box<dyn AuthDB>, Reporter<'a>) {
     let (db, counter): (Box<dyn AuthDB>, Box<dyn CounterTrait>) = match opts.algorithm {
         AuthAlgo::RAW => (Box::new(raw::new(backend)), Box::new(Counter::default())),
-        AuthAlgo::LVMT => (
-            Box::new(lvmt::new(backend, opts)),
-            Box::new(LvmtCounter::default()),
-        ),
-        AuthAlgo::MPT => {
-            let mpt_db = mpt::new(backend, opts);
-            let counter = MptCounter::from_mpt_db(&mpt_db);
-            (Box::new(mpt_db), Box::new(counter))
-        }
+        AuthAlgo::LVMT => match (opts.curve, opts.lvmt_depth) {
+            (Curve::BN254, None) => new_lvmt::<LvmtAMTConfig<Bn254, TypeDepths>>(backend, opts),
+            (Curve::BLS12_381, None) => {
+                new_lvmt::<LvmtAMTConfig<Bls12_381, TypeDepths>>(backend, opts)
+            }
+            (Curve::BN254, Some(x)) => exaust_construct!(
+                x, new_lvmt, LvmtAMTConfig<Bn254>, backend, opts,
+                8, 12, 16, 20
+            ),
+            (Curve::BLS12_381, Some(x)) => exaust_construct!(
+                x, new_lvmt, LvmtAMTConfig<Bls12_381>, backend, opts,
+                8, 12, 16, 20
+            ),
+        },
+        AuthAlgo::MPT => match opts.hasher {
+            HashAlgo::Keccak => new_mpt::<KeccakHasher>(backend, opts),
+            HashAlgo::Blake2b => new_mpt::<Blake2bHasher>(backend, opts),
+            HashAlgo::Blake3 => new_mpt::<Blake3Hasher>(backend, opts),
+        },
         AuthAlgo::LMPTS => (open_lmpts(&opts.db_dir), Box::new(Counter::default())),
         AuthAlgo::AMT(x) => {
-            let authdb = exaust_construct!(x, backend, opts, 20, 21, 22, 23, 24, 25, 26, 27, 28);
+            let authdb = match opts.curve {
+                Curve::BN254 => exaust_construct!(
+                    x, new_amt, SingleAMTConfig<Bn254>, backend, opts,
+                    20, 21, 22, 23, 24, 25, 26, 27, 28
+                ),
+                Curve::BLS12_381 => exaust_construct!(
+                    x, new_amt, SingleAMTConfig<Bls12_381>, backend, opts,
+                    20, 21, 22, 23, 24, 25, 26, 27, 28
+                ),
+            };
             (authdb, Box::new(Counter::default()))
         }
-        AuthAlgo::RAIN => (
-            Box::new(rain_mpt::new(backend)),
-            Box::new(Counter::default()),
-        ),
+        AuthAlgo::RAIN => match opts.hasher {
+            HashAlgo::Keccak => rain_mpt::new::<KeccakHasher>(backend, opts),
+            HashAlgo::Blake2b => rain_mpt::new::<Blake2bHasher>(backend, opts),
+            HashAlgo::Blake3 => rain_mpt::new::<Blake3Hasher>(backend, opts),
+        },
         AuthAlgo::HOT => (
             Box::new(hot::new_blake3(backend)),
             Box::new(Counter::default()),
@@ -66,15 +93,43 @@ pub fn new<'a>(backend: Arc<dyn KeyValueDB>, opts: &'a Options) -> (Box<dyn Auth
     return (db, reporter);
 }
 
+fn new_lvmt<C: lvmt_db::multi_layer_amt::LvmtConfig>(
+    backend: Arc<dyn KeyValueDB>,
+    opts: &Options,
+) -> (Box<dyn AuthDB>, Box<dyn CounterTrait>) {
+    let lvmt = lvmt::new::<C>(backend, opts);
+    let counter = LvmtCounter::from_lvmt(&lvmt);
+    (Box::new(lvmt), Box::new(counter))
+}
+
+fn new_mpt<H: mpt::MptHasher>(
+    backend: Arc<dyn KeyValueDB>,
+    opts: &Options,
+) -> (Box<dyn AuthDB>, Box<dyn CounterTrait>) {
+    let mpt_db = mpt::new::<H>(backend, opts).unwrap_or_else(|e| panic!("{}", e));
+    let counter = MptCounter::from_mpt_db(&mpt_db);
+    (Box::new(mpt_db), Box::new(counter))
+}
+
+fn new_amt<C>(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Box<dyn AuthDB>
+where
+    C: lvmt_db::amt::AMTConfigTrait<
+        Name = lvmt_db::single_amt::TreeName,
+        Data = lvmt_db::single_amt::Node,
+    > + 'static,
+{
+    Box::new(amt::new::<C>(backend, opts))
+}
+
 macro_rules! exaust_construct {
-    ($input: ident, $backend: ident, $opts: ident, $idx:tt $(, $rest:tt)*) => {
+    ($input: ident, $construct: ident, $config: ident < $pe: ident >, $backend: ident, $opts: ident, $idx:tt $(, $rest:tt)*) => {
         if $input == $idx {
-            Box::new(amt::new::<$idx>($backend, $opts)) as Box<dyn AuthDB>
+            $construct::<$config<$pe, Height<$idx>>>($backend, $opts)
         } else {
-            exaust_construct!($input, $backend, $opts, $($rest),*)
+            exaust_construct!($input, $construct, $config<$pe>, $backend, $opts, $($rest),*)
         }
     };
-    ($input: ident, $backend: ident, $opts: ident, )=>{
+    ($input: ident, $construct: ident, $config: ident < $pe: ident >, $backend: ident, $opts: ident, )=>{
         unreachable!("Unsupport index")
     }
 }
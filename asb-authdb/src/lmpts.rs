@@ -57,3 +57,28 @@ impl AuthDB for Lmpts {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    // LMPTs runs on the local `cfx-kvdb-rocksdb` patched in place of the crate of conflux-rust.
+    #[test]
+    fn round_trip_on_patched_rocksdb() {
+        let dir = TempDir::new("lmpts").unwrap();
+        let mut db = new(dir.path().to_str().unwrap());
+        for i in 0..64u8 {
+            db.set(vec![i; 8], vec![i; 32]);
+        }
+        db.commit(0);
+        db.set(vec![0; 8], vec![0xff; 32]);
+        db.commit(1);
+
+        assert_eq!(db.get(vec![0; 8]).as_deref(), Some(&[0xff; 32][..]));
+        for i in 1..64u8 {
+            assert_eq!(db.get(vec![i; 8]).as_deref(), Some(&[i; 32][..]));
+        }
+        assert_eq!(db.get(vec![64; 8]), None);
+    }
+}
@@ -4,23 +4,27 @@ use authdb::AuthDB;
 use kvdb::KeyValueDB;
 use lvmt_db::crypto::export::ProjectiveCurve;
 use lvmt_db::{
-    lvmt_db::{cached_pp, LvmtDB, INC_KEY_COUNT, INC_KEY_LEVEL_SUM, INC_TREE_COUNT},
-    multi_layer_amt::Key,
-    storage::access::PUT_COUNT,
+    lvmt_db::{cached_pp_with_depth, LvmtDB},
+    metrics::{LvmtMetrics, LvmtMetricsSnapshot},
+    multi_layer_amt::{Key, LvmtConfig},
 };
 use std::sync::Arc;
 
-pub struct Lvmt {
-    amt: LvmtDB,
+pub struct Lvmt<C: LvmtConfig> {
+    amt: LvmtDB<C>,
     print_root_period: Option<usize>,
 }
 
-pub fn new(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Lvmt {
-    let pp = cached_pp("./pp");
+pub fn new<C: LvmtConfig>(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Lvmt<C> {
+    let pp = cached_pp_with_depth::<C::PE>("./pp", C::DEPTHS, opts.gen_pp);
     pp.warm_quotient();
     let shard_info = opts.shards.map(|size| (size.trailing_zeros() as usize, 0));
+    let mut amt = LvmtDB::new(backend, pp, true, shard_info);
+    if let Some(threads) = opts.threads {
+        amt.set_commit_threads(threads);
+    }
     Lvmt {
-        amt: LvmtDB::new(backend, pp, true, shard_info),
+        amt,
         print_root_period: if opts.print_root {
             Some(opts.report_epoch)
         } else {
@@ -29,7 +33,7 @@ pub fn new(backend: Arc<dyn KeyValueDB>, opts: &Options) -> Lvmt {
     }
 }
 
-impl AuthDB for Lvmt {
+impl<C: LvmtConfig> AuthDB for Lvmt<C> {
     fn get(&self, key: Vec<u8>) -> Option<Box<[u8]>> {
         // println!("read");
         self.amt.get(&Key(key)).unwrap()
@@ -62,51 +66,43 @@ impl AuthDB for Lvmt {
 
 #[derive(Clone)]
 pub struct LvmtCounter {
-    put_count: [u64; 4],
-    inc_key_count: u64,
-    inc_tree_count: u64,
-    inc_key_level_count: u64,
+    metrics: Arc<LvmtMetrics>,
+    last: LvmtMetricsSnapshot,
 }
 
-impl Default for LvmtCounter {
-    fn default() -> Self {
+impl LvmtCounter {
+    pub fn from_lvmt<C: LvmtConfig>(lvmt: &Lvmt<C>) -> Self {
+        let metrics = lvmt.amt.metrics();
         Self {
-            put_count: [0; 4],
-            inc_key_count: 0,
-            inc_tree_count: 0,
-            inc_key_level_count: 0,
+            last: metrics.snapshot(),
+            metrics,
         }
     }
 }
 
 impl CounterTrait for LvmtCounter {
     fn report(&mut self) -> String {
-        let put_count = *PUT_COUNT.lock().unwrap();
-        let inc_key_count = *INC_KEY_COUNT.lock().unwrap();
-        let inc_tree_count = *INC_TREE_COUNT.lock().unwrap();
-        let inc_key_level_count = *INC_KEY_LEVEL_SUM.lock().unwrap();
+        let current = self.metrics.snapshot();
 
-        let key_diff = inc_key_count - self.inc_key_count;
-        let tree_diff = inc_tree_count - self.inc_tree_count;
-        let level_diff = inc_key_level_count - self.inc_key_level_count;
+        let key_diff = current.inc_key_count - self.last.inc_key_count;
+        let tree_diff = current.inc_tree_count - self.last.inc_tree_count;
+        let level_diff = current.inc_key_level_sum - self.last.inc_key_level_sum;
         let avg_level = (level_diff as f64) / (key_diff as f64);
 
         let answer = format!(
             "avg levels: {:.3}, access writes {:?}, data writes {} {}",
             avg_level,
-            self.put_count
+            self.last
+                .put_count
                 .iter()
-                .zip(put_count.iter())
+                .zip(current.put_count.iter())
                 .map(|(x, y)| y - x)
                 .collect::<Vec<u64>>(),
             key_diff * 2,
             tree_diff * 2,
         );
 
-        self.put_count = *PUT_COUNT.lock().unwrap();
-        self.inc_key_count = *INC_KEY_COUNT.lock().unwrap();
-        self.inc_tree_count = *INC_TREE_COUNT.lock().unwrap();
-        self.inc_key_level_count = *INC_KEY_LEVEL_SUM.lock().unwrap();
+        self.last = current;
 
         answer
     }
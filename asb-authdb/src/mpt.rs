@@ -1,69 +1,420 @@
 use std::cell::RefCell;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
-use hash_db::Hasher;
-use kvdb::{DBKey, DBOp, DBTransaction, KeyValueDB};
-use patricia_trie_ethereum::RlpNodeCodec;
+use hash_db::{HashDB, Hasher};
+use kvdb::{DBTransaction, KeyValueDB};
+use patricia_trie_ethereum::{HashedNullNode, RlpNodeCodec};
 use primitive_types::H256;
-use trie_db::{NodeCodec, Trie, TrieMut};
+use rlp::{Rlp, RlpStream};
+use trie_db::{DBValue, NodeCodec, Recorder, Trie, TrieMut};
 
 use parity_journaldb::{Algorithm, DBHasher, JournalDB};
-use parity_scale_codec::KeyedVec;
 
-use asb_options::Options;
+use asb_options::{HashAlgo, JournalAlgo, Options};
 use asb_profile::CounterTrait;
 use authdb::AuthDB;
 
-pub type TrieDBMut<'db> = trie_db::TrieDBMut<'db, DBHasher, RlpNodeCodec<DBHasher>>;
-pub type TrieDB<'db> = trie_db::TrieDB<'db, DBHasher, RlpNodeCodec<DBHasher>>;
+pub type TrieDBMut<'db, H> = trie_db::TrieDBMut<'db, H, RlpNodeCodec<H>>;
+pub type TrieDB<'db, H> = trie_db::TrieDB<'db, H, RlpNodeCodec<H>>;
 
-pub struct MptDB {
+/// The hasher of the MPT nodes, which also hashes the keys in the two-level mode.
+pub trait MptHasher: DBHasher + HashedNullNode {}
+
+impl<H: DBHasher + HashedNullNode> MptHasher for H {}
+
+// The column of the metadata record. The trie nodes and the journal live in column 0.
+const META_COL: u32 = 1;
+const META_KEY: &[u8] = b"mpt-metadata";
+
+pub struct MptDB<H: MptHasher> {
     backing: Arc<dyn KeyValueDB>,
-    db: Arc<RefCell<Box<dyn JournalDB>>>,
+    db: Arc<RefCell<Box<dyn JournalDB<H>>>>,
+    algorithm: Algorithm,
+    hasher: HashAlgo,
     root: H256,
     epoch: usize,
     print_root_period: Option<usize>,
     journal_epoch: usize,
+    // In the two-level mode, the keys are stored in the storage tries of `accounts` accounts, and
+    // the account leaves of the updated storage tries are written on commit.
+    accounts: Option<usize>,
+    dirty_storage_roots: RefCell<HashMap<H256, H256>>,
 }
 
-fn epoch_hash(epoch: usize) -> H256 {
-    DBHasher::hash(&epoch.to_le_bytes())
+fn epoch_hash<H: MptHasher>(epoch: usize) -> H256 {
+    H::hash(&epoch.to_le_bytes())
+}
+
+// The RLP of an Ethereum account: [nonce, balance, storage_root, code_hash].
+fn account_rlp<H: MptHasher>(storage_root: &H256) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(4);
+    stream.append(&0u64);
+    stream.append(&0u64);
+    stream.append(&storage_root.as_bytes().to_vec());
+    stream.append(&H::hash(&[]).as_bytes().to_vec());
+    stream.out()
+}
+
+fn storage_root_from_rlp(account: &[u8]) -> H256 {
+    let storage_root: Vec<u8> = Rlp::new(account).val_at(2).unwrap();
+    H256::from_slice(&storage_root)
+}
+
+// The metadata record written in the same batch as each commit, so the database always describes
+// a committed trie, even if the program crashes before `flush_all`.
+#[derive(Debug, PartialEq)]
+struct Metadata {
+    root: H256,
+    epoch: usize,
+    journal: String,
+    hasher: String,
+    accounts: Option<usize>,
+    // The number of recent eras not marked canonical yet. It is 0 after `flush_all`.
+    pending_eras: usize,
 }
 
-pub(crate) fn new(backend: Arc<dyn KeyValueDB>, opts: &Options) -> MptDB {
-    let db = parity_journaldb::new(backend.clone(), Algorithm::OverlayRecent, 0);
+impl Metadata {
+    fn encode(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(6);
+        stream.append(&self.root.as_bytes().to_vec());
+        stream.append(&(self.epoch as u64));
+        stream.append(&self.journal);
+        stream.append(&self.hasher);
+        stream.append(&(self.accounts.unwrap_or(0) as u64));
+        stream.append(&(self.pending_eras as u64));
+        stream.out()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, rlp::DecoderError> {
+        let rlp = Rlp::new(data);
+        let root: Vec<u8> = rlp.val_at(0)?;
+        let accounts: u64 = rlp.val_at(4)?;
+        Ok(Self {
+            root: H256::from_slice(&root),
+            epoch: rlp.val_at::<u64>(1)? as usize,
+            journal: rlp.val_at(2)?,
+            hasher: rlp.val_at(3)?,
+            accounts: if accounts == 0 {
+                None
+            } else {
+                Some(accounts as usize)
+            },
+            pending_eras: rlp.val_at::<u64>(5)? as usize,
+        })
+    }
+}
+
+/// A Merkle proof in the format of `eth_getProof`: each list contains the RLP-encoded trie nodes
+/// on the path of the key, starting from the root.
+#[derive(Debug, Clone, Default)]
+pub struct MptProof {
+    /// The nodes in the account trie, or in the only trie if the keys are not split into accounts.
+    pub account_proof: Vec<Vec<u8>>,
+    /// The nodes in the storage trie. It is empty if the keys are not split into accounts or the
+    /// account does not exist.
+    pub storage_proof: Vec<Vec<u8>>,
+}
+
+impl MptProof {
+    /// Checks the proof against the root and returns the proven value of `key`, `None` if the
+    /// proof shows that the key does not exist. `accounts` should match the option of the
+    /// database producing the proof.
+    pub fn verify<H: MptHasher>(
+        &self,
+        root: &H256,
+        key: &[u8],
+        accounts: Option<usize>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let accounts = if let Some(accounts) = accounts {
+            accounts
+        } else {
+            return verify_in_trie::<H>(root, key, &self.account_proof);
+        };
+
+        let (account, slot) = MptDB::<H>::split_key(key, accounts);
+        match verify_in_trie::<H>(root, account.as_bytes(), &self.account_proof)? {
+            Some(account) => {
+                verify_in_trie::<H>(&storage_root_from_rlp(&account), &slot, &self.storage_proof)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.account_proof
+            .iter()
+            .chain(self.storage_proof.iter())
+            .map(Vec::len)
+            .sum()
+    }
+}
+
+// Records the nodes loaded from the database while looking up the key. The nodes embedded in
+// their parents are not loaded separately, so they are carried by the parent nodes.
+fn prove_in_trie<H: MptHasher>(
+    hash_db: &dyn HashDB<H, DBValue>,
+    root: &H256,
+    key: &[u8],
+) -> (Option<Vec<u8>>, Vec<Vec<u8>>) {
+    let mut recorder = Recorder::new();
+    let trie = TrieDB::<H>::new(&hash_db, root).unwrap();
+    let value = trie
+        .get_with(key, &mut recorder)
+        .unwrap()
+        .map(|x| x.into_vec());
+    let nodes = recorder.drain().into_iter().map(|record| record.data).collect();
+    (value, nodes)
+}
+
+// Rebuilds a partial trie from the proof nodes and looks up the key in it. A lookup reaching a
+// node out of the proof means the proof is incomplete.
+fn verify_in_trie<H: MptHasher>(
+    root: &H256,
+    key: &[u8],
+    nodes: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, String> {
+    let mut db = parity_journaldb::new_memory_db::<H>();
+    for node in nodes {
+        db.insert(node);
+    }
+
+    let trie = TrieDB::<H>::new(&db, root).map_err(|e| format!("Invalid proof: {}", e))?;
+    trie.get(key)
+        .map(|x| x.map(|x| x.into_vec()))
+        .map_err(|e| format!("Invalid proof: {}", e))
+}
+
+/// Opens the MPT, recovering the latest committed trie if the database is not empty. It fails if
+/// the database was built with a different journal algorithm, hasher or number of accounts.
+pub fn new<H: MptHasher>(
+    backend: Arc<dyn KeyValueDB>,
+    opts: &Options,
+) -> Result<MptDB<H>, String> {
+    let algorithm = match opts.journal {
+        JournalAlgo::Archive => Algorithm::Archive,
+        JournalAlgo::EarlyMerge => Algorithm::EarlyMerge,
+        JournalAlgo::OverlayRecent => Algorithm::OverlayRecent,
+        JournalAlgo::RefCounted => Algorithm::RefCounted,
+    };
+    let db = parity_journaldb::new(backend.clone(), algorithm, 0);
     let db = Arc::new(RefCell::new(db));
     let print_root_period = if opts.print_root {
         Some(opts.report_epoch)
     } else {
         None
     };
-    let root = if let Some(value) = backend.get([0u8; 256].to_vec()) {
-        H256::from_slice(&value)
-    } else {
-        RlpNodeCodec::<DBHasher>::hashed_null_node()
-    };
 
-    let journal_epoch = 0;
+    let journal_epoch = opts.journal_eras;
 
-    MptDB {
+    let mut mpt_db = MptDB {
         db,
         backing: backend,
-        root,
+        algorithm,
+        hasher: opts.hasher,
+        root: RlpNodeCodec::<H>::hashed_null_node(),
         epoch: 0,
         print_root_period,
         journal_epoch,
+        accounts: opts.mpt_accounts,
+        dirty_storage_roots: Default::default(),
+    };
+    mpt_db.recover()?;
+    Ok(mpt_db)
+}
+
+impl<H: MptHasher> MptDB<H> {
+    // Returns the secure trie keys of the account and the storage slot. The keys of the tasks are
+    // opaque hashes, so a key is assigned to one of the accounts by its hash and used as the slot
+    // key directly.
+    fn split_key(key: &[u8], accounts: usize) -> (H256, Vec<u8>) {
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&H::hash(key).as_bytes()[..8]);
+        let account = u64::from_be_bytes(prefix) % accounts as u64;
+        (H::hash(&account.to_be_bytes()), key.to_vec())
+    }
+
+    fn metadata(&self, pending_eras: usize) -> Metadata {
+        Metadata {
+            root: self.root,
+            epoch: self.epoch,
+            journal: self.algorithm.as_str().into(),
+            hasher: format!("{:?}", self.hasher).to_lowercase(),
+            accounts: self.accounts,
+            pending_eras,
+        }
+    }
+
+    fn recover(&mut self) -> Result<(), String> {
+        let stored = match self.backing.get(META_COL, META_KEY) {
+            Ok(Some(value)) => Metadata::decode(&value)
+                .map_err(|e| format!("Corrupted MPT metadata: {}", e))?,
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(format!("Cannot read MPT metadata: {}", e)),
+        };
+
+        let expected = self.metadata(0);
+        if stored.journal != expected.journal {
+            return Err(format!(
+                "The MPT database uses journal algorithm {}, but {} is selected",
+                stored.journal, expected.journal
+            ));
+        }
+        if stored.hasher != expected.hasher {
+            return Err(format!(
+                "The MPT database uses hasher {}, but {} is selected",
+                stored.hasher, expected.hasher
+            ));
+        }
+        if stored.accounts != expected.accounts {
+            return Err(format!(
+                "The MPT database splits keys into {:?} accounts, but {:?} is selected",
+                stored.accounts, expected.accounts
+            ));
+        }
+
+        // The journal is written in the same batch as the metadata, so it must cover the epoch.
+        let latest_era = self.db.borrow().latest_era();
+        if latest_era.map_or(true, |era| era < stored.epoch as u64) {
+            return Err(format!(
+                "The MPT journal (latest era {:?}) is behind the metadata (epoch {})",
+                latest_era, stored.epoch
+            ));
+        }
+
+        self.root = stored.root;
+        self.epoch = stored.epoch;
+        if stored.pending_eras > 0 {
+            // The program stopped without `flush_all`. Replay the journal of the pending eras
+            // into the backing database.
+            self.flush_eras(stored.pending_eras);
+        }
+        Ok(())
+    }
+
+    // Marks the last `eras` eras canonical and writes the metadata of a flushed database.
+    fn flush_eras(&self, eras: usize) {
+        let mut batch = DBTransaction::new();
+        let mut db = self.db.borrow_mut();
+        for i in (0..eras).into_iter().rev() {
+            let index = if let Some(index) = self.epoch.checked_sub(i) {
+                index
+            } else {
+                continue;
+            };
+            db.mark_canonical(&mut batch, index as u64, &epoch_hash::<H>(index))
+                .unwrap();
+        }
+        batch.put_vec(META_COL, META_KEY, self.metadata(0).encode());
+        db.backing().write(batch).unwrap();
+        db.flush();
+    }
+
+    /// The root of the trie as of the latest commit. In the two-level mode, it is the root of the
+    /// account trie.
+    pub fn root(&self) -> H256 {
+        self.root
+    }
+
+    /// Generates the proof of the inclusion or the exclusion of the key against `root()`. In the
+    /// two-level mode, the updates since the latest commit are reflected in neither of them.
+    pub fn prove(&self, key: &[u8]) -> MptProof {
+        let db = self.db.borrow();
+        let hash_db = db.as_hash_db();
+
+        let accounts = if let Some(accounts) = self.accounts {
+            accounts
+        } else {
+            let (_, account_proof) = prove_in_trie(hash_db, &self.root, key);
+            return MptProof {
+                account_proof,
+                storage_proof: vec![],
+            };
+        };
+
+        let (account, slot) = Self::split_key(key, accounts);
+        let (account, account_proof) = prove_in_trie(hash_db, &self.root, account.as_bytes());
+        let storage_proof = if let Some(account) = account {
+            prove_in_trie(hash_db, &storage_root_from_rlp(&account), &slot).1
+        } else {
+            vec![]
+        };
+        MptProof {
+            account_proof,
+            storage_proof,
+        }
+    }
+
+    fn storage_root(&self, account: &H256) -> H256 {
+        if let Some(root) = self.dirty_storage_roots.borrow().get(account) {
+            return *root;
+        }
+
+        let db = self.db.borrow();
+        let hash_db = &db.as_hash_db();
+        let trie = TrieDB::<H>::new(hash_db, &self.root).unwrap();
+        trie.get(account.as_bytes())
+            .unwrap()
+            .map_or(RlpNodeCodec::<H>::hashed_null_node(), |x| {
+                storage_root_from_rlp(&x)
+            })
+    }
+
+    fn get_storage(&self, key: &[u8], accounts: usize) -> Option<Box<[u8]>> {
+        let (account, slot) = Self::split_key(key, accounts);
+        let storage_root = self.storage_root(&account);
+
+        let db = self.db.borrow();
+        let hash_db = &db.as_hash_db();
+        let trie = TrieDB::<H>::new(hash_db, &storage_root).unwrap();
+        trie.get(&slot)
+            .unwrap()
+            .map(|x| x.into_vec().into_boxed_slice())
+    }
+
+    fn set_storage(&mut self, key: &[u8], value: &[u8], accounts: usize) {
+        let (account, slot) = Self::split_key(key, accounts);
+        let mut storage_root = self.storage_root(&account);
+
+        {
+            let mut db = self.db.borrow_mut();
+            let hash_db = db.as_hash_db_mut();
+            let mut trie = TrieDBMut::<H>::from_existing(hash_db, &mut storage_root).unwrap();
+            trie.insert(&slot, value).unwrap();
+        }
+
+        self.dirty_storage_roots
+            .borrow_mut()
+            .insert(account, storage_root);
+    }
+
+    fn commit_accounts(&mut self) {
+        let dirty_storage_roots = std::mem::take(&mut *self.dirty_storage_roots.borrow_mut());
+        if dirty_storage_roots.is_empty() {
+            return;
+        }
+
+        let mut db = self.db.borrow_mut();
+        let hash_db = db.as_hash_db_mut();
+        let mut trie = TrieDBMut::<H>::from_existing(hash_db, &mut self.root).unwrap();
+        for (account, storage_root) in dirty_storage_roots {
+            trie.insert(account.as_bytes(), &account_rlp::<H>(&storage_root))
+                .unwrap();
+        }
     }
 }
 
-impl AuthDB for MptDB {
+impl<H: MptHasher> AuthDB for MptDB<H> {
     // This logic is in function `require_or_from` of OpenEthereum
     fn get(&self, key: Vec<u8>) -> Option<Box<[u8]>> {
+        if let Some(accounts) = self.accounts {
+            return self.get_storage(&key, accounts);
+        }
+
         let db = self.db.borrow();
         let hash_db = &db.as_hash_db();
 
-        let trie = TrieDB::new(hash_db, &self.root).unwrap();
+        let trie = TrieDB::<H>::new(hash_db, &self.root).unwrap();
         trie.get(key.as_slice())
             .unwrap()
             .map(|x| x.into_vec().into_boxed_slice())
@@ -71,27 +422,33 @@ impl AuthDB for MptDB {
 
     // This logic is in function `commit` in `ethcore/src/state/run` of OpenEthereum
     fn set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        if let Some(accounts) = self.accounts {
+            return self.set_storage(&key, &value, accounts);
+        }
+
         let mut db = self.db.borrow_mut();
         let hash_db = db.as_hash_db_mut();
 
-        let mut trie = TrieDBMut::from_existing(hash_db, &mut self.root).unwrap();
+        let mut trie = TrieDBMut::<H>::from_existing(hash_db, &mut self.root).unwrap();
         trie.insert(key.as_slice(), value.as_slice()).unwrap();
     }
 
     // This logic is in function `commit` in `ethcore/src/state/run` of OpenEthereum
     fn commit(&mut self, index: usize) {
         self.epoch = index;
+        self.commit_accounts();
 
         let mut batch = DBTransaction::new();
         let mut db = self.db.borrow_mut();
 
         // The third parameter is not used in archive journal db. We feed an arbitrary data.
-        db.journal_under(&mut batch, index as u64, &epoch_hash(index))
+        db.journal_under(&mut batch, index as u64, &epoch_hash::<H>(index))
             .unwrap();
         if let Some(old_index) = index.checked_sub(self.journal_epoch) {
-            db.mark_canonical(&mut batch, old_index as u64, &epoch_hash(old_index))
+            db.mark_canonical(&mut batch, old_index as u64, &epoch_hash::<H>(old_index))
                 .unwrap();
         }
+        batch.put_vec(META_COL, META_KEY, self.metadata(self.journal_epoch).encode());
         db.backing().write(batch).unwrap();
         db.flush();
 
@@ -103,20 +460,11 @@ impl AuthDB for MptDB {
     }
 
     fn flush_all(&mut self) {
-        let mut batch = DBTransaction::new();
-        let mut db = self.db.borrow_mut();
-        for i in (0..self.journal_epoch).into_iter().rev() {
-            let index = self.epoch - i;
-            db.mark_canonical(&mut batch, index as u64, &epoch_hash(index))
-                .unwrap();
-        }
-        batch.ops.push(DBOp::Insert {
-            col: 0,
-            key: DBKey::from_slice(&[0u8; 256]),
-            value: self.root.to_keyed_vec(&[]),
-        });
-        db.backing().write(batch).unwrap();
-        db.flush();
+        self.flush_eras(self.journal_epoch);
+    }
+
+    fn root(&self) -> Option<Vec<u8>> {
+        Some(self.root.as_bytes().to_vec())
     }
 
     fn backend(&self) -> Option<&dyn KeyValueDB> {
@@ -124,25 +472,221 @@ impl AuthDB for MptDB {
     }
 }
 
-pub struct MptCounter {
-    journal_db: Arc<RefCell<Box<dyn JournalDB>>>,
+pub struct MptCounter<H: MptHasher> {
+    journal_db: Arc<RefCell<Box<dyn JournalDB<H>>>>,
 }
 
-impl MptCounter {
-    pub fn from_mpt_db(mpt_db: &MptDB) -> Self {
+impl<H: MptHasher> MptCounter<H> {
+    pub fn from_mpt_db(mpt_db: &MptDB<H>) -> Self {
         Self {
             journal_db: mpt_db.db.clone(),
         }
     }
 }
 
-impl CounterTrait for MptCounter {
+impl<H: MptHasher> CounterTrait for MptCounter<H> {
     fn report(&mut self) -> String {
         let mut sizes = BTreeMap::new();
         self.journal_db.borrow().get_sizes(&mut sizes);
-        format!(
-            "Recent backing size: {}",
-            sizes.get("db_overlay_recent_backing_size").unwrap()
-        )
+        if let Some(size) = sizes.get("db_overlay_recent_backing_size") {
+            format!("Recent backing size: {}", size)
+        } else {
+            format!("Journal sizes: {:?}", sizes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asb_options::StructOpt;
+    use blake3_hasher::Blake3Hasher;
+    use keccak_hasher::KeccakHasher;
+
+    fn options(args: &[&str]) -> Options {
+        Options::from_iter(["asb", "-a", "mpt"].iter().chain(args))
+    }
+
+    fn open(backend: &Arc<dyn KeyValueDB>, args: &[&str]) -> Result<MptDB<KeccakHasher>, String> {
+        new::<KeccakHasher>(backend.clone(), &options(args))
+    }
+
+    fn new_backend() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(2))
+    }
+
+    fn key(i: u64) -> Vec<u8> {
+        KeccakHasher::hash(&i.to_le_bytes()).as_bytes().to_vec()
+    }
+
+    fn value(i: u64, round: u8) -> Vec<u8> {
+        [&i.to_le_bytes()[..], &[round; 8]].concat()
+    }
+
+    #[test]
+    fn test_two_level_round_trip() {
+        let mut db = open(&new_backend(), &["--mpt-accounts", "4"]).unwrap();
+        let empty_root = db.root();
+
+        for i in 0..64 {
+            db.set(key(i), value(i, 0));
+        }
+        // The account trie is only updated on commit.
+        assert_eq!(db.root(), empty_root);
+        for i in 0..64 {
+            assert_eq!(db.get(key(i)).as_deref(), Some(&value(i, 0)[..]));
+        }
+
+        db.commit(0);
+        let root = db.root();
+        assert_ne!(root, empty_root);
+        for i in 0..64 {
+            assert_eq!(db.get(key(i)).as_deref(), Some(&value(i, 0)[..]));
+        }
+        assert_eq!(db.get(key(64)), None);
+
+        db.set(key(0), value(0, 1));
+        assert_eq!(db.root(), root);
+        assert_eq!(db.get(key(0)).as_deref(), Some(&value(0, 1)[..]));
+        db.commit(1);
+        assert_ne!(db.root(), root);
+        assert_eq!(db.get(key(0)).as_deref(), Some(&value(0, 1)[..]));
+        assert_eq!(db.get(key(1)).as_deref(), Some(&value(1, 0)[..]));
+    }
+
+    // The deepest node of the proof, which is in the storage trie in the two-level mode.
+    fn last_node(proof: &mut MptProof) -> &mut Vec<u8> {
+        if proof.storage_proof.is_empty() {
+            proof.account_proof.last_mut().unwrap()
+        } else {
+            proof.storage_proof.last_mut().unwrap()
+        }
+    }
+
+    fn check_proofs(args: &[&str], accounts: Option<usize>) {
+        let mut db = open(&new_backend(), args).unwrap();
+        for i in 0..64 {
+            db.set(key(i), value(i, 0));
+        }
+        db.commit(0);
+        let root = db.root();
+
+        for i in 0..64 {
+            let proof = db.prove(&key(i));
+            let proven = proof.verify::<KeccakHasher>(&root, &key(i), accounts);
+            assert_eq!(proven, Ok(Some(value(i, 0))));
+        }
+        for i in 64..128 {
+            let proof = db.prove(&key(i));
+            let proven = proof.verify::<KeccakHasher>(&root, &key(i), accounts);
+            assert_eq!(proven, Ok(None));
+        }
+
+        // Reject an incomplete proof
+        let mut proof = db.prove(&key(0));
+        if proof.storage_proof.pop().is_none() {
+            proof.account_proof.pop();
+        }
+        assert!(proof.verify::<KeccakHasher>(&root, &key(0), accounts).is_err());
+
+        // Reject a proof with a tampered value
+        let mut proof = db.prove(&key(0));
+        *last_node(&mut proof).last_mut().unwrap() ^= 0xff;
+        assert!(proof.verify::<KeccakHasher>(&root, &key(0), accounts).is_err());
+
+        // Reject a stale proof and accept the new one
+        let proof = db.prove(&key(0));
+        db.set(key(0), value(0, 1));
+        db.commit(1);
+        let new_root = db.root();
+        assert!(proof.verify::<KeccakHasher>(&new_root, &key(0), accounts).is_err());
+        let proof = db.prove(&key(0));
+        let proven = proof.verify::<KeccakHasher>(&new_root, &key(0), accounts);
+        assert_eq!(proven, Ok(Some(value(0, 1))));
+    }
+
+    #[test]
+    fn test_proof() {
+        check_proofs(&[], None);
+    }
+
+    #[test]
+    fn test_two_level_proof() {
+        check_proofs(&["--mpt-accounts", "4"], Some(4));
+    }
+
+    fn check_recovery(args: &[&str]) {
+        let backend = new_backend();
+        let mut db = open(&backend, args).unwrap();
+        for epoch in 0..4 {
+            for i in 0..64 {
+                db.set(key(i), value(i, epoch));
+            }
+            db.commit(epoch as usize);
+        }
+        let root = db.root();
+
+        // Reopen without `flush_all`, as if the program crashed after the commit.
+        drop(db);
+        let mut db = open(&backend, args).unwrap();
+        assert_eq!(db.root(), root);
+        for i in 0..64 {
+            assert_eq!(db.get(key(i)).as_deref(), Some(&value(i, 3)[..]));
+        }
+
+        // The recovered trie keeps committing.
+        db.set(key(0), value(0, 4));
+        db.commit(4);
+        let root = db.root();
+        drop(db);
+        let db = open(&backend, args).unwrap();
+        assert_eq!(db.root(), root);
+        assert_eq!(db.get(key(0)).as_deref(), Some(&value(0, 4)[..]));
+        assert_eq!(db.get(key(1)).as_deref(), Some(&value(1, 3)[..]));
+    }
+
+    #[test]
+    fn test_recovery() {
+        check_recovery(&[]);
+        check_recovery(&["--journal-eras", "2"]);
+        check_recovery(&["--mpt-accounts", "4", "--journal-eras", "2"]);
+    }
+
+    #[test]
+    fn test_reject_mismatched_options() {
+        let backend = new_backend();
+        let mut db = open(&backend, &[]).unwrap();
+        db.set(key(0), value(0, 0));
+        db.commit(0);
+        db.flush_all();
+        drop(db);
+
+        let err = open(&backend, &["--journal", "archive"]).err().unwrap();
+        assert!(err.contains("journal algorithm"), "{}", err);
+        let err = new::<Blake3Hasher>(backend.clone(), &options(&["--hasher", "blake3"]))
+            .err()
+            .unwrap();
+        assert!(err.contains("hasher"), "{}", err);
+        let err = open(&backend, &["--mpt-accounts", "4"]).err().unwrap();
+        assert!(err.contains("accounts"), "{}", err);
+
+        // The matching options still open the database.
+        let db = open(&backend, &[]).unwrap();
+        assert_eq!(db.get(key(0)).as_deref(), Some(&value(0, 0)[..]));
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        for accounts in [None, Some(4)] {
+            let metadata = Metadata {
+                root: KeccakHasher::hash(b"root"),
+                epoch: 42,
+                journal: Algorithm::OverlayRecent.as_str().into(),
+                hasher: "keccak".into(),
+                accounts,
+                pending_eras: 3,
+            };
+            assert_eq!(Metadata::decode(&metadata.encode()), Ok(metadata));
+        }
     }
 }
@@ -1,25 +1,76 @@
 use std::sync::{Arc, RwLock};
 
+use asb_options::{Backend, Options};
+use asb_profile::CounterTrait;
 use authdb::AuthDB;
 use kvdb::KeyValueDB;
-use rainblock_trie::MerklePatriciaTree;
+use rainblock_trie::remote::RemoteDB;
+use rainblock_trie::{MerklePatriciaTree, RlpHasher, EMPTY_ROOT};
 
-const CACHED_LEVEL: usize = 6;
-pub struct RainMpt(
-    RwLock<MerklePatriciaTree<CACHED_LEVEL>>,
+pub struct RainMpt<const N: usize, H: RlpHasher>(
+    Arc<RwLock<MerklePatriciaTree<N, H>>>,
     Arc<dyn KeyValueDB>,
 );
 
-pub fn new(backend: Arc<dyn KeyValueDB>) -> RainMpt {
-    RainMpt(
-        RwLock::new(MerklePatriciaTree::<CACHED_LEVEL>::new(backend.clone())),
-        backend,
-    )
+pub fn new<H: RlpHasher>(
+    backend: Arc<dyn KeyValueDB>,
+    opts: &Options,
+) -> (Box<dyn AuthDB>, Box<dyn CounterTrait>) {
+    let depth = opts.rain_depth;
+    // The storage node replaces the backend rather than sitting in front of it, so a backend
+    // chosen explicitly would be ignored.
+    if opts.rain_remote.is_some() && !matches!(opts.backend, Backend::RocksDB) {
+        panic!("--rain-remote replaces the backend and cannot be combined with --backend");
+    }
+    let remote = opts.rain_remote.as_ref().map(|address| {
+        let remote = RemoteDB::connect(address).expect("Cannot connect to the storage node");
+        Arc::new(remote)
+    });
+    let retain = opts.rain_retain;
+    rain_construct!(depth, H, backend, remote, retain, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10)
+}
+
+fn new_with_depth<const N: usize, H: RlpHasher>(
+    backend: Arc<dyn KeyValueDB>,
+    remote: Option<Arc<RemoteDB>>,
+    retain: Option<usize>,
+) -> (Box<dyn AuthDB>, Box<dyn CounterTrait>) {
+    // With a storage node, the trie nodes are kept there and the local backend is left unused. The
+    // I/O statistics are then counted by the client of the storage node.
+    let trie_db: Arc<dyn KeyValueDB> = match &remote {
+        Some(remote) => remote.clone(),
+        None => backend,
+    };
+    let trie = if let Some(retain) = retain {
+        MerklePatriciaTree::<N, H>::with_retention(trie_db.clone(), retain)
+    } else {
+        MerklePatriciaTree::<N, H>::new(trie_db.clone())
+    };
+    let trie = Arc::new(RwLock::new(trie));
+    let counter = RainCounter {
+        trie: trie.clone(),
+        remote,
+        pruning: retain.is_some(),
+        last_round_trips: 0,
+        last_read_bytes: 0,
+        last_pruned_bytes: 0,
+    };
+    (Box::new(RainMpt(trie, trie_db)), Box::new(counter))
 }
 
-impl AuthDB for RainMpt {
+macro_rules! rain_construct {
+    ($input: ident, $hasher: ident, $backend: ident, $remote: ident, $retain: ident, $($idx: tt),*) => {
+        match $input {
+            $($idx => new_with_depth::<$idx, $hasher>($backend, $remote, $retain),)*
+            _ => unreachable!("Unsupport depth"),
+        }
+    };
+}
+use rain_construct;
+
+impl<const N: usize, H: RlpHasher> AuthDB for RainMpt<N, H> {
     fn get(&self, key: Vec<u8>) -> Option<Box<[u8]>> {
-        self.0.write().unwrap().get(key).map(Vec::into_boxed_slice)
+        self.0.read().unwrap().get(key).map(Vec::into_boxed_slice)
     }
 
     fn set(&mut self, key: Vec<u8>, value: Vec<u8>) {
@@ -37,4 +88,49 @@ impl AuthDB for RainMpt {
     fn flush_all(&mut self) {
         self.0.write().unwrap().flush_all().unwrap()
     }
+
+    fn root(&self) -> Option<Vec<u8>> {
+        let root = self.0.read().unwrap().root();
+        Some(root.unwrap_or(EMPTY_ROOT).as_bytes().to_vec())
+    }
+}
+
+pub struct RainCounter<const N: usize, H: RlpHasher> {
+    trie: Arc<RwLock<MerklePatriciaTree<N, H>>>,
+    remote: Option<Arc<RemoteDB>>,
+    pruning: bool,
+    last_round_trips: u64,
+    last_read_bytes: u64,
+    last_pruned_bytes: u64,
+}
+
+impl<const N: usize, H: RlpHasher> CounterTrait for RainCounter<N, H> {
+    fn report(&mut self) -> String {
+        let trie = self.trie.read().unwrap();
+        let mut answer = format!(
+            "Loaded nodes: {}, memory {:.1} MB",
+            trie.loaded_nodes_count(),
+            trie.loaded_nodes_size() as f64 / (1 << 20) as f64
+        );
+        if let Some(remote) = &self.remote {
+            let (round_trips, read_bytes) = (remote.round_trips(), remote.read_bytes());
+            answer += &format!(
+                ", round trips {}, remote reads {:.1} MB",
+                round_trips - self.last_round_trips,
+                (read_bytes - self.last_read_bytes) as f64 / (1 << 20) as f64
+            );
+            self.last_round_trips = round_trips;
+            self.last_read_bytes = read_bytes;
+        }
+        if self.pruning {
+            let pruned_bytes = trie.pruned_bytes();
+            answer += &format!(
+                ", pruned {:.1} MB (total {:.1} MB)",
+                (pruned_bytes - self.last_pruned_bytes) as f64 / (1 << 20) as f64,
+                pruned_bytes as f64 / (1 << 20) as f64
+            );
+            self.last_pruned_bytes = pruned_bytes;
+        }
+        answer
+    }
 }
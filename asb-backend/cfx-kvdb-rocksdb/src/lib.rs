@@ -20,6 +20,7 @@
 
 mod stats;
 
+pub use rocksdb::{DBCompactionStyle, DBCompressionType};
 pub use stats::RunningDbStats;
 
 use std::{cmp, collections::HashMap, error, fs, io, mem, path::Path, result};
@@ -183,6 +184,27 @@ pub struct DatabaseConfig {
     pub disable_wal: bool,
     /// Enable statistics,
     pub enable_statistics: bool,
+    /// Use direct I/O for reads
+    pub direct_reads: bool,
+    /// Use direct I/O for flushes and compactions
+    pub direct_io_for_flush_and_compaction: bool,
+    /// Tuning of each column. The columns beyond the vector use the defaults.
+    pub column_tuning: Vec<ColumnTuning>,
+}
+
+/// Tuning of a column. `None` keeps the default derived from the database configuration.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnTuning {
+    /// Block size of the SST files
+    pub block_size: Option<usize>,
+    /// Bits per key of the bloom filter, 0 to disable it
+    pub bloom_filter_bits: Option<i32>,
+    /// Size of a memtable
+    pub write_buffer_size: Option<u64>,
+    /// Compaction style
+    pub compaction_style: Option<DBCompactionStyle>,
+    /// Compression of all levels
+    pub compression: Option<DBCompressionType>,
 }
 
 impl DatabaseConfig {
@@ -205,6 +227,23 @@ impl DatabaseConfig {
     pub fn memory_budget_mb(&self) -> usize {
         self.memory_budget.unwrap_or(DB_DEFAULT_MEMORY_BUDGET_MB)
     }
+
+    /// The tuning applied to the column, with the defaults filled in. The compression is `None`
+    /// if RocksDB's default is kept.
+    pub fn effective_tuning(&self, col: u32) -> ColumnTuning {
+        let tuning = self.column_tuning.get(col as usize).cloned().unwrap_or_default();
+        ColumnTuning {
+            block_size: Some(tuning.block_size.unwrap_or(self.compaction.block_size)),
+            bloom_filter_bits: Some(tuning.bloom_filter_bits.unwrap_or(10)),
+            write_buffer_size: Some(
+                tuning
+                    .write_buffer_size
+                    .unwrap_or(self.memory_budget_per_col() as u64 / 2),
+            ),
+            compaction_style: Some(tuning.compaction_style.unwrap_or(DBCompactionStyle::Level)),
+            compression: tuning.compression,
+        }
+    }
 }
 
 impl Default for DatabaseConfig {
@@ -216,6 +255,9 @@ impl Default for DatabaseConfig {
             columns: 1,
             disable_wal: false,
             enable_statistics: true,
+            direct_reads: false,
+            direct_io_for_flush_and_compaction: false,
+            column_tuning: Vec::new(),
         }
     }
 }
@@ -290,18 +332,32 @@ impl MallocSizeOf for DBAndColumns {
 }
 
 // get column family configuration from database config.
-fn col_config(
-    config: &DatabaseConfig,
-    block_opts: &BlockBasedOptions,
-) -> io::Result<ColumnFamilyOptions> {
+fn col_config(config: &DatabaseConfig, col: u32) -> io::Result<ColumnFamilyOptions> {
+    let tuning = config.effective_tuning(col);
+
+    let mut block_opts = BlockBasedOptions::default();
+    block_opts.set_block_size(tuning.block_size.unwrap());
+    // Set cache size as recommended by
+    // https://github.com/facebook/rocksdb/wiki/Setup-Options-and-Basic-Tuning#block-cache-size
+    block_opts.set_cache_index_and_filter_blocks(true);
+    block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+    let bloom_filter_bits = tuning.bloom_filter_bits.unwrap();
+    if bloom_filter_bits > 0 {
+        block_opts.set_bloom_filter(bloom_filter_bits, true);
+    }
+
     let mut opts = ColumnFamilyOptions::default();
 
     opts.set_level_compaction_dynamic_level_bytes(true);
-    opts.set_block_based_table_factory(block_opts);
+    opts.set_block_based_table_factory(&block_opts);
     opts.optimize_level_style_compaction(config.memory_budget_per_col() as i32);
     opts.set_target_file_size_base(config.compaction.initial_file_size);
-    opts.set_write_buffer_size(config.memory_budget_per_col() as u64 / 2);
+    opts.set_write_buffer_size(tuning.write_buffer_size.unwrap());
     opts.set_block_cache_size_mb(config.memory_budget_mb() as u64 / 3);
+    opts.set_compaction_style(tuning.compaction_style.unwrap());
+    if let Some(compression) = tuning.compression {
+        opts.compression(compression);
+    }
 
     Ok(opts)
 }
@@ -321,8 +377,6 @@ pub struct Database {
     write_opts: WriteOptions,
     #[ignore_malloc_size_of = "insignificant"]
     read_opts: ReadOptions,
-    #[ignore_malloc_size_of = "insignificant"]
-    block_opts: BlockBasedOptions,
     // Dirty values added with `write_buffered`. Cleaned on `flush`.
     overlay: RwLock<Vec<HashMap<DBKey, KeyState>>>,
     // Values currently being flushed. Cleared when `flush` completes.
@@ -378,6 +432,8 @@ fn generate_options(config: &DatabaseConfig) -> DBOptions {
     opts.increase_parallelism(cmp::max(1, num_cpus::get() as i32 / 2));
     opts.enable_statistics(config.enable_statistics);
     opts.create_missing_column_families(true);
+    opts.set_use_direct_reads(config.direct_reads);
+    opts.set_use_direct_io_for_flush_and_compaction(config.direct_io_for_flush_and_compaction);
 
     opts
 }
@@ -392,14 +448,6 @@ impl Database {
 
     /// Open database file. Creates if it does not exist.
     pub fn open(config: &DatabaseConfig, path: &str) -> io::Result<Database> {
-        let mut block_opts = BlockBasedOptions::default();
-        block_opts.set_block_size(config.compaction.block_size);
-        // Set cache size as recommended by
-        // https://github.com/facebook/rocksdb/wiki/Setup-Options-and-Basic-Tuning#block-cache-size
-        block_opts.set_cache_index_and_filter_blocks(true);
-        block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
-        block_opts.set_bloom_filter(10, true);
-
         let opts = generate_options(config);
 
         // attempt database repair if it has been previously marked as corrupted
@@ -420,7 +468,7 @@ impl Database {
         let cfnames: Vec<&str> = column_names.iter().map(|n| n as &str).collect();
 
         for i in 0..config.columns {
-            cf_options.push((cfnames[i as usize], col_config(&config, &block_opts)?));
+            cf_options.push((cfnames[i as usize], col_config(&config, i)?));
         }
 
         let mut write_opts = WriteOptions::new();
@@ -482,7 +530,6 @@ impl Database {
             opts,
             read_opts,
             write_opts,
-            block_opts,
             stats: stats::RunningDbStats::new(),
             my_stat: RwLock::new(MyStat::new(config.enable_statistics)),
         })
@@ -703,6 +750,15 @@ impl Database {
             .unwrap_or(0)
     }
 
+    /// The total size of the SST files of a column in bytes.
+    pub fn column_sst_size(&self, col: u32) -> Option<u64> {
+        self.db.read().as_ref().and_then(|db| {
+            db.db
+                .get_property_int_cf(db.get_cf(col as usize), "rocksdb.total-sst-files-size")
+                .map(|size| size as u64)
+        })
+    }
+
     /// Drop a column family.
     pub fn drop_column(&self) -> io::Result<()> {
         match *self.db.write() {
@@ -728,7 +784,7 @@ impl Database {
             }) => {
                 let col = column_names.len() as u32;
                 let name = format!("col{}", col);
-                db.create_cf((name.as_str(), col_config(&self.config, &self.block_opts)?))
+                db.create_cf((name.as_str(), col_config(&self.config, col)?))
                     .map_err(other_io_err)?;
                 column_names.push(name);
                 Ok(())
@@ -948,6 +1004,38 @@ mod tests {
         test_db(&DatabaseConfig::default());
     }
 
+    #[test]
+    fn kvdb_with_tuning() {
+        let mut config = DatabaseConfig::default();
+        config.column_tuning = vec![ColumnTuning {
+            block_size: Some(4 * KB),
+            bloom_filter_bits: Some(0),
+            write_buffer_size: Some(4 * MB as u64),
+            compaction_style: Some(DBCompactionStyle::Universal),
+            compression: Some(DBCompressionType::No),
+        }];
+        test_db(&config);
+    }
+
+    #[test]
+    fn effective_tuning() {
+        let mut config = DatabaseConfig::with_columns(2);
+        config.column_tuning = vec![ColumnTuning {
+            block_size: Some(4 * KB),
+            ..Default::default()
+        }];
+
+        let tuning = config.effective_tuning(0);
+        assert_eq!(tuning.block_size, Some(4 * KB));
+        assert_eq!(tuning.bloom_filter_bits, Some(10));
+        let tuning = config.effective_tuning(1);
+        assert_eq!(tuning.block_size, Some(config.compaction.block_size));
+        assert_eq!(
+            tuning.write_buffer_size,
+            Some(config.memory_budget_per_col() as u64 / 2)
+        );
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn df_to_rotational() {
@@ -0,0 +1,179 @@
+//! Serves Parity's binding of RocksDB (`kvdb-rocksdb` 0.9) to the `rocksdb-parity` backend, which
+//! spawns this process on the database directory.
+//!
+//! The requests are read from the standard input and the responses are written to the standard
+//! output, in the little-endian binary frames described in `asb-backend/src/parity_rocksdb.rs`.
+//! The process closes the database and exits when its input is closed.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use kvdb::{DBTransaction, IoStatsKind, KeyValueDB};
+use kvdb_rocksdb::{CompactionProfile, Database, DatabaseConfig};
+
+const OP_GET: u8 = 0;
+const OP_GET_BY_PREFIX: u8 = 1;
+const OP_WRITE: u8 = 2;
+const OP_ITER_FROM_PREFIX: u8 = 3;
+const OP_RESTORE: u8 = 4;
+const OP_IO_STATS: u8 = 5;
+
+const KIND_INSERT: u8 = 0;
+const KIND_DELETE: u8 = 1;
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+
+const USAGE: &str = "Usage: parity-rocksdb-node <db_dir> <num_cols>";
+
+enum Request {
+    Get(u32, Vec<u8>),
+    GetByPrefix(u32, Vec<u8>),
+    Write(DBTransaction),
+    IterFromPrefix(u32, Vec<u8>),
+    Restore(String),
+    IoStats(IoStatsKind),
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; read_u32(reader)? as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn put_bytes(frame: &mut Vec<u8>, data: &[u8]) {
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(data);
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_request(reader: &mut impl Read) -> io::Result<Request> {
+    Ok(match read_u8(reader)? {
+        OP_GET => Request::Get(read_u32(reader)?, read_bytes(reader)?),
+        OP_GET_BY_PREFIX => Request::GetByPrefix(read_u32(reader)?, read_bytes(reader)?),
+        OP_WRITE => {
+            let mut transaction = DBTransaction::new();
+            for _ in 0..read_u32(reader)? {
+                let kind = read_u8(reader)?;
+                let col = read_u32(reader)?;
+                let key = read_bytes(reader)?;
+                match kind {
+                    KIND_INSERT => transaction.put_vec(col, &key, read_bytes(reader)?),
+                    KIND_DELETE => transaction.delete(col, &key),
+                    kind => return Err(invalid_data(format!("Unknown operation {}", kind))),
+                }
+            }
+            Request::Write(transaction)
+        }
+        OP_ITER_FROM_PREFIX => Request::IterFromPrefix(read_u32(reader)?, read_bytes(reader)?),
+        OP_RESTORE => {
+            let path = String::from_utf8(read_bytes(reader)?)
+                .map_err(|e| invalid_data(e.to_string()))?;
+            Request::Restore(path)
+        }
+        OP_IO_STATS => Request::IoStats(match read_u8(reader)? {
+            0 => IoStatsKind::Overall,
+            _ => IoStatsKind::SincePrevious,
+        }),
+        op => return Err(invalid_data(format!("Unknown opcode {}", op))),
+    })
+}
+
+fn put_value(response: &mut Vec<u8>, value: Option<&[u8]>) {
+    if let Some(value) = value {
+        response.push(1);
+        put_bytes(response, value);
+    } else {
+        response.push(0);
+    }
+}
+
+// Appends the payload of the response to the request.
+fn execute(db: &Database, request: Request, response: &mut Vec<u8>) -> io::Result<()> {
+    match request {
+        Request::Get(col, key) => put_value(response, db.get(col, &key)?.as_deref()),
+        Request::GetByPrefix(col, prefix) => {
+            put_value(response, db.get_by_prefix(col, &prefix).as_deref())
+        }
+        Request::Write(transaction) => db.write(transaction)?,
+        Request::IterFromPrefix(col, prefix) => {
+            let items: Vec<_> = db.iter_with_prefix(col, &prefix).collect();
+            response.extend_from_slice(&(items.len() as u32).to_le_bytes());
+            for (key, value) in items {
+                put_bytes(response, &key);
+                put_bytes(response, &value);
+            }
+        }
+        Request::Restore(path) => db.restore(&path)?,
+        Request::IoStats(kind) => {
+            let stats = db.io_stats(kind);
+            for counter in [
+                stats.transactions,
+                stats.reads,
+                stats.cache_reads,
+                stats.writes,
+                stats.bytes_read,
+                stats.cache_read_bytes,
+                stats.bytes_written,
+                stats.span.as_nanos() as u64,
+            ] {
+                response.extend_from_slice(&counter.to_le_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Serves until the input is closed. A request failing in the database gets an error response, but
+// a malformed request ends the process, as the rest of the input cannot be parsed.
+fn serve(db: &Database, reader: &mut impl Read, writer: &mut impl Write) -> io::Result<()> {
+    loop {
+        let request = match read_request(reader) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            request => request?,
+        };
+        let mut response = vec![STATUS_OK];
+        if let Err(e) = execute(db, request, &mut response) {
+            response = vec![STATUS_ERROR];
+            put_bytes(&mut response, e.to_string().as_bytes());
+        }
+        writer.write_all(&response)?;
+        writer.flush()?;
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let db_dir = args.next().expect(USAGE);
+    let num_cols: u32 = args.next().and_then(|x| x.parse().ok()).expect(USAGE);
+
+    let mut config = DatabaseConfig::with_columns(num_cols);
+    config.enable_statistics = false;
+    config.compaction = CompactionProfile::auto(Path::new(&db_dir));
+    let db = Database::open(&config, &db_dir)
+        .unwrap_or_else(|e| panic!("Cannot open the database {}: {}", db_dir, e));
+
+    // The standard output carries the responses, so the messages go to the standard error.
+    let (stdin, stdout) = (io::stdin(), io::stdout());
+    let mut reader = BufReader::new(stdin.lock());
+    let mut writer = BufWriter::new(stdout.lock());
+    if let Err(e) = serve(&db, &mut reader, &mut writer) {
+        eprintln!("parity-rocksdb-node: {}", e);
+        std::process::exit(1);
+    }
+}
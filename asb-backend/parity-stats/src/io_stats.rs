@@ -0,0 +1,70 @@
+//! The I/O statistics of the key-value databases which do not count them by themselves.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use kvdb::{IoStats, IoStatsKind};
+
+#[derive(Default, Clone, Copy)]
+pub struct Tally {
+    pub transactions: u64,
+    pub reads: u64,
+    pub cache_reads: u64,
+    pub writes: u64,
+    pub bytes_read: u64,
+    pub cache_read_bytes: u64,
+    pub bytes_written: u64,
+}
+
+pub struct RunningStats {
+    // The tallies since the start and since the previous query, with their start time.
+    inner: Mutex<[(Tally, Instant); 2]>,
+}
+
+impl RunningStats {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            inner: Mutex::new([(Tally::default(), now); 2]),
+        }
+    }
+
+    pub fn tally<F: Fn(&mut Tally)>(&self, f: F) {
+        for (tally, _) in self.inner.lock().unwrap().iter_mut() {
+            f(tally);
+        }
+    }
+
+    /// The tally since the start.
+    pub fn overall(&self) -> Tally {
+        self.inner.lock().unwrap()[0].0
+    }
+
+    pub fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        let inner = &mut *self.inner.lock().unwrap();
+        let (tally, started) = match kind {
+            IoStatsKind::Overall => inner[0],
+            IoStatsKind::SincePrevious => {
+                ::std::mem::replace(&mut inner[1], (Tally::default(), Instant::now()))
+            }
+        };
+
+        let mut io_stats = IoStats::empty();
+        io_stats.transactions = tally.transactions;
+        io_stats.reads = tally.reads;
+        io_stats.cache_reads = tally.cache_reads;
+        io_stats.writes = tally.writes;
+        io_stats.bytes_read = tally.bytes_read;
+        io_stats.cache_read_bytes = tally.cache_read_bytes;
+        io_stats.bytes_written = tally.bytes_written;
+        io_stats.started = started;
+        io_stats.span = started.elapsed();
+        io_stats
+    }
+}
+
+impl Default for RunningStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -24,8 +24,11 @@ use std::{
 
 #[macro_use]
 extern crate log;
+extern crate kvdb;
 pub extern crate prometheus;
 
+pub mod io_stats;
+
 pub struct PrometheusRegistry {
     prefix: String,
     registry: prometheus::Registry,
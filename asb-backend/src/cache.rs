@@ -0,0 +1,568 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use asb_options::CachePolicy;
+use asb_profile::{CacheStat, CacheStats};
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+
+type CacheKey = (u32, Vec<u8>);
+// A cached `None` records that the key is missing in the backend.
+type CacheValue = Option<DBValue>;
+
+// The bytes charged for an entry, which are the bytes of the key and the value.
+fn entry_size(key: &CacheKey, value: &CacheValue) -> usize {
+    key.1.len() + value.as_ref().map_or(0, |v| v.len())
+}
+
+// The entries in the order of their last use, with their total bytes.
+struct RecencyList<V> {
+    entries: HashMap<CacheKey, (V, usize, u64)>,
+    order: BTreeMap<u64, CacheKey>,
+    bytes: usize,
+    tick: u64,
+}
+
+impl<V> RecencyList<V> {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            bytes: 0,
+            tick: 0,
+        }
+    }
+
+    fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Returns the value and makes the entry the most recently used.
+    fn touch(&mut self, key: &CacheKey) -> Option<&V> {
+        self.tick += 1;
+        let (value, _, tick) = self.entries.get_mut(key)?;
+        let key = self.order.remove(tick).unwrap();
+        *tick = self.tick;
+        self.order.insert(self.tick, key);
+        Some(value)
+    }
+
+    // Inserts the entry as the most recently used one.
+    fn insert(&mut self, key: CacheKey, value: V, size: usize) {
+        self.remove(&key);
+        self.tick += 1;
+        self.bytes += size;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, size, self.tick));
+    }
+
+    // Replaces the value of an entry in place. Returns false if the entry is absent.
+    fn replace(&mut self, key: &CacheKey, value: V, size: usize) -> bool {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.bytes = self.bytes - entry.1 + size;
+            entry.0 = value;
+            entry.1 = size;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<(V, usize)> {
+        let (value, size, tick) = self.entries.remove(key)?;
+        self.order.remove(&tick);
+        self.bytes -= size;
+        Some((value, size))
+    }
+
+    fn pop_least_recent(&mut self) -> Option<(CacheKey, V, usize)> {
+        let (_, key) = self.order.pop_first()?;
+        let (value, size, _) = self.entries.remove(&key).unwrap();
+        self.bytes -= size;
+        Some((key, value, size))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+trait Policy: Send {
+    // Looks up a key, counting as an access.
+    fn get(&mut self, key: &CacheKey) -> Option<CacheValue>;
+    // Admits the value read from the backend after a miss.
+    fn admit(&mut self, key: CacheKey, value: CacheValue);
+    // Updates the value of the key if it is cached, without counting as an access.
+    fn update(&mut self, key: &CacheKey, value: CacheValue);
+    fn clear(&mut self);
+    fn bytes(&self) -> usize;
+}
+
+struct LruCache {
+    capacity: usize,
+    list: RecencyList<CacheValue>,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            list: RecencyList::new(),
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.list.bytes > self.capacity {
+            self.list.pop_least_recent();
+        }
+    }
+}
+
+impl Policy for LruCache {
+    fn get(&mut self, key: &CacheKey) -> Option<CacheValue> {
+        self.list.touch(key).cloned()
+    }
+
+    fn admit(&mut self, key: CacheKey, value: CacheValue) {
+        let size = entry_size(&key, &value);
+        self.list.insert(key, value, size);
+        self.evict();
+    }
+
+    fn update(&mut self, key: &CacheKey, value: CacheValue) {
+        let size = entry_size(key, &value);
+        if self.list.replace(key, value, size) {
+            self.evict();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    fn bytes(&self) -> usize {
+        self.list.bytes
+    }
+}
+
+/// Adaptive replacement cache (Megiddo and Modha, FAST 2003), with the lists bounded in bytes
+/// rather than in entries. `t1` holds the entries used once recently and `t2` the entries used at
+/// least twice. `b1` and `b2` remember the keys evicted from them, and a hit on them moves the
+/// target bytes of `t1` towards the list which would have kept the entry.
+struct AdaptiveCache {
+    capacity: usize,
+    target: usize,
+    t1: RecencyList<CacheValue>,
+    t2: RecencyList<CacheValue>,
+    b1: RecencyList<()>,
+    b2: RecencyList<()>,
+}
+
+impl AdaptiveCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            target: 0,
+            t1: RecencyList::new(),
+            t2: RecencyList::new(),
+            b1: RecencyList::new(),
+            b2: RecencyList::new(),
+        }
+    }
+
+    fn resident_bytes(&self) -> usize {
+        self.t1.bytes + self.t2.bytes
+    }
+
+    // Evicts the resident entries to the ghost lists until they fit in the capacity.
+    fn evict(&mut self, hit_b2: bool) {
+        while self.resident_bytes() > self.capacity {
+            let from_t1 = !self.t1.is_empty()
+                && (self.t1.bytes > self.target
+                    || (hit_b2 && self.t1.bytes == self.target)
+                    || self.t2.is_empty());
+            if from_t1 {
+                let (key, _, size) = self.t1.pop_least_recent().unwrap();
+                self.b1.insert(key, (), size);
+            } else {
+                let (key, _, size) = self.t2.pop_least_recent().unwrap();
+                self.b2.insert(key, (), size);
+            }
+        }
+
+        // The ghost lists remember at most the capacity in bytes together with the resident ones.
+        while self.t1.bytes + self.b1.bytes > self.capacity && !self.b1.is_empty() {
+            self.b1.pop_least_recent();
+        }
+        while self.resident_bytes() + self.b1.bytes + self.b2.bytes > 2 * self.capacity
+            && !self.b2.is_empty()
+        {
+            self.b2.pop_least_recent();
+        }
+    }
+}
+
+impl Policy for AdaptiveCache {
+    fn get(&mut self, key: &CacheKey) -> Option<CacheValue> {
+        if let Some((value, size)) = self.t1.remove(key) {
+            self.t2.insert(key.clone(), value.clone(), size);
+            return Some(value);
+        }
+        self.t2.touch(key).cloned()
+    }
+
+    fn admit(&mut self, key: CacheKey, value: CacheValue) {
+        let size = entry_size(&key, &value);
+        let hit_b2 = if self.b1.contains(&key) {
+            let delta = size.max(size * self.b2.bytes / self.b1.bytes.max(1));
+            self.target = self.capacity.min(self.target + delta);
+            self.b1.remove(&key);
+            self.t2.insert(key, value, size);
+            false
+        } else if self.b2.contains(&key) {
+            let delta = size.max(size * self.b1.bytes / self.b2.bytes.max(1));
+            self.target = self.target.saturating_sub(delta);
+            self.b2.remove(&key);
+            self.t2.insert(key, value, size);
+            true
+        } else {
+            self.t1.insert(key, value, size);
+            false
+        };
+        self.evict(hit_b2);
+    }
+
+    fn update(&mut self, key: &CacheKey, value: CacheValue) {
+        let size = entry_size(key, &value);
+        if self.t1.replace(key, value.clone(), size) || self.t2.replace(key, value, size) {
+            self.evict(false);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.target = 0;
+        self.t1.clear();
+        self.t2.clear();
+        self.b1.clear();
+        self.b2.clear();
+    }
+
+    fn bytes(&self) -> usize {
+        self.resident_bytes()
+    }
+}
+
+// The cache is split into shards by the key, so the accesses to different keys seldom contend.
+const SHARDS: usize = 16;
+
+// The reads of the inner database after a miss of a key, which may race with its writes.
+struct PendingRead {
+    readers: usize,
+    // The generation of the last write of the key during the reads.
+    written: u64,
+}
+
+struct Shard {
+    policy: Box<dyn Policy>,
+    // Increased by every write to the shard.
+    generation: u64,
+    pending: HashMap<CacheKey, PendingRead>,
+}
+
+impl Shard {
+    // Starts a read of the inner database after a miss, and returns the current generation.
+    fn begin_read(&mut self, key: &CacheKey) -> u64 {
+        let pending = self.pending.entry(key.clone()).or_insert(PendingRead {
+            readers: 0,
+            written: 0,
+        });
+        pending.readers += 1;
+        self.generation
+    }
+
+    // Ends a read started at the generation `started`. The value read is admitted only if the key
+    // has not been written since, as it may be stale otherwise.
+    fn end_read(&mut self, key: CacheKey, value: Option<CacheValue>, started: u64) {
+        let pending = self.pending.get_mut(&key).unwrap();
+        let stale = pending.written > started;
+        pending.readers -= 1;
+        if pending.readers == 0 {
+            self.pending.remove(&key);
+        }
+        if let Some(value) = value.filter(|_| !stale) {
+            self.policy.admit(key, value);
+        }
+    }
+
+    fn write(&mut self, key: &CacheKey, value: CacheValue) {
+        self.generation += 1;
+        if let Some(pending) = self.pending.get_mut(key) {
+            pending.written = self.generation;
+        }
+        self.policy.update(key, value);
+    }
+}
+
+/// A wrapper caching the values read from the inner database, bounded by the bytes of the cached
+/// keys and values. The cache is split into shards by the key, each with an equal share of the
+/// capacity. The written values replace the cached ones once they are written to the inner
+/// database, but are not admitted otherwise. The prefix reads and the iterators bypass the cache.
+pub struct CachedDB {
+    inner: Arc<dyn KeyValueDB>,
+    shards: Vec<Mutex<Shard>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachedDB {
+    pub fn new(inner: Arc<dyn KeyValueDB>, capacity: usize, policy: CachePolicy) -> Self {
+        let shards = (0..SHARDS)
+            .map(|_| {
+                let capacity = capacity / SHARDS;
+                let policy: Box<dyn Policy> = match policy {
+                    CachePolicy::LRU => Box::new(LruCache::new(capacity)),
+                    CachePolicy::ARC => Box::new(AdaptiveCache::new(capacity)),
+                };
+                Mutex::new(Shard {
+                    policy,
+                    generation: 0,
+                    pending: HashMap::new(),
+                })
+            })
+            .collect();
+        Self {
+            inner,
+            shards,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, key: &CacheKey) -> &Mutex<Shard> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn updates(transaction: &DBTransaction) -> Vec<(CacheKey, CacheValue)> {
+        let updates = transaction.ops.iter().map(|op| {
+            let value = match op {
+                DBOp::Insert { value, .. } => Some(value.clone()),
+                DBOp::Delete { .. } => None,
+            };
+            ((op.col(), op.key().to_vec()), value)
+        });
+        updates.collect()
+    }
+
+    // Applies the updates after they are written to the inner database, so a read missing the
+    // cache meanwhile either reads the new values or has its value discarded.
+    fn apply(&self, updates: Vec<(CacheKey, CacheValue)>) {
+        for (key, value) in updates {
+            self.shard(&key).lock().unwrap().write(&key, value);
+        }
+    }
+
+    fn cached_bytes(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().policy.bytes())
+            .sum()
+    }
+}
+
+impl KeyValueDB for CachedDB {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let key = (col, key.to_vec());
+        let shard = self.shard(&key);
+        let started = {
+            let shard = &mut *shard.lock().unwrap();
+            if let Some(value) = shard.policy.get(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
+            }
+            shard.begin_read(&key)
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // The lock is not held during the read. If the key is written meanwhile, the value read
+        // here is not admitted.
+        let value = self.inner.get(col, &key.1);
+        let admitted = value.as_ref().ok().cloned();
+        shard.lock().unwrap().end_read(key, admitted, started);
+        value
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.inner.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        let updates = Self::updates(&transaction);
+        self.inner.write_buffered(transaction);
+        self.apply(updates);
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        let updates = Self::updates(&transaction);
+        self.inner.write(transaction)?;
+        self.apply(updates);
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        for shard in &self.shards {
+            shard.lock().unwrap().policy.clear();
+        }
+        self.inner.restore(new_db)
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.inner.io_stats(kind)
+    }
+}
+
+impl CacheStats for CachedDB {
+    fn cache_stats(&self) -> CacheStat {
+        CacheStat {
+            hits: self.hits.swap(0, Ordering::Relaxed),
+            misses: self.misses.swap(0, Ordering::Relaxed),
+            bytes: self.cached_bytes() as u64,
+        }
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for CachedDB {
+    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        parity_util_mem::MallocSizeOf::size_of(&*self.inner, ops) + self.cached_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u8) -> CacheKey {
+        (0, vec![i])
+    }
+
+    // A value taking `size` bytes in the cache together with its key.
+    fn value(size: usize) -> CacheValue {
+        Some(vec![0; size - 1])
+    }
+
+    #[test]
+    fn lru_eviction_and_update() {
+        let mut cache = LruCache::new(30);
+        for i in 1..=3 {
+            cache.admit(key(i), value(10));
+        }
+        assert_eq!(cache.bytes(), 30);
+
+        // The least recently used entry is evicted once the bytes exceed the capacity.
+        assert!(cache.get(&key(1)).is_some());
+        cache.admit(key(4), value(10));
+        assert!(!cache.list.contains(&key(2)));
+        assert_eq!(cache.bytes(), 30);
+
+        // A larger value of a cached entry evicts the others, without making it recently used.
+        cache.update(&key(1), value(20));
+        assert!(!cache.list.contains(&key(3)));
+        assert!(cache.list.contains(&key(4)));
+        assert_eq!(cache.bytes(), 30);
+        assert_eq!(cache.get(&key(1)), Some(value(20)));
+
+        // The keys which are not cached are not admitted on update.
+        cache.update(&key(2), value(10));
+        assert!(!cache.list.contains(&key(2)));
+        assert_eq!(cache.bytes(), 30);
+    }
+
+    #[test]
+    fn arc_promotion_and_target() {
+        let mut cache = AdaptiveCache::new(40);
+        cache.admit(key(1), value(10));
+        cache.admit(key(2), value(10));
+
+        // A second use promotes the entry from t1 to t2.
+        assert!(cache.get(&key(1)).is_some());
+        assert!(cache.t2.contains(&key(1)));
+        assert!(cache.t1.contains(&key(2)));
+
+        cache.admit(key(3), value(10));
+        cache.admit(key(4), value(10));
+        cache.admit(key(5), value(10));
+        assert!(cache.b1.contains(&key(2)));
+        assert_eq!(cache.bytes(), 40);
+
+        // A hit on b1 moves the target towards t1, and the entry goes to t2.
+        cache.admit(key(2), value(10));
+        assert_eq!(cache.target, 10);
+        assert!(cache.t2.contains(&key(2)));
+        assert!(cache.b1.contains(&key(3)));
+
+        assert!(cache.get(&key(4)).is_some());
+        assert!(cache.get(&key(5)).is_some());
+        cache.admit(key(6), value(10));
+        assert!(cache.b2.contains(&key(1)));
+
+        // A hit on b2 moves the target towards t2.
+        cache.admit(key(1), value(10));
+        assert_eq!(cache.target, 0);
+        assert!(cache.t2.contains(&key(1)));
+        assert!(cache.b1.contains(&key(6)));
+        assert_eq!(cache.bytes(), 40);
+
+        // A larger value of a cached entry evicts the least recently used one in t2.
+        cache.update(&key(5), value(20));
+        assert!(cache.b2.contains(&key(2)));
+        assert_eq!(cache.bytes(), 40);
+        assert_eq!(cache.get(&key(5)), Some(value(20)));
+    }
+
+    #[test]
+    fn write_during_miss() {
+        let mut shard = Shard {
+            policy: Box::new(LruCache::new(100)),
+            generation: 0,
+            pending: HashMap::new(),
+        };
+
+        // The value read before a write of the key is stale, so it is not admitted.
+        let started = shard.begin_read(&key(1));
+        shard.write(&key(1), value(10));
+        shard.end_read(key(1), Some(value(5)), started);
+        assert!(shard.policy.get(&key(1)).is_none());
+        assert!(shard.pending.is_empty());
+
+        let started = shard.begin_read(&key(1));
+        shard.write(&key(2), value(10));
+        shard.end_read(key(1), Some(value(5)), started);
+        assert_eq!(shard.policy.get(&key(1)), Some(value(5)));
+    }
+}
@@ -5,18 +5,27 @@ use cfx_kvdb_rocksdb::{CompactionProfile, Database, DatabaseConfig};
 
 use asb_options::Options;
 
+use crate::rocksdb_profile::{self, RocksDbProfile};
+
 pub fn open(db_dir: &str, opts: &Options) -> Arc<Database> {
     let mut db_config = DatabaseConfig::with_columns(opts.num_cols());
 
     db_config.memory_budget = Some(opts.cache_size as usize);
     db_config.compaction = CompactionProfile::auto(Path::new(db_dir));
     db_config.disable_wal = false;
-    #[cfg(not(any(feature = "parity-backend", feature = "lmpts-backend")))]
-    {
-        db_config.enable_statistics = !opts.no_stat;
+    db_config.enable_statistics = !opts.no_stat;
+
+    if let Some(path) = &opts.rocksdb_profile {
+        let profile = RocksDbProfile::load(path)
+            .unwrap_or_else(|e| panic!("Fail to load the RocksDB profile {}: {}", path, e));
+        profile.apply(&mut db_config).unwrap();
     }
 
     let db = Database::open(&db_config, db_dir).unwrap();
 
+    if let Some(report_dir) = &opts.report_dir {
+        rocksdb_profile::record(&db_config, db_dir, report_dir);
+    }
+
     Arc::new(db)
 }
@@ -1,30 +1,29 @@
-#![allow(unused)]
-
-use std::io::Read;
-use std::sync::Arc;
+use std::io;
+use std::sync::{Arc, Mutex};
 
+use asb_profile::{ColumnStat, ColumnStats, EngineStat, EngineStats};
 use cfx_kvdb_rocksdb::Database;
-use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB};
+use kvdb::{DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
 use parity_util_mem::{MallocSizeOf, MallocSizeOfOps};
 
-// Database with enabled statistics
+use crate::io_stats::ColumnTallies;
+
+/// Database with the statistics of each column. The calls go through `KeyValueDB` of the inner
+/// database, so its overall statistics are still collected.
 pub struct DatabaseWithMetrics {
     db: Arc<Database>,
-    pub reads: std::sync::atomic::AtomicI64,
-    pub writes: std::sync::atomic::AtomicI64,
-    bytes_read: std::sync::atomic::AtomicI64,
-    bytes_written: std::sync::atomic::AtomicI64,
+    columns: ColumnTallies,
+    // The tickers of RocksDB at the previous sample, as they are accumulated since the open.
+    last_tickers: Mutex<EngineStat>,
 }
 
 impl DatabaseWithMetrics {
     /// Create a new instance
-    pub fn new(db: Arc<Database>) -> Self {
+    pub fn new(db: Arc<Database>, num_cols: u32) -> Self {
         Self {
             db,
-            reads: std::sync::atomic::AtomicI64::new(0),
-            writes: std::sync::atomic::AtomicI64::new(0),
-            bytes_read: std::sync::atomic::AtomicI64::new(0),
-            bytes_written: std::sync::atomic::AtomicI64::new(0),
+            columns: ColumnTallies::new(num_cols),
+            last_tickers: Mutex::new(EngineStat::default()),
         }
     }
 }
@@ -36,68 +35,31 @@ impl MallocSizeOf for DatabaseWithMetrics {
 }
 
 impl KeyValueDB for DatabaseWithMetrics {
-    fn get(&self, col: u32, key: &[u8]) -> std::io::Result<Option<DBValue>> {
-        let res = self.db.get(col, key);
-        let count = res
-            .as_ref()
-            .map_or(0, |y| y.as_ref().map_or(0, |x| x.bytes().count()));
-
-        self.reads
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        self.bytes_read
-            .fetch_add(count as i64, std::sync::atomic::Ordering::Relaxed);
-
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let res = KeyValueDB::get(&*self.db, col, key);
+        let bytes = res.as_ref().map_or(0, |y| y.as_ref().map_or(0, |x| x.len()));
+        self.columns.tally_read(col, bytes);
         res
     }
 
     fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
-        let res = self.db.get_by_prefix(col, prefix);
-        let count = res.as_ref().map_or(0, |x| x.bytes().count());
-
-        self.reads
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        self.bytes_read
-            .fetch_add(count as i64, std::sync::atomic::Ordering::Relaxed);
-
+        let res = KeyValueDB::get_by_prefix(&*self.db, col, prefix);
+        self.columns.tally_read(col, res.as_ref().map_or(0, |x| x.len()));
         res
     }
-    fn write_buffered(&self, transaction: DBTransaction) {
-        let mut count = 0;
-        for op in &transaction.ops {
-            count += match op {
-                DBOp::Insert { value, .. } => value.bytes().count(),
-                _ => 0,
-            };
-        }
-
-        self.writes.fetch_add(
-            transaction.ops.len() as i64,
-            std::sync::atomic::Ordering::Relaxed,
-        );
-        self.bytes_written
-            .fetch_add(count as i64, std::sync::atomic::Ordering::Relaxed);
 
-        self.db.write_buffered(transaction)
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.columns.tally_writes(&transaction);
+        KeyValueDB::write_buffered(&*self.db, transaction)
     }
-    fn write(&self, transaction: DBTransaction) -> std::io::Result<()> {
-        let mut count = 0;
-        for op in &transaction.ops {
-            count += match op {
-                DBOp::Insert { value, .. } => value.bytes().count(),
-                _ => 0,
-            };
-        }
 
-        self.bytes_written
-            .fetch_add(count as i64, std::sync::atomic::Ordering::Relaxed);
-        self.writes.fetch_add(
-            transaction.ops.len() as i64,
-            std::sync::atomic::Ordering::Relaxed,
-        );
-        self.db.write(transaction)
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.columns.tally_writes(&transaction);
+        KeyValueDB::write(&*self.db, transaction)
     }
-    fn flush(&self) -> std::io::Result<()> {
-        self.db.flush()
+
+    fn flush(&self) -> io::Result<()> {
+        KeyValueDB::flush(&*self.db)
     }
 
     fn iter<'a>(&'a self, col: u32) -> Box<(dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a)> {
@@ -109,36 +71,54 @@ impl KeyValueDB for DatabaseWithMetrics {
         col: u32,
         prefix: &'a [u8],
     ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
-        self.db.iter_from_prefix(col, prefix)
+        KeyValueDB::iter_from_prefix(&*self.db, col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        KeyValueDB::restore(&*self.db, new_db)
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        KeyValueDB::io_stats(&*self.db, kind)
     }
+}
 
-    fn restore(&self, new_db: &str) -> std::io::Result<()> {
-        self.db.restore(new_db)
+impl ColumnStats for DatabaseWithMetrics {
+    fn column_stats(&self) -> Vec<ColumnStat> {
+        self.columns.column_stats(|col| self.db.column_sst_size(col))
     }
 }
 
-impl stats::PrometheusMetrics for DatabaseWithMetrics {
-    fn prometheus_metrics(&self, p: &mut stats::PrometheusRegistry) {
-        p.register_counter(
-            "kvdb_reads",
-            "db reads",
-            self.reads.load(std::sync::atomic::Ordering::Relaxed) as i64,
-        );
-        p.register_counter(
-            "kvdb_writes",
-            "db writes",
-            self.writes.load(std::sync::atomic::Ordering::Relaxed) as i64,
-        );
-        p.register_counter(
-            "kvdb_bytes_read",
-            "db bytes_reads",
-            self.bytes_read.load(std::sync::atomic::Ordering::Relaxed) as i64,
-        );
-        p.register_counter(
-            "kvdb_bytes_written",
-            "db bytes_written",
-            self.bytes_written
-                .load(std::sync::atomic::Ordering::Relaxed) as i64,
-        );
+impl EngineStats for DatabaseWithMetrics {
+    fn engine_stats(&self) -> EngineStat {
+        let stats = self.db.get_statistics();
+        let ticker = |name: &str| stats.get(name).map_or(0, |value| value.count);
+        let tickers = EngineStat {
+            compact_read_bytes: ticker("compact.read.bytes"),
+            compact_write_bytes: ticker("compact.write.bytes"),
+            flush_write_bytes: ticker("flush.write.bytes"),
+            wal_bytes: ticker("wal.bytes"),
+            bytes_written: ticker("bytes.written"),
+            block_cache_hits: ticker("block.cache.hit"),
+            block_cache_misses: ticker("block.cache.miss"),
+            bloom_filter_useful: ticker("bloom.filter.useful"),
+            stall_micros: ticker("stall.micros"),
+        };
+
+        let last = &mut *self.last_tickers.lock().unwrap();
+        let since = |now: u64, before: u64| now.saturating_sub(before);
+        let stat = EngineStat {
+            compact_read_bytes: since(tickers.compact_read_bytes, last.compact_read_bytes),
+            compact_write_bytes: since(tickers.compact_write_bytes, last.compact_write_bytes),
+            flush_write_bytes: since(tickers.flush_write_bytes, last.flush_write_bytes),
+            wal_bytes: since(tickers.wal_bytes, last.wal_bytes),
+            bytes_written: since(tickers.bytes_written, last.bytes_written),
+            block_cache_hits: since(tickers.block_cache_hits, last.block_cache_hits),
+            block_cache_misses: since(tickers.block_cache_misses, last.block_cache_misses),
+            bloom_filter_useful: since(tickers.bloom_filter_useful, last.bloom_filter_useful),
+            stall_micros: since(tickers.stall_micros, last.stall_micros),
+        };
+        *last = tickers;
+        stat
     }
 }
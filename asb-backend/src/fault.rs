@@ -0,0 +1,268 @@
+use std::io;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+
+use crate::overlay::{KeyValuePair, Overlay};
+
+/// The crash injected by `FaultInjectionDB`. The operations and the flushes are counted from 1
+/// since the database is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Crashes when the `n`-th operation is buffered. It and all the operations buffered since the
+    /// last flush are lost.
+    AtOp(u64),
+    /// Crashes at the `n`-th flush, before any buffered operation is written.
+    AtFlush(u64),
+    /// Crashes at the `flush`-th flush, after only the first `ops` buffered operations are written.
+    TruncateFlush { flush: u64, ops: usize },
+}
+
+struct State {
+    fault: Option<Fault>,
+    // The buffered operations in order, as a crash may write only the first ones.
+    buffer: Vec<DBOp>,
+    ops: u64,
+    flushes: u64,
+    // The operations buffered before the last finished flush.
+    durable_ops: u64,
+    crashed: bool,
+}
+
+/// A wrapper buffering the writes until flush, which can simulate a crash of the process. After
+/// the crash, all writes are silently dropped, so the inner database keeps what was durable at the
+/// crash and can be reopened for recovery.
+pub struct FaultInjectionDB {
+    inner: Arc<dyn KeyValueDB>,
+    state: Mutex<State>,
+    // The buffered writes by key, which serve the reads until flushed.
+    overlay: Overlay,
+}
+
+impl FaultInjectionDB {
+    pub fn new(inner: Arc<dyn KeyValueDB>, num_cols: u32, fault: Option<Fault>) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(State {
+                fault,
+                buffer: Vec::new(),
+                ops: 0,
+                flushes: 0,
+                durable_ops: 0,
+                crashed: false,
+            }),
+            overlay: Overlay::new(num_cols),
+        }
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.state.lock().unwrap().crashed
+    }
+
+    /// The number of buffered operations so far.
+    pub fn ops(&self) -> u64 {
+        self.state.lock().unwrap().ops
+    }
+
+    /// The number of flushes so far.
+    pub fn flushes(&self) -> u64 {
+        self.state.lock().unwrap().flushes
+    }
+
+    /// The number of operations buffered before the last flush which finished, so they are all
+    /// in the inner database.
+    pub fn durable_ops(&self) -> u64 {
+        self.state.lock().unwrap().durable_ops
+    }
+
+    // At most `limit` items of the inner database with the prefix from the bound. The inner
+    // database iterates from the prefix only, so the items before the bound are skipped.
+    fn inner_items(
+        &self,
+        col: u32,
+        prefix: &[u8],
+        from: Bound<&[u8]>,
+        limit: usize,
+    ) -> io::Result<Vec<KeyValuePair>> {
+        let after = |key: &[u8]| match from {
+            Bound::Included(from) => key >= from,
+            Bound::Excluded(from) => key > from,
+            Bound::Unbounded => true,
+        };
+        let items = self.inner.iter_from_prefix(col, prefix);
+        Ok(items.filter(|(key, _)| after(&key[..])).take(limit).collect())
+    }
+}
+
+impl KeyValueDB for FaultInjectionDB {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        if let Some(value) = self.overlay.get(col, key) {
+            return Ok(value);
+        }
+        self.inner.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.iter_from_prefix(col, prefix).next().map(|(_, v)| v)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        let state = &mut *self.state.lock().unwrap();
+        if state.crashed {
+            return;
+        }
+        for _ in &transaction.ops {
+            state.ops += 1;
+            if state.fault == Some(Fault::AtOp(state.ops)) {
+                state.buffer.clear();
+                self.overlay.clear();
+                state.crashed = true;
+                return;
+            }
+        }
+        state.buffer.extend(transaction.ops.iter().cloned());
+        self.overlay.write(transaction);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let state = &mut *self.state.lock().unwrap();
+        if state.crashed {
+            return Ok(());
+        }
+        state.flushes += 1;
+        let mut ops = std::mem::take(&mut state.buffer);
+        match state.fault {
+            Some(Fault::AtFlush(n)) if n == state.flushes => {
+                self.overlay.clear();
+                state.crashed = true;
+                return Ok(());
+            }
+            Some(Fault::TruncateFlush { flush, ops: kept }) if flush == state.flushes => {
+                ops.truncate(kept);
+                state.crashed = true;
+            }
+            _ => {}
+        }
+        self.overlay.flush(|_| self.inner.write(DBTransaction { ops }))?;
+        if !state.crashed {
+            state.durable_ops = state.ops;
+        }
+        Ok(())
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.iter_from_prefix(col, &[])
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.overlay.iter_from_prefix(col, prefix, move |from, limit| {
+            self.inner_items(col, prefix, from, limit)
+        })
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        let state = &mut *self.state.lock().unwrap();
+        state.buffer.clear();
+        self.overlay.restore(|| self.inner.restore(new_db))
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.inner.io_stats(kind)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for FaultInjectionDB {
+    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        parity_util_mem::MallocSizeOf::size_of(&*self.inner, ops) + self.overlay.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(fault: Option<Fault>) -> (Arc<dyn KeyValueDB>, FaultInjectionDB) {
+        let inner: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(1));
+        let db = FaultInjectionDB::new(inner.clone(), 1, fault);
+        (inner, db)
+    }
+
+    fn put(db: &FaultInjectionDB, keys: &[&[u8]]) {
+        let mut transaction = db.transaction();
+        for key in keys {
+            transaction.put(0, key, b"value");
+        }
+        db.write_buffered(transaction);
+    }
+
+    fn keys(db: &dyn KeyValueDB) -> Vec<Vec<u8>> {
+        db.iter(0).map(|(key, _)| key.to_vec()).collect()
+    }
+
+    #[test]
+    fn iteration_merges_buffered_writes() {
+        let (inner, db) = open(None);
+        put(&db, &[b"a1", b"b1", b"a3"]);
+        db.flush().unwrap();
+
+        put(&db, &[b"a2"]);
+        let mut transaction = db.transaction();
+        transaction.delete(0, b"a1");
+        db.write_buffered(transaction);
+
+        assert_eq!(keys(&db), vec![b"a2".to_vec(), b"a3".to_vec(), b"b1".to_vec()]);
+        let prefixed: Vec<_> = db.iter_from_prefix(0, b"a").map(|(key, _)| key.to_vec()).collect();
+        assert_eq!(prefixed, vec![b"a2".to_vec(), b"a3".to_vec()]);
+        assert!(db.get_by_prefix(0, b"a").is_some());
+        assert_eq!(keys(&*inner).len(), 3);
+    }
+
+    #[test]
+    fn crash_at_op() {
+        let (inner, db) = open(Some(Fault::AtOp(4)));
+        put(&db, &[b"a", b"b"]);
+        db.flush().unwrap();
+        // The 4th operation crashes, losing the 3rd one buffered with it.
+        put(&db, &[b"c", b"d"]);
+        assert!(db.crashed());
+        assert_eq!(db.get(0, b"c").unwrap(), None);
+
+        put(&db, &[b"e"]);
+        db.flush().unwrap();
+        assert_eq!(keys(&*inner), vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!((db.ops(), db.flushes(), db.durable_ops()), (4, 1, 2));
+    }
+
+    #[test]
+    fn crash_at_flush() {
+        let (inner, db) = open(Some(Fault::AtFlush(2)));
+        put(&db, &[b"a"]);
+        db.flush().unwrap();
+        put(&db, &[b"b", b"c"]);
+        db.flush().unwrap();
+        assert!(db.crashed());
+
+        assert_eq!(keys(&*inner), vec![b"a".to_vec()]);
+        assert_eq!(keys(&db), vec![b"a".to_vec()]);
+        assert_eq!((db.ops(), db.flushes(), db.durable_ops()), (3, 2, 1));
+    }
+
+    #[test]
+    fn crash_amid_flush() {
+        let (inner, db) = open(Some(Fault::TruncateFlush { flush: 2, ops: 1 }));
+        put(&db, &[b"a"]);
+        db.flush().unwrap();
+        put(&db, &[b"c", b"b"]);
+        db.flush().unwrap();
+        assert!(db.crashed());
+
+        // Only the first buffered operation of the torn flush is written.
+        assert_eq!(keys(&*inner), vec![b"a".to_vec(), b"c".to_vec()]);
+        assert_eq!((db.ops(), db.flushes(), db.durable_ops()), (3, 2, 1));
+    }
+}
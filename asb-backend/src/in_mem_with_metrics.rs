@@ -1,5 +1,3 @@
-#![allow(unused)]
-
 // The original journaldb relies on some metric run in crate `ethcore-db`. But it doesn't rely on the other dependencies.
 
 // Copyright 2015-2020 Parity Technologies (UK) Ltd.
@@ -18,31 +16,157 @@
 // You should have received a copy of the GNU General Public License
 // along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
 
-use kvdb::KeyValueDB;
-use std::ops::Deref;
-
-/// InMemory with disabled statistics
-pub struct InMemoryWithMetrics {
-    db: kvdb_memorydb::InMemory,
-}
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
 
-impl Deref for InMemoryWithMetrics {
-    type Target = kvdb_memorydb::InMemory;
+use asb_profile::{ColumnStat, ColumnStats};
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
 
-    fn deref(&self) -> &Self::Target {
-        &self.db
-    }
-}
+use crate::io_stats::ColumnTallies;
+use crate::overlay::KeyValuePair;
 
-impl stats::PrometheusMetrics for InMemoryWithMetrics {
-    fn prometheus_metrics(&self, _: &mut stats::PrometheusRegistry) {}
+/// An in-memory database with the statistics and the size of each column. It keeps the columns
+/// itself rather than in `kvdb_memorydb`, so the replaced values are known to size the columns.
+pub struct InMemoryWithMetrics {
+    columns: RwLock<Vec<BTreeMap<Vec<u8>, DBValue>>>,
+    // The bytes of the keys and values in each column, updated by the writes.
+    sizes: Vec<AtomicU64>,
+    tallies: ColumnTallies,
 }
 
 impl InMemoryWithMetrics {
     /// Create new instance
     pub fn create(num_cols: u32) -> Self {
         Self {
-            db: kvdb_memorydb::create(num_cols),
+            columns: RwLock::new((0..num_cols).map(|_| BTreeMap::new()).collect()),
+            sizes: (0..num_cols).map(|_| AtomicU64::new(0)).collect(),
+            tallies: ColumnTallies::new(num_cols),
         }
     }
+
+    // The items of the column with the prefix, read at once like `kvdb_memorydb`.
+    fn items(&self, col: u32, prefix: &[u8]) -> Vec<KeyValuePair> {
+        let columns = self.columns.read().unwrap();
+        columns[col as usize]
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key[..].into(), value[..].into()))
+            .collect()
+    }
+}
+
+impl KeyValueDB for InMemoryWithMetrics {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let value = self.columns.read().unwrap()[col as usize].get(key).cloned();
+        self.tallies.tally_read(col, value.as_ref().map_or(0, |x| x.len()));
+        Ok(value)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        let columns = self.columns.read().unwrap();
+        let value: Option<Box<[u8]>> = columns[col as usize]
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .next()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(_, value)| value[..].into());
+        self.tallies.tally_read(col, value.as_ref().map_or(0, |x| x.len()));
+        value
+    }
+
+    // The writes are applied at once, so there is nothing to flush.
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.tallies.tally_writes(&transaction);
+        let columns = &mut *self.columns.write().unwrap();
+        for op in transaction.ops {
+            let col = op.col() as usize;
+            let (added, removed) = match op {
+                DBOp::Insert { key, value, .. } => {
+                    let added = (key.len() + value.len()) as u64;
+                    let replaced = columns[col].insert(key.to_vec(), value);
+                    (added, replaced.map_or(0, |old| (key.len() + old.len()) as u64))
+                }
+                DBOp::Delete { key, .. } => {
+                    let deleted = columns[col].remove(&key[..]);
+                    (0, deleted.map_or(0, |old| (key.len() + old.len()) as u64))
+                }
+            };
+            self.sizes[col].fetch_add(added, Ordering::Relaxed);
+            self.sizes[col].fetch_sub(removed, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        Box::new(self.items(col, &[]).into_iter())
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        Box::new(self.items(col, prefix).into_iter())
+    }
+
+    fn restore(&self, _new_db: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Attempted to restore in-memory database",
+        ))
+    }
+
+    fn io_stats(&self, _kind: IoStatsKind) -> IoStats {
+        IoStats::empty()
+    }
+}
+
+impl ColumnStats for InMemoryWithMetrics {
+    fn column_stats(&self) -> Vec<ColumnStat> {
+        self.tallies
+            .column_stats(|col| Some(self.sizes[col as usize].load(Ordering::Relaxed)))
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for InMemoryWithMetrics {
+    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        parity_util_mem::MallocSizeOf::size_of(&*self.columns.read().unwrap(), ops)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_size(db: &InMemoryWithMetrics) -> Vec<Option<u64>> {
+        db.column_stats().iter().map(|stat| stat.disk_size).collect()
+    }
+
+    #[test]
+    fn column_sizes_follow_writes() {
+        let db = InMemoryWithMetrics::create(2);
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        transaction.put(0, b"other", b"value");
+        transaction.put(1, b"key", b"v");
+        db.write(transaction).unwrap();
+        assert_eq!(disk_size(&db), vec![Some(18), Some(4)]);
+
+        // A replaced value is subtracted, and so is a deleted item.
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"longer value");
+        transaction.delete(0, b"other");
+        transaction.delete(1, b"missing");
+        db.write(transaction).unwrap();
+        assert_eq!(disk_size(&db), vec![Some(15), Some(4)]);
+
+        let keys: Vec<_> = db.iter(0).map(|(key, _)| key.to_vec()).collect();
+        assert_eq!(keys, vec![b"key".to_vec()]);
+        assert_eq!(db.get_by_prefix(1, b"k").as_deref(), Some(&b"v"[..]));
+    }
 }
@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use asb_profile::ColumnStat;
+use kvdb::{DBOp, DBTransaction};
+
+pub(crate) use stats::io_stats::RunningStats;
+
+#[derive(Default)]
+struct ColumnTally {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+/// The I/O statistics of each column since the previous query. The counters are updated without
+/// locks, so a query racing with the I/O may split an operation between two samples.
+pub(crate) struct ColumnTallies {
+    columns: Vec<ColumnTally>,
+}
+
+impl ColumnTallies {
+    pub fn new(num_cols: u32) -> Self {
+        Self {
+            columns: (0..num_cols).map(|_| ColumnTally::default()).collect(),
+        }
+    }
+
+    pub fn tally_read(&self, col: u32, bytes: usize) {
+        let tally = &self.columns[col as usize];
+        tally.reads.fetch_add(1, Ordering::Relaxed);
+        tally.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn tally_writes(&self, transaction: &DBTransaction) {
+        for op in &transaction.ops {
+            let mut bytes = op.key().len() as u64;
+            if let DBOp::Insert { value, .. } = op {
+                bytes += value.len() as u64;
+            }
+            let tally = &self.columns[op.col() as usize];
+            tally.writes.fetch_add(1, Ordering::Relaxed);
+            tally.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    pub fn column_stats(&self, disk_size: impl Fn(u32) -> Option<u64>) -> Vec<ColumnStat> {
+        let take = |counter: &AtomicU64| counter.swap(0, Ordering::Relaxed);
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(col, tally)| ColumnStat {
+                reads: take(&tally.reads),
+                writes: take(&tally.writes),
+                bytes_read: take(&tally.bytes_read),
+                bytes_written: take(&tally.bytes_written),
+                disk_size: disk_size(col as u32),
+            })
+            .collect()
+    }
+}
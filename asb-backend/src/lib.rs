@@ -1,39 +1,127 @@
 use asb_options::{Backend, Options};
+use asb_profile::{CacheStats, ColumnStats, EngineStats, ModeledTime};
 use kvdb::KeyValueDB;
 use std::sync::Arc;
 
-#[cfg(not(feature = "parity-backend"))]
+mod cache;
+#[cfg(feature = "rocksdb")]
 mod cfx_kvdb_rocksdb;
-
-#[cfg(not(feature = "parity-backend"))]
+#[cfg(feature = "rocksdb")]
 mod db_with_mertics;
+mod fault;
+mod in_mem_with_metrics;
+mod io_stats;
+mod mdbx;
+mod overlay;
+mod parity_rocksdb;
+mod redb_kvdb;
+#[cfg(feature = "rocksdb")]
+mod rocksdb_profile;
+mod simulated;
+mod trace;
 
-#[cfg(feature = "lmpts-backend")]
+#[cfg(feature = "rocksdb")]
 pub extern crate cfx_storage;
 
-#[cfg(all(feature = "parity-backend", feature = "lmpts-backend"))]
-compile_error!("Multiple backends are chosen!");
+pub use cache::CachedDB;
+pub use fault::{Fault, FaultInjectionDB};
+pub use trace::{replay, ReplaySummary, TraceRecorder};
 
-mod in_mem_with_metrics;
-mod mdbx;
+#[cfg(feature = "rocksdb")]
+use db_with_mertics::DatabaseWithMetrics;
+use in_mem_with_metrics::InMemoryWithMetrics;
+
+/// The instruments of the backend for the reporter.
+#[derive(Default)]
+pub struct Instruments {
+    /// The clock of the modeled I/O time if the backend is simulated.
+    pub clock: Option<Arc<dyn ModeledTime>>,
+    /// The statistics of each column if the backend tracks them.
+    pub columns: Option<Arc<dyn ColumnStats>>,
+    /// The internal statistics of the storage engine if the backend exports them.
+    pub engine: Option<Arc<dyn EngineStats>>,
+    /// The statistics of the value cache if it is enabled.
+    pub cache: Option<Arc<dyn CacheStats>>,
+}
 
-#[cfg(feature = "parity-backend")]
-mod parity_kvdb_rocksdb;
+/// Opens the backend, together with its instruments.
+pub fn backend(opts: &Options) -> (Arc<dyn KeyValueDB>, Instruments) {
+    let (mut db, mut instruments) = open_backend(opts);
+    if let Some(size) = opts.value_cache {
+        let cached = Arc::new(CachedDB::new(db, (size << 20) as usize, opts.value_cache_policy));
+        instruments.cache = Some(cached.clone());
+        db = cached;
+    }
+    // The trace is recorded above the cache, so it can be replayed with any cache.
+    if let Some(ref path) = opts.record_trace {
+        let recorder = TraceRecorder::create(db, path, opts.num_cols())
+            .unwrap_or_else(|e| panic!("Cannot create the trace file {}: {}", path, e));
+        return (Arc::new(recorder), instruments);
+    }
+    (db, instruments)
+}
 
-pub fn backend(opts: &Options) -> Arc<dyn KeyValueDB> {
+fn open_backend(opts: &Options) -> (Arc<dyn KeyValueDB>, Instruments) {
     match opts.backend {
+        #[cfg(feature = "rocksdb")]
         Backend::RocksDB => {
-            let db_dir = opts.db_dir.as_str();
-            #[cfg(not(feature = "parity-backend"))]
-            {
-                cfx_kvdb_rocksdb::open(db_dir, opts)
-            }
-            #[cfg(feature = "parity-backend")]
-            {
-                parity_kvdb_rocksdb::open(db_dir, opts.num_cols())
+            let db = cfx_kvdb_rocksdb::open(&opts.db_dir, opts);
+            if opts.no_stat {
+                return (db, Instruments::default());
             }
+            let db = Arc::new(DatabaseWithMetrics::new(db, opts.num_cols()));
+            let instruments = Instruments {
+                clock: None,
+                columns: Some(db.clone()),
+                engine: Some(db.clone()),
+                cache: None,
+            };
+            (db, instruments)
+        }
+        #[cfg(not(feature = "rocksdb"))]
+        Backend::RocksDB => {
+            panic!("The rocksdb backend can only work with feature rocksdb!")
+        }
+        Backend::ParityRocksDB => (
+            Arc::new(parity_rocksdb::open(opts).unwrap_or_else(|e| panic!("{}", e))),
+            Instruments::default(),
+        ),
+        Backend::InMemoryDB if opts.no_stat => (
+            Arc::new(kvdb_memorydb::create(opts.num_cols())),
+            Instruments::default(),
+        ),
+        Backend::InMemoryDB => with_columns(InMemoryWithMetrics::create(opts.num_cols())),
+        Backend::MDBX if opts.no_stat => (
+            Arc::new(mdbx::open_database(opts).unwrap()),
+            Instruments::default(),
+        ),
+        Backend::MDBX => with_columns(mdbx::open_database(opts).unwrap()),
+        Backend::Redb => (
+            Arc::new(redb_kvdb::open_database(&opts.db_dir, opts.num_cols()).unwrap()),
+            Instruments::default(),
+        ),
+        Backend::Simulated => {
+            let db = simulated::open(opts);
+            let instruments = Instruments {
+                clock: Some(db.clock()),
+                columns: None,
+                engine: None,
+                cache: None,
+            };
+            (Arc::new(db), instruments)
         }
-        Backend::InMemoryDB => Arc::new(kvdb_memorydb::create(opts.num_cols())),
-        Backend::MDBX => Arc::new(mdbx::open_database(opts)),
     }
 }
+
+fn with_columns<DB: KeyValueDB + ColumnStats + 'static>(
+    db: DB,
+) -> (Arc<dyn KeyValueDB>, Instruments) {
+    let db = Arc::new(db);
+    let instruments = Instruments {
+        clock: None,
+        columns: Some(db.clone()),
+        engine: None,
+        cache: None,
+    };
+    (db, instruments)
+}
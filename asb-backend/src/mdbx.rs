@@ -1,33 +1,74 @@
-#![allow(unused)]
-
-use libmdbx::Cursor;
-use libmdbx::Database;
-use libmdbx::DatabaseFlags;
-use libmdbx::Environment;
-use libmdbx::EnvironmentBuilder;
-use libmdbx::Geometry;
-use libmdbx::Transaction;
-use libmdbx::WriteFlags;
-use libmdbx::WriteMap;
-use libmdbx::RW;
-
-use asb_options::Options;
-
-use kvdb::DBValue;
-use kvdb::KeyValueDB;
+use std::fs;
 use std::io;
 use std::io::ErrorKind::Other;
-use std::sync::Arc;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
-use std::sync::RwLockWriteGuard;
 
 use ouroboros::self_referencing;
-use std::path::Path;
 
-pub fn open_database(opts: &Options) -> MdbxDatabase {
+use libmdbx::{
+    Database, DatabaseFlags, Environment, EnvironmentFlags, Geometry, Mode, SyncMode, WriteFlags,
+    WriteMap,
+};
+
+use asb_options::{MdbxSync, Options};
+use asb_profile::{ColumnStat, ColumnStats};
+use kvdb::{DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+
+use crate::io_stats::{ColumnTallies, RunningStats};
+use crate::overlay::{KeyValuePair, Overlay};
+
+// MDBX keeps its data in this file under the database directory.
+const DATA_FILE: &str = "mdbx.dat";
+
+fn table_name(col: u32) -> String {
+    format!("table{}", col)
+}
+
+fn into_io_error(err: libmdbx::Error) -> io::Error {
+    io::Error::new(Other, err)
+}
+
+fn closed() -> io::Error {
+    io::Error::new(Other, "The MDBX environment failed to reopen in restore")
+}
+
+// The environment with the handles of its tables, which stay open as long as the environment.
+#[self_referencing]
+struct Env {
+    env: Environment<WriteMap>,
+    #[borrows(env)]
+    #[covariant]
+    tables: Vec<Database<'this>>,
+}
+
+// Creates the tables at first, and keeps their handles open for the later transactions.
+fn open_tables(
+    env: &Environment<WriteMap>,
+    num_cols: u32,
+) -> libmdbx::Result<Vec<Database<'_>>> {
+    let txn = env.begin_rw_txn()?;
+    for col in 0..num_cols {
+        let table = txn.create_db(Some(&table_name(col)), DatabaseFlags::empty())?;
+        txn.prime_for_permaopen(table);
+    }
+    let (_, mut tables) = txn.commit_and_rebind_open_dbs()?;
+    // The handles are numbered in the order of opening, which is the order of the columns.
+    tables.sort_by_key(Database::dbi);
+    Ok(tables)
+}
+
+fn open_environment(path: &Path, sync: MdbxSync, num_cols: u32) -> io::Result<Env> {
     const TB: usize = 1 << 40;
     const GB: usize = 1 << 30;
-    let mut builder: EnvironmentBuilder<WriteMap> = Environment::new();
+    let sync_mode = match sync {
+        MdbxSync::Durable => SyncMode::Durable,
+        MdbxSync::SafeNoSync => SyncMode::SafeNoSync,
+        MdbxSync::UtterlyNoSync => SyncMode::UtterlyNoSync,
+    };
+
+    let mut builder = Environment::new();
     builder.set_max_dbs(10);
     builder.set_geometry(Geometry {
         size: Some(0..4 * TB),
@@ -36,186 +77,347 @@ pub fn open_database(opts: &Options) -> MdbxDatabase {
         page_size: None,
     });
     builder.set_rp_augment_limit(16 * 256 * 1024);
-    let env = builder.open(Path::new(&opts.db_dir)).unwrap();
-    let txn = MdbxTransaction::build(Arc::new(env));
-    make_backend(opts, txn)
-}
-
-#[self_referencing]
-pub struct MdbxTransaction {
-    env: Arc<Environment<WriteMap>>,
-    #[borrows(env)]
-    #[covariant]
-    txn: Option<Transaction<'this, RW, WriteMap>>,
-}
-
-impl MdbxTransaction {
-    pub fn build(env: Arc<Environment<WriteMap>>) -> Self {
-        MdbxTransactionBuilder {
-            env,
-            txn_builder: |env| Some(env.begin_rw_txn().unwrap()),
-        }
-        .build()
-    }
+    builder.set_flags(EnvironmentFlags {
+        mode: Mode::ReadWrite { sync_mode },
+        ..Default::default()
+    });
+    let env = builder.open(path).map_err(into_io_error)?;
 
-    fn commit(&mut self) {
-        self.with_txn_mut(|txn| std::mem::take(txn).unwrap().commit());
-        let mut next_txn = MdbxTransaction::build(self.borrow_env().clone());
-        std::mem::swap(self, &mut next_txn);
+    EnvTryBuilder {
+        env,
+        tables_builder: |env| open_tables(env, num_cols),
     }
+    .try_build()
+    .map_err(into_io_error)
 }
 
+/// A key-value database on MDBX. The buffered writes are written in one MDBX write transaction on
+/// flush. The reads of the committed data go through read-only transactions on the latest
+/// snapshot.
 pub struct MdbxDatabase {
-    txn: RwLock<MdbxTransaction>,
-    buffer: RwLock<Vec<kvdb::DBTransaction>>,
+    // It is `None` while the data file is replaced in `restore`, or after both the new and the
+    // previous files failed to open there.
+    env: RwLock<Option<Env>>,
+    path: PathBuf,
+    sync: MdbxSync,
     num_cols: u32,
+    overlay: Overlay,
+    stats: RunningStats,
+    columns: Option<ColumnTallies>,
+}
+
+pub fn open_database(opts: &Options) -> io::Result<MdbxDatabase> {
+    let mut db = MdbxDatabase::open(&opts.db_dir, opts.mdbx_sync, opts.num_cols())?;
+    if opts.no_stat {
+        db.columns = None;
+    }
+    Ok(db)
 }
 
 impl MdbxDatabase {
-    fn cursor(&self, col: u32) -> Cursor<'_, RW> {
-        let table_name = format!("table{}", col);
-        self.txn.write().unwrap().with_txn_mut(|txn| {
-            let txn = txn.as_mut().unwrap();
-            let db = txn
-                .create_db(Some(&table_name), DatabaseFlags::empty())
-                .unwrap();
-            txn.cursor(&db).unwrap()
+    pub fn open(db_dir: &str, sync: MdbxSync, num_cols: u32) -> io::Result<Self> {
+        let path = PathBuf::from(db_dir);
+        Ok(Self {
+            env: RwLock::new(Some(open_environment(&path, sync, num_cols)?)),
+            path,
+            sync,
+            num_cols,
+            overlay: Overlay::new(num_cols),
+            stats: RunningStats::new(),
+            columns: Some(ColumnTallies::new(num_cols)),
         })
     }
-}
 
-pub fn make_backend(opts: &Options, db: MdbxTransaction) -> MdbxDatabase {
-    let num_cols = opts.num_cols();
-    MdbxDatabase {
-        txn: RwLock::new(db),
-        buffer: RwLock::new(Vec::new()),
-        num_cols,
+    fn committed_get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let env = self.env.read().unwrap();
+        let env = env.as_ref().ok_or_else(closed)?;
+        let txn = env.borrow_env().begin_ro_txn().map_err(into_io_error)?;
+        let table = &env.borrow_tables()[col as usize];
+        txn.get::<Vec<u8>>(table, key).map_err(into_io_error)
     }
-}
 
-fn into_io_error(err: libmdbx::Error) -> io::Error {
-    io::Error::new(Other, err)
-}
+    // At most `limit` committed items in the column from the bound, in the order of keys.
+    fn committed_items(
+        &self,
+        col: u32,
+        from: Bound<&[u8]>,
+        limit: usize,
+    ) -> io::Result<Vec<KeyValuePair>> {
+        let env = self.env.read().unwrap();
+        let env = env.as_ref().ok_or_else(closed)?;
+        let txn = env.borrow_env().begin_ro_txn().map_err(into_io_error)?;
+        let table = &env.borrow_tables()[col as usize];
+        let mut cursor = txn.cursor(table).map_err(into_io_error)?;
+
+        let mut item = match from {
+            Bound::Included(from) | Bound::Excluded(from) => {
+                cursor.set_range::<Vec<u8>, Vec<u8>>(from).map_err(into_io_error)?
+            }
+            Bound::Unbounded => cursor.first::<Vec<u8>, Vec<u8>>().map_err(into_io_error)?,
+        };
+        if let (Bound::Excluded(from), Some((key, _))) = (from, &item) {
+            if &key[..] == from {
+                item = cursor.next::<Vec<u8>, Vec<u8>>().map_err(into_io_error)?;
+            }
+        }
+
+        let mut items = Vec::new();
+        while let Some((key, value)) = item {
+            items.push((key.into_boxed_slice(), value.into_boxed_slice()));
+            if items.len() == limit {
+                break;
+            }
+            item = cursor.next::<Vec<u8>, Vec<u8>>().map_err(into_io_error)?;
+        }
+        Ok(items)
+    }
 
-fn map_output<T, F>(
-    output: libmdbx::Result<Option<(Vec<u8>, Vec<u8>)>>,
-    f: F,
-) -> io::Result<Option<T>>
-where
-    F: FnOnce((Vec<u8>, Vec<u8>)) -> T,
-{
-    match output {
-        Ok(out) => Ok(out.map(f)),
-        Err(err) => Err(into_io_error(err)),
+    // The bytes of the pages of the table.
+    fn table_size(&self, col: u32) -> Option<u64> {
+        let env = self.env.read().unwrap();
+        let env = env.as_ref()?;
+        let txn = env.borrow_env().begin_ro_txn().ok()?;
+        let stat = txn.db_stat(&env.borrow_tables()[col as usize]).ok()?;
+        let pages = stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages();
+        Some(pages as u64 * stat.page_size() as u64)
     }
-}
 
-fn is_prefix(prefix: &[u8], full: &[u8]) -> bool {
-    if prefix.len() > full.len() {
-        return false;
+    fn tally_column_read(&self, col: u32, bytes: usize) {
+        if let Some(columns) = &self.columns {
+            columns.tally_read(col, bytes);
+        }
     }
-    full.starts_with(prefix)
 }
 
 impl KeyValueDB for MdbxDatabase {
     fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
-        map_output(self.cursor(col).set_key(key), |(_, v)| v)
+        if let Some(value) = self.overlay.get(col, key) {
+            let bytes = value.as_ref().map_or(0, |v| v.len());
+            self.stats.tally(|t| {
+                t.reads += 1;
+                t.cache_reads += 1;
+                t.cache_read_bytes += bytes as u64;
+            });
+            self.tally_column_read(col, bytes);
+            return Ok(value);
+        }
+
+        let value = self.committed_get(col, key)?;
+        let bytes = value.as_ref().map_or(0, |v| v.len());
+        self.stats.tally(|t| {
+            t.reads += 1;
+            t.bytes_read += bytes as u64;
+        });
+        self.tally_column_read(col, bytes);
+        Ok(value)
     }
 
     fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
-        let value = map_output(self.cursor(col).set_range(prefix), |(k, v)| {
-            is_prefix(prefix, &k).then(|| v.into_boxed_slice())
-        })
-        .unwrap();
-        value.and_then(|x| x)
-    }
-
-    fn write_buffered(&self, transaction: kvdb::DBTransaction) {
-        self.buffer.write().unwrap().push(transaction);
-    }
-
-    fn flush(&self) -> std::io::Result<()> {
-        let mut cursors: Vec<_> = (0..self.num_cols).map(|db| self.cursor(db)).collect();
-        for kvdb_txn in self.buffer.write().unwrap().drain(..) {
-            for kvdb_op in kvdb_txn.ops {
-                match kvdb_op {
-                    kvdb::DBOp::Insert { col, key, value } => cursors[col as usize]
-                        .put(key.as_ref(), value.as_ref(), WriteFlags::UPSERT)
-                        .map_err(into_io_error)
-                        .unwrap(),
-                    kvdb::DBOp::Delete { col, key } => {
-                        let mut cursor = &mut cursors[col as usize];
-                        if cursor
-                            .set_key::<(), ()>(&key)
-                            .map_err(into_io_error)
-                            .unwrap()
-                            .is_some()
-                        {
-                            cursor
-                                .del(WriteFlags::CURRENT)
-                                .map_err(into_io_error)
-                                .unwrap();
-                        }
+        self.iter_from_prefix(col, prefix).next().map(|(_, v)| v)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        if let Some(columns) = &self.columns {
+            columns.tally_writes(&transaction);
+        }
+        self.overlay.write(transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.overlay.flush(|changes| {
+            let (mut writes, mut bytes_written) = (0, 0);
+            let env = self.env.read().unwrap();
+            let env = env.as_ref().ok_or_else(closed)?;
+            let txn = env.borrow_env().begin_rw_txn().map_err(into_io_error)?;
+            for (table, changes) in env.borrow_tables().iter().zip(changes) {
+                for (key, value) in changes {
+                    writes += 1;
+                    bytes_written += key.len();
+                    if let Some(value) = value {
+                        bytes_written += value.len();
+                        txn.put(table, key, value, WriteFlags::UPSERT).map_err(into_io_error)?;
+                    } else {
+                        txn.del(table, key, None).map_err(into_io_error)?;
                     }
                 }
             }
-        }
-        self.txn.write().unwrap().commit();
-        // self.txn.read().unwrap().borrow_env().sync(true).unwrap();
-        // println!("Commit");
-        Ok(())
-    }
-
-    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
-        let mut cursor = self.cursor(col);
-        cursor.first::<(), ()>().unwrap();
-        return Box::new(MdbxCursorIterator {
-            cursor,
-            prefix: vec![],
-        });
+            txn.commit().map_err(into_io_error)?;
+
+            self.stats.tally(|t| {
+                t.transactions += 1;
+                t.writes += writes;
+                t.bytes_written += bytes_written as u64;
+            });
+            Ok(())
+        })
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.iter_from_prefix(col, &[])
     }
 
     fn iter_from_prefix<'a>(
         &'a self,
         col: u32,
         prefix: &'a [u8],
-    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
-        let mut cursor = self.cursor(col);
-        cursor.set_key::<(), ()>(prefix).unwrap();
-        return Box::new(MdbxCursorIterator {
-            cursor,
-            prefix: prefix.to_vec(),
-        });
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.overlay.iter_from_prefix(col, prefix, move |from, limit| {
+            self.committed_items(col, from, limit)
+        })
     }
 
-    fn restore(&self, new_db: &str) -> std::io::Result<()> {
-        unimplemented!()
+    // Replaces the data with a copy of the MDBX environment in `new_db`.
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.overlay.restore(|| {
+            // Copy the file beside the data file at first, so a failed copy leaves the data intact.
+            let data = self.path.join(DATA_FILE);
+            let staged = data.with_extension("restore");
+            if let Err(err) = fs::copy(Path::new(new_db).join(DATA_FILE), &staged) {
+                let _ = fs::remove_file(&staged);
+                return Err(err);
+            }
+
+            let env = &mut *self.env.write().unwrap();
+            // Close the environment before replacing its data file, and keep the previous file
+            // until the copy opens. The lock file is reset on open.
+            *env = None;
+            let backup = data.with_extension("backup");
+            let mut result = fs::rename(&data, &backup);
+            if result.is_ok() {
+                result = fs::rename(&staged, &data);
+                if result.is_ok() {
+                    match open_environment(&self.path, self.sync, self.num_cols) {
+                        Ok(opened) => {
+                            *env = Some(opened);
+                            let _ = fs::remove_file(&backup);
+                            return Ok(());
+                        }
+                        Err(err) => result = Err(err),
+                    }
+                }
+                let _ = fs::rename(&backup, &data);
+            }
+            let _ = fs::remove_file(&staged);
+
+            // Reopen the previous environment. If it fails too, the environment is left closed
+            // and the later calls return errors.
+            *env = Some(open_environment(&self.path, self.sync, self.num_cols)?);
+            result
+        })
     }
-}
 
-pub struct MdbxCursorIterator<'txn> {
-    cursor: Cursor<'txn, RW>,
-    prefix: Vec<u8>,
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.stats.io_stats(kind)
+    }
 }
 
-impl<'txn> Iterator for MdbxCursorIterator<'txn> {
-    type Item = (Box<[u8]>, Box<[u8]>);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let (k, v): Self::Item = map_output(self.cursor.get_current(), |(k, v)| {
-            (k.into_boxed_slice(), v.into_boxed_slice())
-        })
-        .unwrap()?;
-        if !is_prefix(&self.prefix, &v) {
-            return None;
+impl ColumnStats for MdbxDatabase {
+    fn column_stats(&self) -> Vec<ColumnStat> {
+        match &self.columns {
+            Some(columns) => columns.column_stats(|col| self.table_size(col)),
+            None => Vec::new(),
         }
-        self.cursor.next::<(), ()>().unwrap();
-        Some((k, v))
     }
 }
 
 impl parity_util_mem::MallocSizeOf for MdbxDatabase {
-    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
-        0
+    // The pages are mapped from the data file, so only the overlay is counted.
+    fn size_of(&self, _ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        self.overlay.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn iter_from_prefix_matches_keys() {
+        let tempdir = TempDir::new("").unwrap();
+        let db =
+            MdbxDatabase::open(tempdir.path().to_str().unwrap(), MdbxSync::Durable, 1).unwrap();
+
+        // The prefix is checked against the keys rather than the values.
+        let mut transaction = db.transaction();
+        transaction.put(0, b"ab", b"zz");
+        transaction.put(0, b"ac", b"zz");
+        transaction.put(0, b"ba", b"ab");
+        db.write(transaction).unwrap();
+
+        let keys: Vec<_> = db.iter_from_prefix(0, b"a").map(|(key, _)| key.to_vec()).collect();
+        assert_eq!(keys, vec![b"ab".to_vec(), b"ac".to_vec()]);
+        assert_eq!(&*db.get_by_prefix(0, b"b").unwrap(), b"ab");
+        assert_eq!(db.iter(0).count(), 3);
+    }
+
+    #[test]
+    fn failed_restore_keeps_data() {
+        let tempdir = TempDir::new("").unwrap();
+        let db =
+            MdbxDatabase::open(tempdir.path().to_str().unwrap(), MdbxSync::Durable, 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+
+        // There is no data file to copy from an empty directory.
+        let empty = TempDir::new("").unwrap();
+        assert!(db.restore(empty.path().to_str().unwrap()).is_err());
+        assert_eq!(db.get(0, b"key").unwrap().unwrap(), b"value");
+
+        let mut transaction = db.transaction();
+        transaction.put(0, b"other", b"value");
+        db.write(transaction).unwrap();
+        assert_eq!(db.iter(0).count(), 2);
+    }
+
+    #[test]
+    fn restore_replaces_data() {
+        let source = TempDir::new("").unwrap();
+        {
+            let db = MdbxDatabase::open(source.path().to_str().unwrap(), MdbxSync::Durable, 1)
+                .unwrap();
+            let mut transaction = db.transaction();
+            transaction.put(0, b"restored", b"value");
+            db.write(transaction).unwrap();
+        }
+
+        let tempdir = TempDir::new("").unwrap();
+        let db =
+            MdbxDatabase::open(tempdir.path().to_str().unwrap(), MdbxSync::Durable, 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+        // The buffered writes are dropped with the previous data.
+        let mut transaction = db.transaction();
+        transaction.put(0, b"buffered", b"value");
+        db.write_buffered(transaction);
+
+        db.restore(source.path().to_str().unwrap()).unwrap();
+        assert_eq!(db.get(0, b"restored").unwrap().unwrap(), b"value");
+        assert_eq!(db.get(0, b"key").unwrap(), None);
+        assert_eq!(db.get(0, b"buffered").unwrap(), None);
+
+        let mut transaction = db.transaction();
+        transaction.put(0, b"other", b"value");
+        db.write(transaction).unwrap();
+        assert_eq!(db.iter(0).count(), 2);
+    }
+
+    #[test]
+    fn unopenable_restore_keeps_data() {
+        let tempdir = TempDir::new("").unwrap();
+        let db =
+            MdbxDatabase::open(tempdir.path().to_str().unwrap(), MdbxSync::Durable, 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+
+        // The copy is not an MDBX data file, so the previous file is opened again.
+        let garbage = TempDir::new("").unwrap();
+        fs::write(garbage.path().join(DATA_FILE), vec![0xab; 1 << 16]).unwrap();
+        assert!(db.restore(garbage.path().to_str().unwrap()).is_err());
+        assert_eq!(db.get(0, b"key").unwrap().unwrap(), b"value");
+        assert!(!tempdir.path().join(DATA_FILE).with_extension("backup").exists());
     }
 }
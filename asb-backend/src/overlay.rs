@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+use std::collections::{btree_map, BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::iter::Peekable;
+use std::ops::Bound;
+use std::sync::RwLock;
+
+use kvdb::{DBOp, DBTransaction, DBValue};
+
+pub(crate) type KeyValuePair = (Box<[u8]>, Box<[u8]>);
+
+/// The buffered changes of each column. A `None` value is a deletion.
+pub(crate) type Changes = Vec<HashMap<Vec<u8>, Option<DBValue>>>;
+
+// The committed items read in one read transaction by an iterator.
+const BATCH: usize = 1024;
+
+/// The buffered writes of a backend which writes them in one transaction on flush. They serve the
+/// reads, including the prefix reads and the iterators, until they are flushed.
+pub(crate) struct Overlay {
+    changes: RwLock<Changes>,
+}
+
+impl Overlay {
+    pub fn new(num_cols: u32) -> Self {
+        Self {
+            changes: RwLock::new((0..num_cols).map(|_| HashMap::new()).collect()),
+        }
+    }
+
+    /// The buffered change of the key, if any.
+    pub fn get(&self, col: u32, key: &[u8]) -> Option<Option<DBValue>> {
+        self.changes.read().unwrap()[col as usize].get(key).cloned()
+    }
+
+    pub fn write(&self, transaction: DBTransaction) {
+        let changes = &mut *self.changes.write().unwrap();
+        for op in transaction.ops {
+            match op {
+                DBOp::Insert { col, key, value } => {
+                    changes[col as usize].insert(key.to_vec(), Some(value));
+                }
+                DBOp::Delete { col, key } => {
+                    changes[col as usize].insert(key.to_vec(), None);
+                }
+            }
+        }
+    }
+
+    /// Writes the buffered changes with `commit`, and drops them if it succeeds. The new writes
+    /// and the reads wait until it finishes, so they never miss the changes in between.
+    pub fn flush(&self, commit: impl FnOnce(&Changes) -> io::Result<()>) -> io::Result<()> {
+        let changes = &mut *self.changes.write().unwrap();
+        if changes.iter().all(HashMap::is_empty) {
+            return Ok(());
+        }
+        commit(changes)?;
+        for column in changes.iter_mut() {
+            column.clear();
+        }
+        Ok(())
+    }
+
+    /// Drops the buffered changes.
+    pub fn clear(&self) {
+        for column in self.changes.write().unwrap().iter_mut() {
+            column.clear();
+        }
+    }
+
+    /// Drops the buffered changes and replaces the committed data with `replace`, while the
+    /// others wait.
+    pub fn restore(&self, replace: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+        let changes = &mut *self.changes.write().unwrap();
+        for column in changes.iter_mut() {
+            column.clear();
+        }
+        replace()
+    }
+
+    /// The bytes of the buffered keys and values.
+    pub fn bytes(&self) -> usize {
+        let changes = self.changes.read().unwrap();
+        let entries = changes.iter().flat_map(|column| column.iter());
+        entries
+            .map(|(key, value)| key.len() + value.as_ref().map_or(0, |v| v.len()))
+            .sum()
+    }
+
+    /// The items with the prefix in the column, merging the buffered changes at this time into
+    /// the committed items. `read` returns at most the given number of committed items from the
+    /// bound in the order of keys, and is called in batches as the iterator goes.
+    pub fn iter_from_prefix<'a>(
+        &self,
+        col: u32,
+        prefix: &[u8],
+        read: impl FnMut(Bound<&[u8]>, usize) -> io::Result<Vec<KeyValuePair>> + 'a,
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        let changes: BTreeMap<_, _> = self.changes.read().unwrap()[col as usize]
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let committed = CommittedItems {
+            read,
+            prefix: prefix.to_vec(),
+            last: None,
+            batch: VecDeque::new(),
+            done: false,
+        };
+        Box::new(MergedItems {
+            committed: committed.peekable(),
+            changes: changes.into_iter().peekable(),
+        })
+    }
+}
+
+// The committed items with a prefix, read in batches with a read transaction each, so the
+// iterator neither loads the whole column nor holds a transaction.
+struct CommittedItems<F> {
+    read: F,
+    prefix: Vec<u8>,
+    last: Option<Box<[u8]>>,
+    batch: VecDeque<KeyValuePair>,
+    done: bool,
+}
+
+impl<F> Iterator for CommittedItems<F>
+where
+    F: FnMut(Bound<&[u8]>, usize) -> io::Result<Vec<KeyValuePair>>,
+{
+    type Item = KeyValuePair;
+
+    fn next(&mut self) -> Option<KeyValuePair> {
+        if self.batch.is_empty() && !self.done {
+            let from = match &self.last {
+                Some(last) => Bound::Excluded(&last[..]),
+                None => Bound::Included(&self.prefix[..]),
+            };
+            match (self.read)(from, BATCH) {
+                Ok(items) => {
+                    self.done = items.is_empty();
+                    self.batch = items.into();
+                }
+                // The iterators cannot return errors, so an error ends the iteration.
+                Err(e) => {
+                    eprintln!("Iteration stops on an I/O error: {}", e);
+                    self.done = true;
+                }
+            }
+        }
+
+        let item = self.batch.pop_front()?;
+        if !item.0.starts_with(&self.prefix) {
+            self.batch.clear();
+            self.done = true;
+            return None;
+        }
+        self.last = Some(item.0.clone());
+        Some(item)
+    }
+}
+
+struct MergedItems<I: Iterator<Item = KeyValuePair>> {
+    committed: Peekable<I>,
+    changes: Peekable<btree_map::IntoIter<Vec<u8>, Option<DBValue>>>,
+}
+
+impl<I: Iterator<Item = KeyValuePair>> Iterator for MergedItems<I> {
+    type Item = KeyValuePair;
+
+    fn next(&mut self) -> Option<KeyValuePair> {
+        loop {
+            let order = match (self.committed.peek(), self.changes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((key, _)), Some((change_key, _))) => key[..].cmp(&change_key[..]),
+            };
+            match order {
+                Ordering::Less => return self.committed.next(),
+                // The buffered change shadows the committed item.
+                Ordering::Equal => {
+                    self.committed.next();
+                }
+                Ordering::Greater => {}
+            }
+            if let (key, Some(value)) = self.changes.next().unwrap() {
+                return Some((key.into(), value.into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(pairs: &[(&str, &str)]) -> Vec<KeyValuePair> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.as_bytes().into(), value.as_bytes().into()))
+            .collect()
+    }
+
+    #[test]
+    fn merge_changes_into_committed_items() {
+        let committed = items(&[("a1", "1"), ("a2", "2"), ("a4", "4"), ("b1", "5")]);
+        let overlay = Overlay::new(1);
+        let mut transaction = DBTransaction::new();
+        transaction.put(0, b"a3", b"3");
+        transaction.put(0, b"a4", b"44");
+        transaction.delete(0, b"a2");
+        transaction.put(0, b"b2", b"6");
+        overlay.write(transaction);
+
+        // Read in batches of one item, to cover the bounds between batches.
+        let merged: Vec<_> = overlay
+            .iter_from_prefix(0, b"a", |from, _| {
+                let item = committed.iter().find(|(key, _)| match from {
+                    Bound::Included(from) => &key[..] >= from,
+                    Bound::Excluded(from) => &key[..] > from,
+                    Bound::Unbounded => true,
+                });
+                Ok(item.cloned().into_iter().collect())
+            })
+            .collect();
+        assert_eq!(merged, items(&[("a1", "1"), ("a3", "3"), ("a4", "44")]));
+    }
+}
@@ -0,0 +1,268 @@
+//! The RocksDB backend on Parity's binding (`kvdb-rocksdb` 0.9).
+//!
+//! Parity's binding links another version of the RocksDB library than the Conflux crates, and a
+//! C++ library can be linked only once in a build. So the binding runs in its own process, the
+//! `parity-rocksdb-node` built from `asb-backend/parity-rocksdb`, which is spawned on the database
+//! directory and serves the requests over its standard input and output.
+//!
+//! A request is a little-endian binary frame starting with an opcode:
+//!
+//! - `OP_GET` and `OP_GET_BY_PREFIX`: `col: u32`, `key_len: u32`, `key`. The payload of the
+//!   response is `found: u8`, followed by `value_len: u32` and `value` if found.
+//! - `OP_WRITE`: `ops: u32`, followed by each operation: `kind: u8` (0 for insert and 1 for
+//!   delete), `col: u32`, `key_len: u32`, `key` and, for insert, `value_len: u32`, `value`.
+//! - `OP_ITER_FROM_PREFIX`: `col: u32`, `prefix_len: u32`, `prefix`. The payload is `items: u32`,
+//!   followed by `key_len: u32`, `key`, `value_len: u32` and `value` of each item.
+//! - `OP_RESTORE`: `path_len: u32`, `path`.
+//! - `OP_IO_STATS`: `kind: u8` (0 for overall and 1 since the previous query). The payload is the
+//!   transactions, reads, cache reads, writes, bytes read, cache read bytes, bytes written and the
+//!   span in nanoseconds, each in `u64`.
+//!
+//! A response starts with a status, 0 for success followed by the payload, or 1 for an error
+//! followed by `message_len: u32` and the message.
+//!
+//! As with the former `parity-backend` feature, the buffered writes are kept here until they are
+//! written in one batch on flush, and the reads do not see them before.
+
+use std::io::{self, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use asb_options::Options;
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+use parity_util_mem::{MallocSizeOf, MallocSizeOfOps};
+
+use crate::overlay::KeyValuePair;
+
+const OP_GET: u8 = 0;
+const OP_GET_BY_PREFIX: u8 = 1;
+const OP_WRITE: u8 = 2;
+const OP_ITER_FROM_PREFIX: u8 = 3;
+const OP_RESTORE: u8 = 4;
+const OP_IO_STATS: u8 = 5;
+
+const KIND_INSERT: u8 = 0;
+const KIND_DELETE: u8 = 1;
+
+const STATUS_OK: u8 = 0;
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; read_u32(reader)? as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_value(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    Ok(if read_u8(reader)? == 1 {
+        Some(read_bytes(reader)?)
+    } else {
+        None
+    })
+}
+
+fn put_bytes(frame: &mut Vec<u8>, data: &[u8]) {
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(data);
+}
+
+fn key_frame(op: u8, col: u32, key: &[u8]) -> Vec<u8> {
+    let mut frame = vec![op];
+    frame.extend_from_slice(&col.to_le_bytes());
+    put_bytes(&mut frame, key);
+    frame
+}
+
+// The interface of `KeyValueDB` has no way to return the errors of these methods.
+fn node_failure(err: io::Error) -> ! {
+    panic!("The Parity RocksDB node fails: {}", err)
+}
+
+struct Node {
+    // The requests are written to the input of the child.
+    child: Child,
+    reader: BufReader<ChildStdout>,
+}
+
+pub struct ParityRocksDB {
+    node: Mutex<Node>,
+    buffered_transactions: RwLock<Vec<DBOp>>,
+}
+
+pub fn open(opts: &Options) -> io::Result<ParityRocksDB> {
+    let mut child = Command::new(&opts.parity_node)
+        .arg(&opts.db_dir)
+        .arg(opts.num_cols().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let message = format!(
+                "Cannot run {}: {}. Build it with `cargo build --release --manifest-path asb-backend/parity-rocksdb/Cargo.toml`",
+                opts.parity_node, e
+            );
+            io::Error::new(e.kind(), message)
+        })?;
+    let reader = BufReader::new(child.stdout.take().unwrap());
+    Ok(ParityRocksDB {
+        node: Mutex::new(Node { child, reader }),
+        buffered_transactions: Default::default(),
+    })
+}
+
+impl ParityRocksDB {
+    // Sends the request, and parses the payload of the response with `read`.
+    fn request<T>(
+        &self,
+        frame: &[u8],
+        read: impl FnOnce(&mut BufReader<ChildStdout>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let node = &mut *self.node.lock().unwrap();
+        let stdin = node.child.stdin.as_mut().unwrap();
+        stdin.write_all(frame)?;
+        stdin.flush()?;
+
+        if read_u8(&mut node.reader)? == STATUS_OK {
+            read(&mut node.reader)
+        } else {
+            let message = String::from_utf8_lossy(&read_bytes(&mut node.reader)?).into_owned();
+            Err(io::Error::new(io::ErrorKind::Other, message))
+        }
+    }
+}
+
+impl Drop for ParityRocksDB {
+    fn drop(&mut self) {
+        // Closing the input makes the node close the database and exit.
+        let node = self.node.get_mut().unwrap();
+        node.child.stdin.take();
+        let _ = node.child.wait();
+    }
+}
+
+impl MallocSizeOf for ParityRocksDB {
+    fn size_of(&self, _ops: &mut MallocSizeOfOps) -> usize {
+        // The database is held by the node, so only the buffered writes are counted.
+        let ops = self.buffered_transactions.read().unwrap();
+        ops.iter()
+            .map(|op| match op {
+                DBOp::Insert { key, value, .. } => key.len() + value.len(),
+                DBOp::Delete { key, .. } => key.len(),
+            })
+            .sum()
+    }
+}
+
+impl KeyValueDB for ParityRocksDB {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.request(&key_frame(OP_GET, col, key), read_value)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.request(&key_frame(OP_GET_BY_PREFIX, col, prefix), read_value)
+            .unwrap_or_else(|e| node_failure(e))
+            .map(Vec::into_boxed_slice)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        let txs = &mut *self.buffered_transactions.write().unwrap();
+        txs.extend(transaction.ops);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let ops = std::mem::take(&mut *self.buffered_transactions.write().unwrap());
+        if ops.is_empty() {
+            return Ok(());
+        }
+
+        let mut frame = vec![OP_WRITE];
+        frame.extend_from_slice(&(ops.len() as u32).to_le_bytes());
+        for op in ops {
+            match op {
+                DBOp::Insert { col, key, value } => {
+                    frame.push(KIND_INSERT);
+                    frame.extend_from_slice(&col.to_le_bytes());
+                    put_bytes(&mut frame, &key);
+                    put_bytes(&mut frame, &value);
+                }
+                DBOp::Delete { col, key } => {
+                    frame.push(KIND_DELETE);
+                    frame.extend_from_slice(&col.to_le_bytes());
+                    put_bytes(&mut frame, &key);
+                }
+            }
+        }
+        self.request(&frame, |_| Ok(()))
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.iter_from_prefix(col, &[])
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        // The node sends the items at once, as the benchmarks seldom iterate.
+        let items = self
+            .request(&key_frame(OP_ITER_FROM_PREFIX, col, prefix), |reader| {
+                let mut items = Vec::new();
+                for _ in 0..read_u32(reader)? {
+                    let key = read_bytes(reader)?.into_boxed_slice();
+                    let value = read_bytes(reader)?.into_boxed_slice();
+                    items.push((key, value));
+                }
+                Ok(items)
+            })
+            .unwrap_or_else(|e| node_failure(e));
+        Box::new(items.into_iter())
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        let mut frame = vec![OP_RESTORE];
+        put_bytes(&mut frame, new_db.as_bytes());
+        self.request(&frame, |_| Ok(()))
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        let kind = match kind {
+            IoStatsKind::Overall => 0,
+            IoStatsKind::SincePrevious => 1,
+        };
+        self.request(&[OP_IO_STATS, kind], |reader| {
+            let mut io_stats = IoStats::empty();
+            io_stats.transactions = read_u64(reader)?;
+            io_stats.reads = read_u64(reader)?;
+            io_stats.cache_reads = read_u64(reader)?;
+            io_stats.writes = read_u64(reader)?;
+            io_stats.bytes_read = read_u64(reader)?;
+            io_stats.cache_read_bytes = read_u64(reader)?;
+            io_stats.bytes_written = read_u64(reader)?;
+            io_stats.span = Duration::from_nanos(read_u64(reader)?);
+            io_stats.started = Instant::now()
+                .checked_sub(io_stats.span)
+                .unwrap_or_else(Instant::now);
+            Ok(io_stats)
+        })
+        .unwrap_or_else(|e| node_failure(e))
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::io;
+use std::io::ErrorKind::Other;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use kvdb::{DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+use redb::{Database, ReadableTable, TableDefinition};
+
+use crate::io_stats::RunningStats;
+use crate::overlay::{KeyValuePair, Overlay};
+
+// The file of the database under the database directory.
+const DATA_FILE: &str = "redb";
+
+// Each column of kvdb is a table in redb.
+fn table_name(col: u32) -> String {
+    format!("col{}", col)
+}
+
+fn into_io_error<E: Into<redb::Error>>(err: E) -> io::Error {
+    io::Error::new(Other, err.into())
+}
+
+fn closed() -> io::Error {
+    io::Error::new(Other, "The redb file failed to reopen in restore")
+}
+
+fn open_file(path: &Path, num_cols: u32) -> io::Result<Database> {
+    let db = Database::create(path).map_err(into_io_error)?;
+
+    // Create the tables at first, so the read transactions always find them.
+    let txn = db.begin_write().map_err(into_io_error)?;
+    for col in 0..num_cols {
+        let name = table_name(col);
+        txn.open_table(TableDefinition::<&[u8], &[u8]>::new(&name))
+            .map_err(into_io_error)?;
+    }
+    txn.commit().map_err(into_io_error)?;
+    Ok(db)
+}
+
+/// A key-value database on redb, a B-tree store written in pure Rust. The buffered writes are
+/// written in one redb write transaction on flush.
+pub struct RedbDatabase {
+    // It is `None` while the data file is replaced in `restore`, or after both the new and the
+    // previous files failed to open there.
+    db: RwLock<Option<Database>>,
+    path: PathBuf,
+    num_cols: u32,
+    overlay: Overlay,
+    stats: RunningStats,
+}
+
+pub fn open_database(db_dir: &str, num_cols: u32) -> io::Result<RedbDatabase> {
+    let path = Path::new(db_dir).join(DATA_FILE);
+    Ok(RedbDatabase {
+        db: RwLock::new(Some(open_file(&path, num_cols)?)),
+        path,
+        num_cols,
+        overlay: Overlay::new(num_cols),
+        stats: RunningStats::new(),
+    })
+}
+
+impl RedbDatabase {
+    fn committed_get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let name = table_name(col);
+        let db = self.db.read().unwrap();
+        let txn = db.as_ref().ok_or_else(closed)?.begin_read().map_err(into_io_error)?;
+        let table = txn
+            .open_table(TableDefinition::<&[u8], &[u8]>::new(&name))
+            .map_err(into_io_error)?;
+        let value = table.get(key).map_err(into_io_error)?;
+        Ok(value.map(|value| value.value().to_vec()))
+    }
+
+    // At most `limit` committed items in the column from the bound, in the order of keys.
+    fn committed_items(
+        &self,
+        col: u32,
+        from: Bound<&[u8]>,
+        limit: usize,
+    ) -> io::Result<Vec<KeyValuePair>> {
+        let name = table_name(col);
+        let db = self.db.read().unwrap();
+        let txn = db.as_ref().ok_or_else(closed)?.begin_read().map_err(into_io_error)?;
+        let table = txn
+            .open_table(TableDefinition::<&[u8], &[u8]>::new(&name))
+            .map_err(into_io_error)?;
+
+        let mut items = Vec::new();
+        for item in table.range((from, Bound::Unbounded)).map_err(into_io_error)? {
+            let (key, value) = item.map_err(into_io_error)?;
+            items.push((key.value().into(), value.value().into()));
+            if items.len() == limit {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl KeyValueDB for RedbDatabase {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        if let Some(value) = self.overlay.get(col, key) {
+            let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+            self.stats.tally(|t| {
+                t.reads += 1;
+                t.cache_reads += 1;
+                t.cache_read_bytes += bytes;
+            });
+            return Ok(value);
+        }
+
+        let value = self.committed_get(col, key)?;
+        let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+        self.stats.tally(|t| {
+            t.reads += 1;
+            t.bytes_read += bytes;
+        });
+        Ok(value)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.iter_from_prefix(col, prefix).next().map(|(_, v)| v)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.overlay.write(transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.overlay.flush(|changes| {
+            let (mut writes, mut bytes_written) = (0, 0);
+            let db = self.db.read().unwrap();
+            let txn = db.as_ref().ok_or_else(closed)?.begin_write().map_err(into_io_error)?;
+            for (col, changes) in changes.iter().enumerate() {
+                let name = table_name(col as u32);
+                let mut table = txn
+                    .open_table(TableDefinition::<&[u8], &[u8]>::new(&name))
+                    .map_err(into_io_error)?;
+                for (key, value) in changes {
+                    writes += 1;
+                    bytes_written += key.len();
+                    if let Some(value) = value {
+                        bytes_written += value.len();
+                        table.insert(&key[..], &value[..]).map_err(into_io_error)?;
+                    } else {
+                        table.remove(&key[..]).map_err(into_io_error)?;
+                    }
+                }
+            }
+            txn.commit().map_err(into_io_error)?;
+
+            self.stats.tally(|t| {
+                t.transactions += 1;
+                t.writes += writes;
+                t.bytes_written += bytes_written as u64;
+            });
+            Ok(())
+        })
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.iter_from_prefix(col, &[])
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = KeyValuePair> + 'a> {
+        self.overlay.iter_from_prefix(col, prefix, move |from, limit| {
+            self.committed_items(col, from, limit)
+        })
+    }
+
+    // Replaces the data with a copy of the redb file in `new_db`.
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.overlay.restore(|| {
+            // Copy the file beside the data file at first, so a failed copy leaves the data intact.
+            let staged = self.path.with_extension("restore");
+            if let Err(err) = fs::copy(Path::new(new_db).join(DATA_FILE), &staged) {
+                let _ = fs::remove_file(&staged);
+                return Err(err);
+            }
+
+            let db = &mut *self.db.write().unwrap();
+            // Close the database before replacing its file, and keep the previous file until the
+            // copy opens.
+            *db = None;
+            let backup = self.path.with_extension("backup");
+            let mut result = fs::rename(&self.path, &backup);
+            if result.is_ok() {
+                result = fs::rename(&staged, &self.path);
+                if result.is_ok() {
+                    match open_file(&self.path, self.num_cols) {
+                        Ok(opened) => {
+                            *db = Some(opened);
+                            let _ = fs::remove_file(&backup);
+                            return Ok(());
+                        }
+                        Err(err) => result = Err(err),
+                    }
+                }
+                let _ = fs::rename(&backup, &self.path);
+            }
+            let _ = fs::remove_file(&staged);
+
+            // Reopen the previous file. If it fails too, the database is left closed and the
+            // later calls return errors.
+            *db = Some(open_file(&self.path, self.num_cols)?);
+            result
+        })
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.stats.io_stats(kind)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for RedbDatabase {
+    // The pages are cached by redb out of the allocator, so only the overlay is counted.
+    fn size_of(&self, _ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        self.overlay.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn round_trip() {
+        let tempdir = TempDir::new("").unwrap();
+        let db = open_database(tempdir.path().to_str().unwrap(), 2).unwrap();
+
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key1", b"horse");
+        transaction.put(0, b"key2", b"pig");
+        transaction.put(1, b"key1", b"cat");
+        db.write_buffered(transaction);
+        assert_eq!(db.get(0, b"key1").unwrap().unwrap(), b"horse");
+        // The buffered writes are visible to the prefix reads before the flush.
+        assert_eq!(&*db.get_by_prefix(0, b"key2").unwrap(), b"pig");
+
+        db.flush().unwrap();
+        assert_eq!(db.get(0, b"key2").unwrap().unwrap(), b"pig");
+        let items: Vec<_> = db.iter(0).collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(&*items[0].0, b"key1");
+        assert_eq!(&*items[1].1, b"pig");
+
+        let mut transaction = db.transaction();
+        transaction.delete(0, b"key1");
+        transaction.put(0, b"key3", b"dog");
+        db.write_buffered(transaction);
+        assert!(db.get(0, b"key1").unwrap().is_none());
+        let keys: Vec<Box<[u8]>> = db.iter(0).map(|(key, _)| key).collect();
+        let expected: Vec<Box<[u8]>> = vec![b"key2".to_vec().into(), b"key3".to_vec().into()];
+        assert_eq!(keys, expected);
+
+        db.flush().unwrap();
+        assert!(db.get(0, b"key1").unwrap().is_none());
+        assert_eq!(db.iter(0).count(), 2);
+        assert_eq!(db.iter(1).count(), 1);
+    }
+
+    #[test]
+    fn restore() {
+        let source = TempDir::new("").unwrap();
+        let db = open_database(source.path().to_str().unwrap(), 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+        drop(db);
+
+        let tempdir = TempDir::new("").unwrap();
+        let db = open_database(tempdir.path().to_str().unwrap(), 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"other", b"value");
+        db.write_buffered(transaction);
+        db.restore(source.path().to_str().unwrap()).unwrap();
+        assert_eq!(db.get(0, b"key").unwrap().unwrap(), b"value");
+        assert!(db.get(0, b"other").unwrap().is_none());
+    }
+
+    #[test]
+    fn failed_restore_keeps_data() {
+        let tempdir = TempDir::new("").unwrap();
+        let db = open_database(tempdir.path().to_str().unwrap(), 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+
+        // There is no redb file to copy from an empty directory.
+        let empty = TempDir::new("").unwrap();
+        assert!(db.restore(empty.path().to_str().unwrap()).is_err());
+        assert_eq!(db.get(0, b"key").unwrap().unwrap(), b"value");
+
+        let mut transaction = db.transaction();
+        transaction.put(0, b"other", b"value");
+        db.write(transaction).unwrap();
+        assert_eq!(db.iter(0).count(), 2);
+    }
+
+    #[test]
+    fn unopenable_restore_keeps_data() {
+        let tempdir = TempDir::new("").unwrap();
+        let db = open_database(tempdir.path().to_str().unwrap(), 1).unwrap();
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key", b"value");
+        db.write(transaction).unwrap();
+
+        // The copy is not a redb file, so the previous file is opened again.
+        let garbage = TempDir::new("").unwrap();
+        fs::write(garbage.path().join(DATA_FILE), vec![0xab; 1 << 16]).unwrap();
+        assert!(db.restore(garbage.path().to_str().unwrap()).is_err());
+        assert_eq!(db.get(0, b"key").unwrap().unwrap(), b"value");
+        assert!(!db.path.with_extension("backup").exists());
+    }
+}
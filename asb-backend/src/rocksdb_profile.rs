@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use cfx_kvdb_rocksdb::{ColumnTuning, DBCompactionStyle, DBCompressionType, DatabaseConfig};
+use serde::{Deserialize, Serialize};
+
+/// The tuning options of a RocksDB column in a profile. The missing options keep the defaults.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnProfile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bloom_filter_bits: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_buffer_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+}
+
+/// A RocksDB tuning profile. The options in `column` apply to every column, and are overridden by
+/// the options in `columns` for the column with the index as the key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RocksDbProfile {
+    pub direct_reads: bool,
+    pub direct_io_for_flush_and_compaction: bool,
+    pub column: ColumnProfile,
+    pub columns: BTreeMap<String, ColumnProfile>,
+}
+
+fn parse_compaction_style(s: &str) -> Result<DBCompactionStyle, String> {
+    return Ok(match s {
+        "level" => DBCompactionStyle::Level,
+        "universal" => DBCompactionStyle::Universal,
+        "fifo" => DBCompactionStyle::Fifo,
+        _ => {
+            return Err(format!("Unrecognized compaction style {}", s));
+        }
+    });
+}
+
+fn parse_compression(s: &str) -> Result<DBCompressionType, String> {
+    return Ok(match s {
+        "no" | "none" => DBCompressionType::No,
+        "snappy" => DBCompressionType::Snappy,
+        "zlib" => DBCompressionType::Zlib,
+        "bz2" => DBCompressionType::Bz2,
+        "lz4" => DBCompressionType::Lz4,
+        "lz4hc" => DBCompressionType::Lz4hc,
+        "zstd" => DBCompressionType::Zstd,
+        _ => {
+            return Err(format!("Unrecognized compression {}", s));
+        }
+    });
+}
+
+impl ColumnProfile {
+    fn merge(&self, other: &ColumnProfile) -> ColumnProfile {
+        ColumnProfile {
+            block_size: other.block_size.or(self.block_size),
+            bloom_filter_bits: other.bloom_filter_bits.or(self.bloom_filter_bits),
+            write_buffer_size: other.write_buffer_size.or(self.write_buffer_size),
+            compaction_style: other
+                .compaction_style
+                .clone()
+                .or_else(|| self.compaction_style.clone()),
+            compression: other.compression.clone().or_else(|| self.compression.clone()),
+        }
+    }
+
+    fn tuning(&self) -> Result<ColumnTuning, String> {
+        Ok(ColumnTuning {
+            block_size: self.block_size,
+            bloom_filter_bits: self.bloom_filter_bits,
+            write_buffer_size: self.write_buffer_size,
+            compaction_style: self
+                .compaction_style
+                .as_deref()
+                .map(parse_compaction_style)
+                .transpose()?,
+            compression: self.compression.as_deref().map(parse_compression).transpose()?,
+        })
+    }
+
+    fn from_tuning(tuning: &ColumnTuning) -> ColumnProfile {
+        let name = |x: String| x.to_lowercase();
+        ColumnProfile {
+            block_size: tuning.block_size,
+            bloom_filter_bits: tuning.bloom_filter_bits,
+            write_buffer_size: tuning.write_buffer_size,
+            compaction_style: tuning.compaction_style.map(|x| name(format!("{:?}", x))),
+            compression: tuning.compression.map(|x| name(format!("{:?}", x))),
+        }
+    }
+}
+
+impl RocksDbProfile {
+    /// Loads the profile in JSON if the file name ends with `.json`, or in TOML otherwise.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if path.ends_with(".json") {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        }
+    }
+
+    pub fn apply(&self, config: &mut DatabaseConfig) -> Result<(), String> {
+        for key in self.columns.keys() {
+            match key.parse::<u32>() {
+                Ok(col) if col < config.columns => {}
+                _ => return Err(format!("Unknown column {}", key)),
+            }
+        }
+
+        config.direct_reads = self.direct_reads;
+        config.direct_io_for_flush_and_compaction = self.direct_io_for_flush_and_compaction;
+        config.column_tuning = (0..config.columns)
+            .map(|col| match self.columns.get(&col.to_string()) {
+                Some(column) => self.column.merge(column).tuning(),
+                None => self.column.tuning(),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+/// Writes the effective options of the database to the report directory, both as a profile
+/// resolving the defaults and as the options file of RocksDB.
+pub fn record(config: &DatabaseConfig, db_dir: &str, report_dir: &str) {
+    let profile = RocksDbProfile {
+        direct_reads: config.direct_reads,
+        direct_io_for_flush_and_compaction: config.direct_io_for_flush_and_compaction,
+        column: ColumnProfile::default(),
+        columns: (0..config.columns)
+            .map(|col| {
+                let tuning = config.effective_tuning(col);
+                (col.to_string(), ColumnProfile::from_tuning(&tuning))
+            })
+            .collect(),
+    };
+    let report_dir = Path::new(report_dir);
+    fs::write(
+        report_dir.join("rocksdb_profile.json"),
+        serde_json::to_string_pretty(&profile).unwrap(),
+    )
+    .unwrap();
+
+    // RocksDB writes its options to `OPTIONS-<number>` on every open. Keep the latest one.
+    let latest = fs::read_dir(db_dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("OPTIONS-"))
+        .max();
+    if let Some(name) = latest {
+        fs::copy(Path::new(db_dir).join(name), report_dir.join("rocksdb_OPTIONS")).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use asb_options::{Options, SimProfile};
+use asb_profile::ModeledTime;
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+use kvdb_memorydb::InMemory;
+
+use crate::io_stats::RunningStats;
+
+const MB: u64 = 1 << 20;
+
+/// The cost model of a storage device. A read costs `read_latency` plus the transfer time of the
+/// key and the value. A scan costs one `read_latency` plus the transfer time of the items it yields. A flush
+/// costs `sync_latency`, plus `write_latency` for each written key and the transfer time of the
+/// written bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyModel {
+    pub read_latency: Duration,
+    pub write_latency: Duration,
+    pub sync_latency: Duration,
+    /// Bytes per second.
+    pub read_bandwidth: u64,
+    /// Bytes per second.
+    pub write_bandwidth: u64,
+}
+
+impl LatencyModel {
+    pub fn from_profile(profile: SimProfile) -> Self {
+        let us = Duration::from_micros;
+        match profile {
+            SimProfile::NVMe => Self {
+                read_latency: us(20),
+                write_latency: us(2),
+                sync_latency: us(20),
+                read_bandwidth: 3000 * MB,
+                write_bandwidth: 2000 * MB,
+            },
+            SimProfile::SSD => Self {
+                read_latency: us(100),
+                write_latency: us(10),
+                sync_latency: us(200),
+                read_bandwidth: 550 * MB,
+                write_bandwidth: 500 * MB,
+            },
+            SimProfile::HDD => Self {
+                read_latency: us(8000),
+                write_latency: us(50),
+                sync_latency: us(10000),
+                read_bandwidth: 150 * MB,
+                write_bandwidth: 150 * MB,
+            },
+            // A network-attached block store, e.g., AWS EBS gp3 with the baseline throughput.
+            SimProfile::Cloud => Self {
+                read_latency: us(500),
+                write_latency: us(50),
+                sync_latency: us(1000),
+                read_bandwidth: 125 * MB,
+                write_bandwidth: 125 * MB,
+            },
+        }
+    }
+
+    fn transfer(bytes: u64, bandwidth: u64) -> Duration {
+        Duration::from_nanos(bytes * 1_000_000_000 / bandwidth)
+    }
+
+    fn read_cost(&self, bytes: u64) -> Duration {
+        self.read_latency + Self::transfer(bytes, self.read_bandwidth)
+    }
+
+    fn flush_cost(&self, writes: u64, bytes: u64) -> Duration {
+        self.sync_latency
+            + Duration::from_nanos(self.write_latency.as_nanos() as u64 * writes)
+            + Self::transfer(bytes, self.write_bandwidth)
+    }
+}
+
+/// The I/O time charged by the simulated backend.
+pub struct SimulatedClock {
+    charged_nanos: AtomicU64,
+    sleep: bool,
+}
+
+impl SimulatedClock {
+    fn charge(&self, cost: Duration) {
+        self.charged_nanos
+            .fetch_add(cost.as_nanos() as u64, Ordering::Relaxed);
+        if !self.sleep {
+            return;
+        }
+        // `thread::sleep` overshoots by tens of microseconds, so short waits spin.
+        if cost >= Duration::from_millis(1) {
+            thread::sleep(cost);
+        } else {
+            let start = Instant::now();
+            while start.elapsed() < cost {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+impl ModeledTime for SimulatedClock {
+    fn io_time(&self) -> Duration {
+        Duration::from_nanos(self.charged_nanos.load(Ordering::Relaxed))
+    }
+
+    fn is_virtual(&self) -> bool {
+        !self.sleep
+    }
+}
+
+/// An in-memory database charging the I/O time of a storage device. With a virtual clock, the time
+/// is only accounted; otherwise the calling thread waits for it.
+pub struct SimulatedDB {
+    db: InMemory,
+    model: LatencyModel,
+    clock: Arc<SimulatedClock>,
+    // The writes and bytes buffered since the last flush.
+    pending_writes: AtomicU64,
+    pending_bytes: AtomicU64,
+    stats: RunningStats,
+}
+
+pub fn open(opts: &Options) -> SimulatedDB {
+    SimulatedDB {
+        db: kvdb_memorydb::create(opts.num_cols()),
+        model: LatencyModel::from_profile(opts.sim_profile),
+        clock: Arc::new(SimulatedClock {
+            charged_nanos: AtomicU64::new(0),
+            sleep: opts.sim_sleep,
+        }),
+        pending_writes: AtomicU64::new(0),
+        pending_bytes: AtomicU64::new(0),
+        stats: RunningStats::new(),
+    }
+}
+
+impl SimulatedDB {
+    pub fn clock(&self) -> Arc<SimulatedClock> {
+        self.clock.clone()
+    }
+
+    // Charges a scan, which seeks once and then reads the items in order.
+    fn scan<'a>(
+        &'a self,
+        iter: Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>,
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.clock.charge(self.model.read_latency);
+        Box::new(iter.inspect(move |(key, value)| {
+            let bytes = (key.len() + value.len()) as u64;
+            self.clock
+                .charge(LatencyModel::transfer(bytes, self.model.read_bandwidth));
+            self.stats.tally(|t| {
+                t.reads += 1;
+                t.bytes_read += bytes;
+            });
+        }))
+    }
+}
+
+impl KeyValueDB for SimulatedDB {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let value = self.db.get(col, key)?;
+        let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+        self.clock.charge(self.model.read_cost(key.len() as u64 + bytes));
+        self.stats.tally(|t| {
+            t.reads += 1;
+            t.bytes_read += bytes;
+        });
+        Ok(value)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        let value = self.db.get_by_prefix(col, prefix);
+        let bytes = value.as_ref().map_or(0, |v| v.len()) as u64;
+        // The matched key is not returned, so the prefix is charged in place of it.
+        self.clock.charge(self.model.read_cost(prefix.len() as u64 + bytes));
+        self.stats.tally(|t| {
+            t.reads += 1;
+            t.bytes_read += bytes;
+        });
+        value
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        let (mut writes, mut bytes) = (0, 0);
+        for op in &transaction.ops {
+            writes += 1;
+            bytes += op.key().len() as u64;
+            if let DBOp::Insert { value, .. } = op {
+                bytes += value.len() as u64;
+            }
+        }
+        self.pending_writes.fetch_add(writes, Ordering::Relaxed);
+        self.pending_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.db.write_buffered(transaction);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let writes = self.pending_writes.swap(0, Ordering::Relaxed);
+        let bytes = self.pending_bytes.swap(0, Ordering::Relaxed);
+        if writes == 0 {
+            return Ok(());
+        }
+        self.clock.charge(self.model.flush_cost(writes, bytes));
+        self.stats.tally(|t| {
+            t.transactions += 1;
+            t.writes += writes;
+            t.bytes_written += bytes;
+        });
+        self.db.flush()
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.scan(self.db.iter(col))
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.scan(self.db.iter_from_prefix(col, prefix))
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.db.restore(new_db)
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.stats.io_stats(kind)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for SimulatedDB {
+    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        parity_util_mem::MallocSizeOf::size_of(&self.db, ops)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asb_options::StructOpt;
+
+    fn charged(db: &SimulatedDB) -> u128 {
+        db.clock().io_time().as_nanos()
+    }
+
+    #[test]
+    fn charged_time_on_ssd() {
+        let args = ["asb", "-a", "raw", "-b", "simulated", "--sim-profile", "ssd"];
+        let db = open(&Options::from_iter(args));
+        assert!(db.clock().is_virtual());
+
+        // 200 us to sync, 10 us for each of the 2 writes, and 200 bytes at 500 MB/s.
+        let mut transaction = db.transaction();
+        transaction.put(0, b"key0", &[0; 96]);
+        transaction.put(0, b"key1", &[1; 96]);
+        db.write_buffered(transaction);
+        assert_eq!(charged(&db), 0);
+        db.flush().unwrap();
+        assert_eq!(charged(&db), 220_381);
+
+        // 100 us to read, and the key and the value at 550 MB/s.
+        db.get(0, b"key0").unwrap();
+        assert_eq!(charged(&db), 220_381 + 100_173);
+        db.get(0, b"none").unwrap();
+        assert_eq!(charged(&db), 320_554 + 100_006);
+        db.get_by_prefix(0, b"key1").unwrap();
+        assert_eq!(charged(&db), 420_560 + 100_173);
+
+        // One seek for the scan, and the items at 550 MB/s.
+        assert_eq!(db.iter_from_prefix(0, b"key").count(), 2);
+        assert_eq!(charged(&db), 520_733 + 100_346);
+
+        // Nothing is buffered.
+        db.flush().unwrap();
+        assert_eq!(charged(&db), 621_079);
+    }
+}
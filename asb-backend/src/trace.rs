@@ -0,0 +1,366 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use kvdb::{DBOp, DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+
+// The trace file starts with the magic, the version and the number of columns. Each record is an
+// operation tag and the nanoseconds since the recording started, followed by
+// - get: the key and the value length (`MISSING` if not found);
+// - write_buffered and write: the number of ops, and for each op, the op tag, the key and the
+//   value length for an insertion;
+// - flush: nothing.
+// A key is encoded as the column, the key length and the key. All integers are in little endian.
+const MAGIC: &[u8; 8] = b"ASBTRACE";
+const VERSION: u8 = 2;
+
+const GET: u8 = 0;
+const WRITE_BUFFERED: u8 = 1;
+const WRITE: u8 = 2;
+const FLUSH: u8 = 3;
+
+const INSERT: u8 = 0;
+const DELETE: u8 = 1;
+
+const MISSING: u32 = u32::MAX;
+
+fn key_hash(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    hasher.finish()
+}
+
+fn encode_key(buf: &mut Vec<u8>, col: u32, key: &[u8]) {
+    buf.extend_from_slice(&col.to_le_bytes());
+    buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    buf.extend_from_slice(key);
+}
+
+fn encode_transaction(transaction: &DBTransaction) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(transaction.ops.len() as u32).to_le_bytes());
+    for op in &transaction.ops {
+        match op {
+            DBOp::Insert { col, key, value } => {
+                buf.push(INSERT);
+                encode_key(&mut buf, *col, key);
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            }
+            DBOp::Delete { col, key } => {
+                buf.push(DELETE);
+                encode_key(&mut buf, *col, key);
+            }
+        }
+    }
+    buf
+}
+
+/// A wrapper logging every `get`, `write_buffered`, `write` and `flush` to a trace file. The keys
+/// are logged in full, so a replay finds the keys written before the recording, but only the sizes
+/// of the values are logged. The prefix reads and the iterators are not logged.
+pub struct TraceRecorder {
+    inner: Arc<dyn KeyValueDB>,
+    writer: Mutex<BufWriter<File>>,
+    start: Instant,
+}
+
+impl TraceRecorder {
+    pub fn create(inner: Arc<dyn KeyValueDB>, path: &str, num_cols: u32) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&num_cols.to_le_bytes())?;
+        Ok(Self {
+            inner,
+            writer: Mutex::new(writer),
+            start: Instant::now(),
+        })
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+
+    fn record(&self, tag: u8, nanos: u64, body: &[u8]) {
+        let writer = &mut *self.writer.lock().unwrap();
+        writer.write_all(&[tag]).unwrap();
+        writer.write_all(&nanos.to_le_bytes()).unwrap();
+        writer.write_all(body).unwrap();
+    }
+}
+
+impl KeyValueDB for TraceRecorder {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let nanos = self.now();
+        let value = self.inner.get(col, key)?;
+
+        let mut body = Vec::with_capacity(12 + key.len());
+        encode_key(&mut body, col, key);
+        let len = value.as_ref().map_or(MISSING, |v| v.len() as u32);
+        body.extend_from_slice(&len.to_le_bytes());
+        self.record(GET, nanos, &body);
+        Ok(value)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.inner.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.record(WRITE_BUFFERED, self.now(), &encode_transaction(&transaction));
+        self.inner.write_buffered(transaction)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.record(WRITE, self.now(), &encode_transaction(&transaction));
+        self.inner.write(transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.record(FLUSH, self.now(), &[]);
+        // Keep the trace complete up to the last flush, even if the database is never dropped.
+        self.writer.lock().unwrap().flush()?;
+        self.inner.flush()
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.inner.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.inner.restore(new_db)
+    }
+
+    fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+        self.inner.io_stats(kind)
+    }
+}
+
+impl parity_util_mem::MallocSizeOf for TraceRecorder {
+    fn size_of(&self, ops: &mut parity_util_mem::MallocSizeOfOps) -> usize {
+        parity_util_mem::MallocSizeOf::size_of(&*self.inner, ops)
+    }
+}
+
+struct TraceReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    // Returns `None` at the end of the trace.
+    fn read_tag(&mut self) -> io::Result<Option<u8>> {
+        let mut buf = [0u8; 1];
+        loop {
+            match self.reader.read(&mut buf) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(buf[0])),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn read_key(&mut self) -> io::Result<(u32, Vec<u8>)> {
+        let col = self.read_u32()?;
+        let len = self.read_u32()?;
+        let mut key = vec![0u8; len as usize];
+        self.reader.read_exact(&mut key)?;
+        Ok((col, key))
+    }
+
+    fn read_transaction(&mut self) -> io::Result<DBTransaction> {
+        let count = self.read_u32()?;
+        let mut ops = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let tag = self.read_u8()?;
+            let (col, key) = self.read_key()?;
+            let op = match tag {
+                INSERT => {
+                    let len = self.read_u32()?;
+                    let value = synthetic_bytes(len, key_hash(&key));
+                    DBOp::Insert {
+                        col,
+                        key: key.into(),
+                        value,
+                    }
+                }
+                DELETE => DBOp::Delete {
+                    col,
+                    key: key.into(),
+                },
+                _ => return Err(invalid_data(format!("Unknown op tag {}", tag))),
+            };
+            ops.push(op);
+        }
+        Ok(DBTransaction { ops })
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Pseudo-random bytes determined by the seed, so the values are not trivially compressible.
+fn synthetic_bytes(len: u32, seed: u64) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// The summary of a replayed trace.
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub gets: u64,
+    pub transactions: u64,
+    pub writes: u64,
+    pub flushes: u64,
+    /// The reads whose key is found in the replay but missing in the trace, or the opposite. It is
+    /// non-zero if the replay does not start from the recorded state.
+    pub mismatched_gets: u64,
+}
+
+/// Drives the database with the I/O stream in the trace file, as fast as possible. The values are
+/// synthesized with the recorded sizes.
+pub fn replay(path: &str, db: &dyn KeyValueDB, num_cols: u32) -> io::Result<ReplaySummary> {
+    let mut reader = TraceReader {
+        reader: BufReader::new(File::open(path)?),
+    };
+
+    let mut magic = [0u8; 8];
+    reader.reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data(format!("{} is not an I/O trace", path)));
+    }
+    let version = reader.read_u8()?;
+    if version != VERSION {
+        return Err(invalid_data(format!("Unsupported trace version {}", version)));
+    }
+    let trace_cols = reader.read_u32()?;
+    if trace_cols != num_cols {
+        return Err(invalid_data(format!(
+            "The trace is recorded with {} columns, but the database has {}",
+            trace_cols, num_cols
+        )));
+    }
+
+    let mut summary = ReplaySummary::default();
+    while let Some(tag) = reader.read_tag()? {
+        let _nanos = reader.read_u64()?;
+        match tag {
+            GET => {
+                let (col, key) = reader.read_key()?;
+                let recorded = reader.read_u32()?;
+                let value = db.get(col, &key)?;
+                summary.gets += 1;
+                if value.is_some() != (recorded != MISSING) {
+                    summary.mismatched_gets += 1;
+                }
+            }
+            WRITE_BUFFERED | WRITE => {
+                let transaction = reader.read_transaction()?;
+                summary.transactions += 1;
+                summary.writes += transaction.ops.len() as u64;
+                if tag == WRITE {
+                    db.write(transaction)?;
+                } else {
+                    db.write_buffered(transaction);
+                }
+            }
+            FLUSH => {
+                summary.flushes += 1;
+                db.flush()?;
+            }
+            _ => return Err(invalid_data(format!("Unknown record tag {}", tag))),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    // The data before the recording, as if it is copied from `--warmup-from`.
+    fn warmed_up() -> Arc<dyn KeyValueDB> {
+        let db = kvdb_memorydb::create(2);
+        let mut transaction = db.transaction();
+        transaction.put(0, b"warm", b"value");
+        transaction.put(1, b"cold", b"value");
+        db.write(transaction).unwrap();
+        Arc::new(db)
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let tempdir = TempDir::new("").unwrap();
+        let path = tempdir.path().join("trace");
+        let path = path.to_str().unwrap();
+
+        let recorder = TraceRecorder::create(warmed_up(), path, 2).unwrap();
+        assert!(recorder.get(0, b"warm").unwrap().is_some());
+        let mut transaction = recorder.transaction();
+        transaction.put(0, b"key1", b"horse");
+        transaction.put(1, b"key2", b"pig");
+        transaction.delete(1, b"cold");
+        recorder.write_buffered(transaction);
+        recorder.flush().unwrap();
+        assert!(recorder.get(1, b"key2").unwrap().is_some());
+        assert!(recorder.get(1, b"cold").unwrap().is_none());
+        let mut transaction = recorder.transaction();
+        transaction.put(0, b"key3", b"dog");
+        recorder.write(transaction).unwrap();
+        assert!(recorder.get(0, b"missing").unwrap().is_none());
+        drop(recorder);
+
+        let db = warmed_up();
+        let summary = replay(path, &*db, 2).unwrap();
+        assert_eq!(summary.gets, 4);
+        assert_eq!(summary.transactions, 2);
+        assert_eq!(summary.writes, 4);
+        assert_eq!(summary.flushes, 1);
+        assert_eq!(summary.mismatched_gets, 0);
+        assert_eq!(db.get(0, b"key1").unwrap().unwrap().len(), 5);
+        assert!(db.get(1, b"cold").unwrap().is_none());
+
+        // Without the data before the recording, the read of the warm key misses.
+        let db = kvdb_memorydb::create(2);
+        assert_eq!(replay(path, &db, 2).unwrap().mismatched_gets, 1);
+        assert!(replay(path, &db, 3).is_err());
+    }
+}
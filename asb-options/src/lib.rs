@@ -69,6 +69,82 @@ pub struct Options {
 
     #[structopt(long)]
     pub shards: Option<usize>,
+
+    #[structopt(long, help = "Number of threads for the LVMT commit")]
+    pub threads: Option<usize>,
+
+    #[structopt(long, parse(try_from_str = parse_curve), default_value = "bn254")]
+    pub curve: Curve,
+
+    #[structopt(long, parse(try_from_str = parse_lvmt_depth), help = "Depth of each AMT in LVMT")]
+    pub lvmt_depth: Option<usize>,
+
+    #[structopt(long, help = "Generate missing public parameters with an insecure setup")]
+    pub gen_pp: bool,
+
+    #[structopt(long, parse(try_from_str = parse_journal), default_value = "fast")]
+    pub journal: JournalAlgo,
+
+    #[structopt(long, help = "Number of recent eras kept by the MPT journal", default_value = "0")]
+    pub journal_eras: usize,
+
+    #[structopt(
+        long,
+        parse(try_from_str = parse_mpt_accounts),
+        help = "Split MPT keys into the storage tries of this number of accounts"
+    )]
+    pub mpt_accounts: Option<usize>,
+
+    #[structopt(
+        long,
+        parse(try_from_str = parse_rain_depth),
+        help = "Depth of the in-memory top layer of RainBlock",
+        default_value = "6"
+    )]
+    pub rain_depth: usize,
+
+    #[structopt(
+        long,
+        help = "Address of the RainBlock storage node (host:port or unix:path), which holds all the trie nodes below the top layer in place of --backend"
+    )]
+    pub rain_remote: Option<String>,
+
+    #[structopt(long, help = "Prune the RainBlock nodes obsoleted before the last K commits")]
+    pub rain_retain: Option<usize>,
+
+    #[structopt(long, parse(try_from_str = parse_hasher), default_value = "keccak")]
+    pub hasher: HashAlgo,
+
+    #[structopt(long, parse(try_from_str = parse_sim_profile), default_value = "ssd")]
+    pub sim_profile: SimProfile,
+
+    #[structopt(long, help = "Wait for the modeled I/O time of the simulated backend")]
+    pub sim_sleep: bool,
+
+    #[structopt(long, help = "Record the I/O of the backend to the trace file")]
+    pub record_trace: Option<String>,
+
+    #[structopt(long, help = "Replay the I/O trace file on the backend instead of the tasks")]
+    pub replay_trace: Option<String>,
+
+    #[structopt(long, parse(try_from_str = parse_mdbx_sync), default_value = "durable")]
+    pub mdbx_sync: MdbxSync,
+
+    #[structopt(long, help = "The RocksDB tuning profile in TOML or JSON")]
+    pub rocksdb_profile: Option<String>,
+
+    #[structopt(
+        long,
+        help = "The executable of the node serving the rocksdb-parity backend",
+        default_value = "asb-backend/parity-rocksdb/target/release/parity-rocksdb-node"
+    )]
+    pub parity_node: String,
+
+    #[structopt(long, help = "Cache the values read from the backend in the given size in MB")]
+    pub value_cache: Option<u64>,
+
+    #[structopt(long, parse(try_from_str = parse_cache_policy), default_value = "lru")]
+    pub value_cache_policy: CachePolicy,
 }
 
 impl Options {
@@ -78,14 +154,62 @@ impl Options {
         } else {
             "real".into()
         };
-        if self.algorithm != AuthAlgo::LVMT || self.shards.is_none() {
-            format!("{}/{:?}_{}/", input, self.algorithm, task_code)
-        } else {
-            format!("{}/LVMT{}_{}/", input, self.shards.unwrap(), task_code)
+        let variant_code = self.variant_code();
+        let algo_code = format!("{:?}{}", self.algorithm, variant_code);
+        match self.shards {
+            Some(shards) if self.algorithm == AuthAlgo::LVMT => {
+                if variant_code.is_empty() {
+                    // The name of the default variant is kept, so the existing warmups still load.
+                    format!("{}/{}{}_{}/", input, algo_code, shards, task_code)
+                } else {
+                    format!("{}/{}-s{}_{}/", input, algo_code, shards, task_code)
+                }
+            }
+            _ => format!("{}/{}_{}/", input, algo_code, task_code),
         }
     }
+    // Distinguishes the non-default variants of an algorithm.
+    fn variant_code(&self) -> String {
+        let mut code = String::new();
+        match self.algorithm {
+            AuthAlgo::LVMT | AuthAlgo::AMT(_) => {
+                if self.curve != Curve::BN254 {
+                    code += &format!("-{:?}", self.curve);
+                }
+                if let (AuthAlgo::LVMT, Some(depth)) = (&self.algorithm, self.lvmt_depth) {
+                    code += &format!("-d{}", depth);
+                }
+            }
+            AuthAlgo::MPT => {
+                if self.journal != JournalAlgo::OverlayRecent {
+                    code += &format!("-{:?}", self.journal);
+                }
+                if self.journal_eras > 0 {
+                    code += &format!("-e{}", self.journal_eras);
+                }
+                if let Some(accounts) = self.mpt_accounts {
+                    code += &format!("-a{}", accounts);
+                }
+            }
+            AuthAlgo::RAIN => {
+                if self.rain_depth != 6 {
+                    code += &format!("-d{}", self.rain_depth);
+                }
+                if let Some(retain) = self.rain_retain {
+                    code += &format!("-r{}", retain);
+                }
+            }
+            _ => {}
+        }
+        if matches!(self.algorithm, AuthAlgo::MPT | AuthAlgo::RAIN)
+            && self.hasher != HashAlgo::Keccak
+        {
+            code += &format!("-{:?}", self.hasher);
+        }
+        code
+    }
     pub fn settings(&self) -> String {
-        format!("{:?},{:e}", self.algorithm, self.total_keys)
+        format!("{:?}{},{:e}", self.algorithm, self.variant_code(), self.total_keys)
     }
     pub fn warmup_to(&self) -> Option<String> {
         self.warmup_to.as_ref().map(|x| self.warmup_dir(x))
@@ -97,7 +221,7 @@ impl Options {
     pub fn num_cols(&self) -> u32 {
         match self.algorithm {
             AuthAlgo::LVMT => 3,
-            AuthAlgo::HOT | AuthAlgo::HOTKeccak => 2,
+            AuthAlgo::MPT | AuthAlgo::HOT | AuthAlgo::HOTKeccak => 2,
             _ => 1,
         }
     }
@@ -135,6 +259,156 @@ fn parse_algo(s: &str) -> Result<AuthAlgo, String> {
     });
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Curve {
+    BN254,
+    BLS12_381,
+}
+
+fn parse_curve(s: &str) -> Result<Curve, String> {
+    return Ok(match s {
+        "bn254" => Curve::BN254,
+        "bls12-381" => Curve::BLS12_381,
+        _ => {
+            return Err("Unrecognized curve".into());
+        }
+    });
+}
+
+// The AMT depths of LVMT instantiated in `asb-authdb`.
+const LVMT_DEPTHS: [usize; 4] = [8, 12, 16, 20];
+
+fn parse_lvmt_depth(s: &str) -> Result<usize, String> {
+    let depth = s.parse::<usize>().map_err(|x| x.to_string())?;
+    if !LVMT_DEPTHS.contains(&depth) {
+        return Err(format!(
+            "Unsupported LVMT depth {}, the supported depths are {:?}",
+            depth, LVMT_DEPTHS
+        ));
+    }
+    Ok(depth)
+}
+
+// The top-layer depths of RainBlock instantiated in `asb-authdb`.
+const RAIN_DEPTHS: std::ops::RangeInclusive<usize> = 1..=10;
+
+fn parse_rain_depth(s: &str) -> Result<usize, String> {
+    let depth = s.parse::<usize>().map_err(|x| x.to_string())?;
+    if !RAIN_DEPTHS.contains(&depth) {
+        return Err(format!(
+            "Unsupported RainBlock depth {}, the supported depths are {:?}",
+            depth, RAIN_DEPTHS
+        ));
+    }
+    Ok(depth)
+}
+
+/// The journal algorithms of OpenEthereum, also known as the pruning modes "archive", "light",
+/// "fast" and "basic".
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum JournalAlgo {
+    Archive,
+    EarlyMerge,
+    OverlayRecent,
+    RefCounted,
+}
+
+fn parse_journal(s: &str) -> Result<JournalAlgo, String> {
+    return Ok(match s {
+        "archive" => JournalAlgo::Archive,
+        "light" | "early-merge" => JournalAlgo::EarlyMerge,
+        "fast" | "overlay-recent" => JournalAlgo::OverlayRecent,
+        "basic" | "ref-counted" => JournalAlgo::RefCounted,
+        _ => {
+            return Err("Unrecognized journal algorithm".into());
+        }
+    });
+}
+
+fn parse_mpt_accounts(s: &str) -> Result<usize, String> {
+    let accounts = s.parse::<usize>().map_err(|x| x.to_string())?;
+    if accounts == 0 {
+        return Err("The number of MPT accounts must be positive".into());
+    }
+    Ok(accounts)
+}
+
+/// The hash functions of the trie nodes in MPT and RainBlock.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum HashAlgo {
+    Keccak,
+    Blake2b,
+    Blake3,
+}
+
+fn parse_hasher(s: &str) -> Result<HashAlgo, String> {
+    return Ok(match s {
+        "keccak" => HashAlgo::Keccak,
+        "blake2" | "blake2b" => HashAlgo::Blake2b,
+        "blake3" => HashAlgo::Blake3,
+        _ => {
+            return Err("Unrecognized hash function".into());
+        }
+    });
+}
+
+/// The storage devices modeled by the simulated backend.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SimProfile {
+    NVMe,
+    SSD,
+    HDD,
+    Cloud,
+}
+
+fn parse_sim_profile(s: &str) -> Result<SimProfile, String> {
+    return Ok(match s {
+        "nvme" => SimProfile::NVMe,
+        "ssd" => SimProfile::SSD,
+        "hdd" => SimProfile::HDD,
+        "cloud" => SimProfile::Cloud,
+        _ => {
+            return Err("Unrecognized device profile".into());
+        }
+    });
+}
+
+/// The durability modes of MDBX.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MdbxSync {
+    Durable,
+    SafeNoSync,
+    UtterlyNoSync,
+}
+
+fn parse_mdbx_sync(s: &str) -> Result<MdbxSync, String> {
+    return Ok(match s {
+        "durable" => MdbxSync::Durable,
+        "safe-no-sync" => MdbxSync::SafeNoSync,
+        "utterly-no-sync" => MdbxSync::UtterlyNoSync,
+        _ => {
+            return Err("Unrecognized MDBX durability mode".into());
+        }
+    });
+}
+
+/// The eviction policies of the value cache.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum CachePolicy {
+    LRU,
+    ARC,
+}
+
+fn parse_cache_policy(s: &str) -> Result<CachePolicy, String> {
+    return Ok(match s {
+        "lru" => CachePolicy::LRU,
+        "arc" => CachePolicy::ARC,
+        _ => {
+            return Err("Unrecognized cache policy".into());
+        }
+    });
+}
+
 fn parse_num(s: &str) -> Result<usize, String> {
     let base = match s
         .chars()
@@ -161,17 +435,43 @@ fn parse_num(s: &str) -> Result<usize, String> {
 #[strum(serialize_all = "lowercase")]
 pub enum Backend {
     RocksDB,
+    ParityRocksDB,
     InMemoryDB,
     MDBX,
+    Redb,
+    Simulated,
 }
 
 fn parse_backend(s: &str) -> Result<Backend, String> {
     return Ok(match s {
-        "rocksdb" => Backend::RocksDB,
+        "rocksdb" | "rocksdb-cfx" => Backend::RocksDB,
+        "rocksdb-parity" => Backend::ParityRocksDB,
         "memory" => Backend::InMemoryDB,
         "mdbx" => Backend::MDBX,
+        "redb" => Backend::Redb,
+        "simulated" => Backend::Simulated,
         _ => {
             return Err("Unrecognized backend".into());
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warmup_name(args: &[&str]) -> String {
+        let args = ["asb", "-a", "lvmt", "--warmup-from", "w"].iter().chain(args);
+        Options::from_iter(args).warmup_from().unwrap()
+    }
+
+    #[test]
+    fn lvmt_shards_in_warmup_dir() {
+        assert_eq!(warmup_name(&["--shards", "16"]), "w/LVMT16_1e5/");
+        assert_eq!(warmup_name(&["--shards", "16", "--lvmt-depth", "16"]), "w/LVMT-d16-s16_1e5/");
+        assert_ne!(
+            warmup_name(&["--shards", "6", "--lvmt-depth", "16"]),
+            warmup_name(&["--shards", "16", "--lvmt-depth", "16"])
+        );
+    }
+}
@@ -10,8 +10,8 @@ use simple_process_stats::ProcessStats;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::sync::Mutex;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 
 lazy_static! {
@@ -32,6 +32,15 @@ pub struct Reporter<'a> {
 
     opts: &'a Options,
     counter: Box<dyn CounterTrait>,
+
+    modeled_time: Option<Arc<dyn ModeledTime>>,
+    round_start_io_time: Duration,
+
+    column_stats: Option<Arc<dyn ColumnStats>>,
+
+    engine_stats: Option<Arc<dyn EngineStats>>,
+
+    cache_stats: Option<Arc<dyn CacheStats>>,
 }
 
 impl<'a> Reporter<'a> {
@@ -58,6 +67,11 @@ impl<'a> Reporter<'a> {
             total_write_count: 0,
             round_start_read_count: 0,
             round_start_write_count: 0,
+            modeled_time: None,
+            round_start_io_time: Duration::ZERO,
+            column_stats: None,
+            engine_stats: None,
+            cache_stats: None,
         }
     }
 
@@ -65,9 +79,37 @@ impl<'a> Reporter<'a> {
         self.counter = counter;
     }
 
+    pub fn set_modeled_time(&mut self, modeled_time: Arc<dyn ModeledTime>) {
+        self.modeled_time = Some(modeled_time);
+    }
+
+    pub fn set_column_stats(&mut self, column_stats: Arc<dyn ColumnStats>) {
+        self.column_stats = Some(column_stats);
+    }
+
+    pub fn set_engine_stats(&mut self, engine_stats: Arc<dyn EngineStats>) {
+        self.engine_stats = Some(engine_stats);
+    }
+
+    pub fn set_cache_stats(&mut self, cache_stats: Arc<dyn CacheStats>) {
+        self.cache_stats = Some(cache_stats);
+    }
+
     pub fn start(&mut self) {
         self.start_time = Instant::now();
         self.round_start_time = Instant::now();
+        if let Some(modeled_time) = &self.modeled_time {
+            self.round_start_io_time = modeled_time.io_time();
+        }
+        if let Some(column_stats) = &self.column_stats {
+            column_stats.column_stats();
+        }
+        if let Some(engine_stats) = &self.engine_stats {
+            engine_stats.engine_stats();
+        }
+        if let Some(cache_stats) = &self.cache_stats {
+            cache_stats.cache_stats();
+        }
         self.counter.reset();
     }
 
@@ -155,17 +197,120 @@ impl<'a> Reporter<'a> {
                 ("".into(), "".into())
             }
         };
+        // With a virtual clock, the modeled time is the running time plus the charged I/O time.
+        // Otherwise, the running time includes the I/O time already.
+        let (modeled_stdout, modeled_fileout) = if let Some(modeled_time) = &self.modeled_time {
+            let io_time = modeled_time.io_time();
+            let io_per_op = (io_time - self.round_start_io_time).as_secs_f64()
+                / (read_count + write_count) as f64;
+            let modeled_per_op = if modeled_time.is_virtual() {
+                avg_time + io_per_op
+            } else {
+                avg_time
+            };
+            self.round_start_io_time = io_time;
+            (
+                format!(
+                    "I/O {:>7.3?} us/op, modeled {:>7.3?} us/op > ",
+                    io_per_op * 1e6,
+                    modeled_per_op * 1e6
+                ),
+                format!(",{:.3?},{:.3?}", io_per_op * 1e6, modeled_per_op * 1e6),
+            )
+        } else {
+            ("".into(), "".into())
+        };
+
+        // The amplification and the size of each column, while the log keeps the raw counts.
+        let (column_stdout, column_fileout) = if let Some(column_stats) = &self.column_stats {
+            let mut stdout = String::new();
+            let mut fileout = String::new();
+            for (col, stat) in column_stats.column_stats().iter().enumerate() {
+                stdout += &format!(
+                    "col{} r {:.3} w {:.3}",
+                    col,
+                    stat.reads as f64 / read_count as f64,
+                    stat.writes as f64 / write_count as f64
+                );
+                if let Some(size) = stat.disk_size {
+                    stdout += &format!(" {:.1} MB", size as f64 / (1 << 20) as f64);
+                }
+                stdout += ", ";
+                fileout += &format!(
+                    ",{},{},{},{},{}",
+                    stat.reads,
+                    stat.writes,
+                    stat.bytes_read,
+                    stat.bytes_written,
+                    stat.disk_size.map_or(String::new(), |size| size.to_string())
+                );
+            }
+            (stdout.trim_end_matches(", ").to_string() + " > ", fileout)
+        } else {
+            ("".into(), "".into())
+        };
+
+        // The device-level write amplification is next to the logical one above.
+        let (engine_stdout, engine_fileout) = if let Some(engine_stats) = &self.engine_stats {
+            let stat = engine_stats.engine_stats();
+            let mb = |bytes: u64| bytes as f64 / (1 << 20) as f64;
+            (
+                format!(
+                    "Compaction r {:.1} MB w {:.1} MB, Device write amp {:>6.3}, Block cache hit {:.1}%, Bloom useful {}, Stall {:.3} s > ",
+                    mb(stat.compact_read_bytes),
+                    mb(stat.compact_write_bytes),
+                    stat.device_write_amp(),
+                    stat.block_cache_hit_rate() * 100.0,
+                    c(stat.bloom_filter_useful),
+                    stat.stall_micros as f64 / 1e6,
+                ),
+                format!(
+                    ",{},{},{},{},{},{}",
+                    stat.compact_read_bytes,
+                    stat.compact_write_bytes,
+                    stat.device_write_amp(),
+                    stat.block_cache_hit_rate(),
+                    stat.bloom_filter_useful,
+                    stat.stall_micros
+                ),
+            )
+        } else {
+            ("".into(), "".into())
+        };
+
+        let (cache_stdout, cache_fileout) = if let Some(cache_stats) = &self.cache_stats {
+            let stat = cache_stats.cache_stats();
+            let hit_rate = stat.hits as f64 / (stat.hits + stat.misses) as f64;
+            (
+                format!(
+                    "Value cache hit {:.1}%, {:.1} MB > ",
+                    hit_rate * 100.0,
+                    stat.bytes as f64 / (1 << 20) as f64
+                ),
+                format!(",{},{},{}", stat.hits, stat.misses, stat.bytes),
+            )
+        } else {
+            ("".into(), "".into())
+        };
+
         let customized = self.counter.report();
-        println!("{} {} {}", common, stdout, customized);
+        println!(
+            "{} {}{}{}{}{} {}",
+            common, stdout, cache_stdout, engine_stdout, modeled_stdout, column_stdout, customized
+        );
 
         if let Some(file) = &mut self.log_file {
             let _ = writeln!(
                 file,
-                "{},{},{:.3?},{}",
+                "{},{},{:.3?},{}{}{}{}{}",
                 self.opts.settings(),
                 (epoch + 1) / self.opts.report_epoch,
                 avg_time * 1e6,
-                fileout
+                fileout,
+                modeled_fileout,
+                column_fileout,
+                engine_fileout,
+                cache_fileout
             );
         }
         self.empty_reads = 0;
@@ -186,6 +331,78 @@ impl<'a> Reporter<'a> {
     }
 }
 
+/// The I/O time modeled by a simulated backend.
+pub trait ModeledTime {
+    /// The total I/O time charged so far.
+    fn io_time(&self) -> Duration;
+    /// Whether the I/O time is only accounted, rather than spent by waiting.
+    fn is_virtual(&self) -> bool;
+}
+
+/// The I/O statistics of a backend tracked for each column.
+pub trait ColumnStats {
+    /// The statistics of each column since the previous call.
+    fn column_stats(&self) -> Vec<ColumnStat>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ColumnStat {
+    pub reads: u64,
+    pub writes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// The size of the column on disk (in memory for the in-memory backend) in bytes, if known.
+    pub disk_size: Option<u64>,
+}
+
+/// The internal statistics of a storage engine, sampled from its tickers.
+pub trait EngineStats {
+    /// The statistics since the previous call.
+    fn engine_stats(&self) -> EngineStat;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EngineStat {
+    pub compact_read_bytes: u64,
+    pub compact_write_bytes: u64,
+    pub flush_write_bytes: u64,
+    pub wal_bytes: u64,
+    /// The bytes of the keys and the values written by the user.
+    pub bytes_written: u64,
+    pub block_cache_hits: u64,
+    pub block_cache_misses: u64,
+    /// The reads skipping a file by the bloom filter.
+    pub bloom_filter_useful: u64,
+    pub stall_micros: u64,
+}
+
+impl EngineStat {
+    /// The bytes written to the device, by the WAL, the flushes and the compactions, per byte
+    /// written by the user.
+    pub fn device_write_amp(&self) -> f64 {
+        let device_bytes = self.wal_bytes + self.flush_write_bytes + self.compact_write_bytes;
+        device_bytes as f64 / self.bytes_written as f64
+    }
+
+    pub fn block_cache_hit_rate(&self) -> f64 {
+        let accesses = self.block_cache_hits + self.block_cache_misses;
+        self.block_cache_hits as f64 / accesses as f64
+    }
+}
+
+/// The statistics of a value cache in front of the backend.
+pub trait CacheStats {
+    /// The hits and the misses since the previous call, with the current bytes in the cache.
+    fn cache_stats(&self) -> CacheStat;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheStat {
+    pub hits: u64,
+    pub misses: u64,
+    pub bytes: u64,
+}
+
 pub trait CounterTrait {
     fn reset(&mut self) {}
     fn report(&mut self) -> String {
@@ -1,5 +1,8 @@
 mod counter;
 mod profiler;
 
-pub use counter::{Counter, CounterTrait, Reporter};
+pub use counter::{
+    CacheStat, CacheStats, ColumnStat, ColumnStats, Counter, CounterTrait, EngineStat, EngineStats,
+    ModeledTime, Reporter,
+};
 pub use profiler::Profiler;
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use asb_backend::{Fault, FaultInjectionDB};
+use asb_options::{AuthAlgo, Options, StructOpt};
+use asb_tasks::Event;
+use kvdb::KeyValueDB;
+
+type Writes = Vec<(Vec<u8>, Vec<u8>)>;
+
+// The trace of a run without fault.
+struct Reference {
+    // The number of buffered operations and flushes before each epoch and after the last one.
+    ops: Vec<u64>,
+    flushes: Vec<u64>,
+    // The root after each epoch.
+    roots: Vec<Option<Vec<u8>>>,
+}
+
+struct Outcome {
+    // The last epoch whose operations were all written by a flush finished before the crash.
+    durable: Option<usize>,
+    // The epoch during which the crash happens.
+    crashed: Option<usize>,
+}
+
+fn fresh_backend(opts: &Options) -> Arc<dyn KeyValueDB> {
+    let _ = fs::remove_dir_all(&opts.db_dir);
+    fs::create_dir_all(&opts.db_dir).unwrap();
+    asb_backend::backend(opts).0
+}
+
+fn run(opts: &Options, db: Arc<FaultInjectionDB>, epochs: &[Writes]) -> (Reference, Outcome) {
+    let (mut authdb, _reporter) = asb_authdb::new(db.clone(), opts);
+    let mut reference = Reference {
+        ops: vec![],
+        flushes: vec![],
+        roots: vec![],
+    };
+    let mut outcome = Outcome {
+        durable: None,
+        crashed: None,
+    };
+    // The number of buffered operations after each epoch finished.
+    let mut epoch_ops = vec![];
+    for (epoch, writes) in epochs.iter().enumerate() {
+        reference.ops.push(db.ops());
+        reference.flushes.push(db.flushes());
+        for (key, value) in writes {
+            authdb.set(key.clone(), value.clone());
+        }
+        authdb.commit(epoch);
+        if db.crashed() {
+            outcome.crashed = Some(epoch);
+            break;
+        }
+        reference.roots.push(authdb.root());
+        epoch_ops.push(db.ops());
+    }
+    reference.ops.push(db.ops());
+    reference.flushes.push(db.flushes());
+
+    // An epoch is durable once a flush finished after its last buffered operation, as the
+    // algorithm may leave the writes of its commit buffered.
+    let durable_ops = db.durable_ops();
+    outcome.durable = epoch_ops.iter().rposition(|&ops| ops <= durable_ops);
+    (reference, outcome)
+}
+
+// Reopens the algorithm on the surviving data and compares it with the state of the last durable
+// epoch.
+fn check(
+    opts: &Options,
+    inner: Arc<dyn KeyValueDB>,
+    epochs: &[Writes],
+    reference: &Reference,
+    outcome: &Outcome,
+) -> Result<(), String> {
+    let (authdb, _reporter) = catch_unwind(AssertUnwindSafe(|| asb_authdb::new(inner, opts)))
+        .map_err(|_| "cannot reopen".to_string())?;
+
+    let durable_epochs = outcome.durable.map_or(0, |epoch| epoch + 1);
+    if let Some(epoch) = outcome.durable {
+        let (expected, actual) = (&reference.roots[epoch], authdb.root());
+        if expected != &actual {
+            return Err(format!("root {:?}, expected {:?}", actual, expected));
+        }
+    }
+
+    let mut expected = HashMap::new();
+    for writes in &epochs[..durable_epochs] {
+        expected.extend(writes.iter().cloned());
+    }
+    let touched = outcome.crashed.map_or(epochs.len(), |epoch| epoch + 1);
+    for (key, _) in epochs[..touched].iter().flatten() {
+        let actual = catch_unwind(AssertUnwindSafe(|| authdb.get(key.clone())))
+            .map_err(|_| format!("cannot read key {:?}", key))?;
+        if actual.as_deref() != expected.get(key).map(Vec::as_slice) {
+            return Err(format!("wrong value of key {:?}", key));
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let options: Options = Options::from_args();
+    if options.algorithm == AuthAlgo::LMPTS {
+        panic!("LMPTs can not change backend")
+    }
+    let num_epochs = options.max_epoch.unwrap_or(10);
+
+    let tasks = asb_tasks::tasks(&options);
+    let epochs: Vec<Writes> = tasks
+        .tasks()
+        .take(num_epochs)
+        .map(|events| {
+            let writes = events.0.into_iter().filter_map(|event| match event {
+                Event::Write(key, value) => Some((key, value)),
+                Event::Read(_) => None,
+            });
+            writes.collect()
+        })
+        .collect();
+
+    println!(
+        "Crash testing {:?} on {:?} with {} epochs",
+        options.algorithm,
+        options.backend,
+        epochs.len()
+    );
+    let num_cols = options.num_cols();
+    let db = Arc::new(FaultInjectionDB::new(fresh_backend(&options), num_cols, None));
+    let (reference, _) = run(&options, db, &epochs);
+
+    // Crash in the middle of the operations of each epoch, at its first flush, and amid its first
+    // flush.
+    let mut faults = vec![];
+    for epoch in 0..epochs.len() {
+        let (ops, next_ops) = (reference.ops[epoch], reference.ops[epoch + 1]);
+        if next_ops > ops {
+            faults.push(Fault::AtOp((ops + next_ops) / 2 + 1));
+        }
+        let (flushes, next_flushes) = (reference.flushes[epoch], reference.flushes[epoch + 1]);
+        if next_flushes > flushes {
+            faults.push(Fault::AtFlush(flushes + 1));
+            faults.push(Fault::TruncateFlush {
+                flush: flushes + 1,
+                ops: 1,
+            });
+        }
+    }
+
+    let mut failures = 0;
+    for fault in faults {
+        let inner = fresh_backend(&options);
+        let db = Arc::new(FaultInjectionDB::new(inner.clone(), num_cols, Some(fault)));
+        let (_, outcome) = run(&options, db, &epochs);
+        if outcome.crashed.is_none() {
+            println!("{:?}: not triggered", fault);
+            continue;
+        }
+
+        let result = check(&options, inner, &epochs, &reference, &outcome);
+        match result {
+            Ok(()) => println!(
+                "{:?}: crashed in epoch {:?}, recovered to epoch {:?}",
+                fault, outcome.crashed, outcome.durable
+            ),
+            Err(e) => {
+                failures += 1;
+                println!(
+                    "{:?}: crashed in epoch {:?}, FAILED to recover to epoch {:?}: {}",
+                    fault, outcome.crashed, outcome.durable, e
+                );
+            }
+        }
+    }
+
+    if failures > 0 {
+        println!("{} crash points failed", failures);
+        std::process::exit(1);
+    }
+    println!("All crash points recovered");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each epoch writes the same keys with new values.
+    fn epochs() -> Vec<Writes> {
+        let writes = |epoch: u8| -> Writes { (0..8u8).map(|i| (vec![i], vec![epoch, i])).collect() };
+        (0..4).map(writes).collect()
+    }
+
+    fn crash(opts: &Options, epochs: &[Writes], fault: Fault) -> (Arc<dyn KeyValueDB>, Outcome) {
+        let inner = asb_backend::backend(opts).0;
+        let db = Arc::new(FaultInjectionDB::new(inner.clone(), opts.num_cols(), Some(fault)));
+        let (_, outcome) = run(opts, db, epochs);
+        (inner, outcome)
+    }
+
+    #[test]
+    fn raw_recovers_to_last_flush() {
+        let opts = Options::from_iter(["asb-crash", "-a", "raw", "-b", "memory"]);
+        let epochs = epochs();
+        let db = FaultInjectionDB::new(asb_backend::backend(&opts).0, opts.num_cols(), None);
+        let (reference, _) = run(&opts, Arc::new(db), &epochs);
+        // The raw algorithm flushes once in each commit.
+        assert_eq!(reference.flushes, vec![0, 1, 2, 3, 4]);
+
+        // In the middle of epoch 2, and at its flush.
+        for fault in [Fault::AtOp(2 * 8 + 4), Fault::AtFlush(3)] {
+            let (inner, outcome) = crash(&opts, &epochs, fault);
+            assert_eq!((outcome.crashed, outcome.durable), (Some(2), Some(1)));
+            check(&opts, inner, &epochs, &reference, &outcome).unwrap();
+        }
+
+        // The torn flush leaves one write of epoch 2, which is caught as the raw algorithm has
+        // no journal to recover from.
+        let (inner, outcome) = crash(&opts, &epochs, Fault::TruncateFlush { flush: 3, ops: 1 });
+        assert_eq!((outcome.crashed, outcome.durable), (Some(2), Some(1)));
+        assert!(check(&opts, inner, &epochs, &reference, &outcome).is_err());
+    }
+}
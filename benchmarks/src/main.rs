@@ -39,8 +39,26 @@ fn main() {
         fs::create_dir_all(dir).unwrap()
     }
 
+    if let Some(ref trace) = options.replay_trace {
+        let (backend, instruments) = asb_backend::backend(&options);
+        run::replay_trace(trace, backend, instruments.clock, &options);
+        return;
+    }
+
     let tasks = asb_tasks::tasks(&options);
-    let backend = asb_backend::backend(&options);
-    let (db, reporter) = asb_authdb::new(backend, &options);
+    let (backend, instruments) = asb_backend::backend(&options);
+    let (db, mut reporter) = asb_authdb::new(backend, &options);
+    if let Some(clock) = instruments.clock {
+        reporter.set_modeled_time(clock);
+    }
+    if let Some(columns) = instruments.columns {
+        reporter.set_column_stats(columns);
+    }
+    if let Some(engine) = instruments.engine {
+        reporter.set_engine_stats(engine);
+    }
+    if let Some(cache) = instruments.cache {
+        reporter.set_cache_stats(cache);
+    }
     run_tasks(db, tasks, reporter, &options);
 }
@@ -1,9 +1,9 @@
 use asb_options::Options;
-use asb_profile::{Profiler, Reporter};
+use asb_profile::{ModeledTime, Profiler, Reporter};
 use asb_tasks::{Event, Events, TaskTrait};
 use authdb::AuthDB;
 use fs_extra::dir::CopyOptions;
-use kvdb::IoStatsKind;
+use kvdb::{IoStatsKind, KeyValueDB};
 use std::fs;
 use std::sync::Arc;
 use std::thread::sleep;
@@ -117,3 +117,35 @@ pub fn run_tasks(
 
     reporter.collect_profiling(profiler);
 }
+
+pub fn replay_trace(
+    path: &str,
+    backend: Arc<dyn KeyValueDB>,
+    clock: Option<Arc<dyn ModeledTime>>,
+    opts: &Options,
+) {
+    println!("Replaying I/O trace {}", path);
+    let time = Instant::now();
+    let summary = asb_backend::replay(path, &*backend, opts.num_cols())
+        .unwrap_or_else(|e| panic!("Cannot replay the trace {}: {}", path, e));
+    let elapsed = time.elapsed();
+
+    println!(
+        "Time {:>7.3?}s, {} gets, {} writes in {} transactions, {} flushes",
+        elapsed.as_secs_f64(),
+        summary.gets,
+        summary.writes,
+        summary.transactions,
+        summary.flushes
+    );
+    if let Some(clock) = clock {
+        println!("Modeled I/O time {:>7.3?}s", clock.io_time().as_secs_f64());
+    }
+    if summary.mismatched_gets > 0 {
+        println!(
+            "Warning: {} gets differ in the presence of the key from the trace. The replay may not \
+             start from the recorded state.",
+            summary.mismatched_gets
+        );
+    }
+}